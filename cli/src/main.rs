@@ -1,13 +1,36 @@
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::io::{BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 
-use clap::{Parser, Subcommand};
-use flight_data_reader::configuration::RocketConfig;
-use flight_data_reader::csv::CsvGenerator;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use flight_data_reader::analysis::altitude::{AltitudeEstimator, AltitudeOptions};
+use flight_data_reader::analysis::events::{EventDetector, EventOptions};
+use flight_data_reader::analysis::kalman::KalmanFilter;
+use flight_data_reader::analysis::orientation::{
+    FusionAlgorithm, OrientationEstimator, OrientationOptions,
+};
+use flight_data_reader::analysis::rate::{RateAnalysis, RateOptions, TimeBase};
+use flight_data_reader::analysis::Channel;
+use flight_data_reader::cbor::{CborGenerator, CborReader};
+use flight_data_reader::codegen::{write_c_header, write_rust_encoder};
+use flight_data_reader::configuration::format::ConfigFormat;
+use flight_data_reader::configuration::{
+    json_schema, Endianess, FilterConfig, RocketConfig, ValueRole,
+};
+use flight_data_reader::csv::{CsvGenerator, CsvOptions, FloatFormat, QuotePolicy};
 use flight_data_reader::data::PacketParser;
-use flight_data_reader::report::Report;
-use flight_data_reader::result_table::TableGenerator;
+use flight_data_reader::gps::{TrackGenerator, TrackOptions};
+use flight_data_reader::import::config_from_c_header;
+use flight_data_reader::influx::{InfluxGenerator, InfluxOptions};
+use flight_data_reader::load_config;
+use flight_data_reader::matlab::MatGenerator;
+use flight_data_reader::openrocket::{OpenRocketGenerator, OpenRocketOptions};
+use flight_data_reader::report::{Report, ReportOptions};
+use flight_data_reader::result_table::assembly::{PrimarySensor, RowPerPacket, TimeWindow};
+use flight_data_reader::result_table::resample::{ResampleOptions, ResamplePolicy, Resampler};
+use flight_data_reader::result_table::{FillPolicy, RowSource, SourceIterator, TableGenerator};
+use flight_data_reader::xlsx::XlsxGenerator;
 
 #[derive(Parser)]
 struct Cli {
@@ -23,15 +46,24 @@ enum Action {
         config: PathBuf,
     },
     Convert {
+        /// The input format, either `bin` for data from the flight computer or
+        /// `cbor` for an archive.
+        #[clap(short, long, default_value = "bin")]
+        from: String,
+        /// The output format, one of `csv`, `mat`, `influx`, `gpx`, `kml`,
+        /// `xlsx`, `openrocket` or `cbor`.
         #[clap(short, long, default_value = "csv")]
         to: String,
-        /// The location of the config file.
+        /// The location of the config file. Not needed for archives or data
+        /// with an embedded config.
         #[clap(short, long)]
-        config: PathBuf,
+        config: Option<PathBuf>,
         /// The encoded file from the flight computer.
         data: PathBuf,
         /// The location to write the decoded data to.
         output: PathBuf,
+        #[clap(flatten)]
+        formats: FormatArgs,
     },
     Report {
         /// The location of the config file.
@@ -41,15 +73,517 @@ enum Action {
         data: PathBuf,
         /// The location to write latex report to.
         output: PathBuf,
+        /// The percentiles to estimate for every value, between 0 and 100.
+        #[clap(
+            long,
+            value_delimiter = ',',
+            value_parser = parse_percentile,
+            default_values_t = ReportOptions::default().percentiles
+        )]
+        percentiles: Vec<f64>,
+        #[clap(flatten)]
+        rates: RateArgs,
+        #[clap(flatten)]
+        events: EventArgs,
+        #[clap(flatten)]
+        orientation: OrientationArgs,
+        #[clap(flatten)]
+        filters: FilterArgs,
+    },
+    /// Print the data rate and dropouts of every sensor.
+    Stats {
+        /// The location of the config file. Not needed for data with an
+        /// embedded config.
+        #[clap(short, long)]
+        config: Option<PathBuf>,
+        /// The encoded file from the flight computer.
+        data: PathBuf,
+        #[clap(flatten)]
+        rates: RateArgs,
+    },
+    /// Detect the events of the flight, such as launch and apogee.
+    Events {
+        /// The location of the config file. Not needed for data with an
+        /// embedded config.
+        #[clap(short, long)]
+        config: Option<PathBuf>,
+        /// The encoded file from the flight computer.
+        data: PathBuf,
+        /// Print the events as JSON.
+        #[clap(long)]
+        json: bool,
+        #[clap(flatten)]
+        events: EventArgs,
+    },
+    Codegen {
+        /// The language to generate, either `c` for a header or `rust` for
+        /// code using the `flight_data_encoder` crate.
+        #[clap(short, long, default_value = "c")]
+        language: String,
+        /// The location of the config file.
+        #[clap(short, long)]
+        config: PathBuf,
+        /// The location to write the generated code to.
+        output: PathBuf,
+    },
+    Import {
+        /// The name of the rocket, the name of the header file by default.
+        #[clap(short, long)]
+        name: Option<String>,
+        /// The firmware writes values in little endian.
+        #[clap(long)]
+        little_endian: bool,
+        /// The C header defining the packets.
+        header: PathBuf,
+        /// The location to write the config file to.
+        output: PathBuf,
+    },
+    Schema {
+        /// The location to write the JSON Schema of config files to, or the
+        /// standard output if not given.
+        output: Option<PathBuf>,
     },
+    Config {
+        #[clap(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Translate a config file to another format, with its includes resolved.
+    Convert {
+        /// The output format, one of `json`, `yaml` or `toml`. By default it
+        /// is chosen from the extension of the output file.
+        #[clap(short, long)]
+        to: Option<String>,
+        /// The config file to translate.
+        input: PathBuf,
+        /// The location to write the translated config to.
+        output: PathBuf,
+    },
+}
+
+/// Options for the data rate analysis of sensors.
+#[derive(Args)]
+struct RateArgs {
+    /// How many times longer than the nominal period of a sensor a gap
+    /// between packets must be to count as a dropout.
+    #[clap(long, default_value_t = RateOptions::default().gap_factor)]
+    gap_factor: f64,
+}
+
+impl From<RateArgs> for RateOptions {
+    fn from(args: RateArgs) -> Self {
+        RateOptions {
+            gap_factor: args.gap_factor,
+        }
+    }
+}
+
+/// Options for detecting flight events.
+#[derive(Args)]
+struct EventArgs {
+    /// The acceleration towards the nose as `sensor.value`, instead of the
+    /// value found from roles. Start it with `-` if the value is positive
+    /// towards the tail.
+    #[clap(long, allow_hyphen_values = true)]
+    acceleration: Option<String>,
+    /// The pressure as `sensor.value`, instead of the value found from roles.
+    #[clap(long)]
+    pressure: Option<String>,
+    /// How much the acceleration must be above 1 g for launch, in m/s².
+    #[clap(long, default_value_t = EventOptions::default().launch_acceleration)]
+    launch_acceleration: f64,
+    /// How far above the ground the rocket must be for launch, in metres.
+    #[clap(long, default_value_t = EventOptions::default().launch_altitude)]
+    launch_altitude: f64,
+    /// The size of the jolt from a parachute opening, in m/s².
+    #[clap(long, default_value_t = EventOptions::default().deployment_acceleration)]
+    deployment_acceleration: f64,
+    /// How long the launch and burnout conditions must hold, in seconds.
+    #[clap(long, default_value_t = EventOptions::default().debounce)]
+    debounce: f64,
+}
+
+impl EventArgs {
+    fn options(self, config: &RocketConfig) -> Result<EventOptions, String> {
+        let acceleration = match self.acceleration {
+            Some(name) => {
+                let (name, sign) = match name.strip_prefix('-') {
+                    Some(name) => (name.to_string(), -1.0),
+                    None => (name, 1.0),
+                };
+                let mut channel = Channel::from_name(config, &name, ValueRole::AccelerationX)
+                    .ok_or_else(|| format!("No acceleration value named {name}"))?;
+                channel.scale *= sign;
+                Some(channel)
+            }
+            None => None,
+        };
+        let pressure = match self.pressure {
+            Some(name) => Some(
+                Channel::from_name(config, &name, ValueRole::Pressure)
+                    .ok_or_else(|| format!("No pressure value named {name}"))?,
+            ),
+            None => None,
+        };
+
+        Ok(EventOptions {
+            acceleration,
+            pressure,
+            launch_acceleration: self.launch_acceleration,
+            launch_altitude: self.launch_altitude,
+            deployment_acceleration: self.deployment_acceleration,
+            debounce: self.debounce,
+            ..Default::default()
+        })
+    }
+}
+
+/// Options for estimating the orientation of the rocket.
+#[derive(Args)]
+struct OrientationArgs {
+    /// The gyroscope as three `sensor.value` names for the X, Y and Z axes,
+    /// separated by commas, instead of the values found from roles.
+    #[clap(long, value_delimiter = ',', num_args = 3)]
+    gyro: Option<Vec<String>>,
+    /// The accelerometer as three `sensor.value` names, like `--gyro`. A
+    /// gravity vector from the sensor can be given instead.
+    #[clap(long, value_delimiter = ',', num_args = 3)]
+    accelerometer: Option<Vec<String>>,
+    /// The magnetometer as three `sensor.value` names, like `--gyro`.
+    #[clap(long, value_delimiter = ',', num_args = 3)]
+    magnetometer: Option<Vec<String>>,
+    /// The filter used to fuse the sensors.
+    #[clap(long, value_enum, default_value_t = FusionArg::Madgwick)]
+    fusion: FusionArg,
+    /// How strongly the accelerometer and magnetometer correct the gyroscope.
+    #[clap(long, default_value_t = OrientationOptions::default().gain)]
+    fusion_gain: f64,
+    /// The tilt from vertical above which the rocket has tumbled, in degrees.
+    #[clap(long, default_value_t = OrientationOptions::default().tumble_tilt)]
+    tumble_tilt: f64,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FusionArg {
+    Madgwick,
+    Mahony,
+}
+
+impl OrientationArgs {
+    fn options(self, config: &RocketConfig) -> Result<OrientationOptions, String> {
+        let vector = |names: Option<Vec<String>>, role: ValueRole, kind: &str| {
+            let Some(names) = names else {
+                return Ok(None);
+            };
+            let mut channels = names.iter().map(|name| {
+                Channel::from_name(config, name, role)
+                    .ok_or_else(|| format!("No {kind} value named {name}"))
+            });
+            let (Some(x), Some(y), Some(z)) = (channels.next(), channels.next(), channels.next())
+            else {
+                return Err(format!("The {kind} needs three values"));
+            };
+            Ok(Some([x?, y?, z?]))
+        };
+
+        Ok(OrientationOptions {
+            gyro: vector(self.gyro, ValueRole::AngularVelocityX, "gyroscope")?,
+            acceleration: vector(
+                self.accelerometer,
+                ValueRole::AccelerationX,
+                "accelerometer",
+            )?,
+            magnetic_field: vector(self.magnetometer, ValueRole::MagneticFieldX, "magnetometer")?,
+            algorithm: match self.fusion {
+                FusionArg::Madgwick => FusionAlgorithm::Madgwick,
+                FusionArg::Mahony => FusionAlgorithm::Mahony,
+            },
+            gain: self.fusion_gain,
+            tumble_tilt: self.tumble_tilt,
+            ..Default::default()
+        })
+    }
+}
+
+/// Options for every output format of the convert command.
+#[derive(Args)]
+struct FormatArgs {
+    /// The time the log started, in nanoseconds since the Unix epoch.
+    ///
+    /// InfluxDB output uses the current time if this is not given.
+    #[clap(long)]
+    start_time: Option<i64>,
+    #[clap(flatten)]
+    csv: CsvArgs,
+    #[clap(flatten)]
+    influx: InfluxArgs,
+    #[clap(flatten)]
+    track: TrackArgs,
+    #[clap(flatten)]
+    openrocket: OpenRocketArgs,
+    #[clap(flatten)]
+    filters: FilterArgs,
+    #[clap(flatten)]
+    resample: ResampleArgs,
+    #[clap(flatten)]
+    rows: RowArgs,
+}
+
+/// Filters applied to values, in addition to those in the config.
+#[derive(Args)]
+struct FilterArgs {
+    /// A filter for a value as `sensor.value=filter`, where the filter is its
+    /// type followed by its parameters, such as
+    /// `BMP.pressure=low_pass,cutoff=5,sample_rate=100`. Can be repeated, and
+    /// the filters are applied after those in the config. Filters are only
+    /// applied to reports and to csv and xlsx output.
+    #[clap(long = "filter")]
+    filters: Vec<String>,
+}
+
+impl FilterArgs {
+    fn apply(&self, config: &mut RocketConfig) -> Result<(), String> {
+        for filter in self.filters.iter() {
+            let Some((name, filter)) = filter.split_once('=') else {
+                return Err(format!("Expected sensor.value=filter: {filter}"));
+            };
+            let filter = filter.parse::<FilterConfig>()?;
+
+            let value = name.split_once('.').and_then(|(sensor_name, value_name)| {
+                config
+                    .sensors
+                    .iter_mut()
+                    .find(|s| s.name == sensor_name)?
+                    .values
+                    .iter_mut()
+                    .find(|v| v.name == value_name)
+            });
+            let Some(value) = value else {
+                return Err(format!("No value named {name}"));
+            };
+            value.filters.push(filter);
+        }
+
+        Ok(())
+    }
+}
+
+/// Options for how CSV rows are assembled from packets and filled.
+#[derive(Args)]
+struct RowArgs {
+    /// Write a row for every packet.
+    #[clap(long, conflicts_with_all = ["resample", "primary_sensor", "row_window"])]
+    row_per_packet: bool,
+    /// Write a row for every packet of this sensor, with the most recent
+    /// values of the others.
+    #[clap(long, value_name = "SENSOR", conflicts_with_all = ["resample", "row_window"])]
+    primary_sensor: Option<String>,
+    /// Write a row for every window of this many seconds with packets.
+    #[clap(long, value_name = "SECONDS", conflicts_with = "resample")]
+    row_window: Option<f64>,
+    /// Repeat the last value of a column in rows where it was not sampled.
+    #[clap(long, conflicts_with = "resample")]
+    fill_forward: bool,
+    /// Only repeat values sampled at most this many rows before.
+    #[clap(long, value_name = "ROWS", conflicts_with = "resample")]
+    fill_max_age: Option<u64>,
+    /// Add a column for each column that is 1 in rows where it was sampled.
+    #[clap(long, conflicts_with = "resample")]
+    updated_columns: bool,
+}
+
+impl RowArgs {
+    fn apply<I: SourceIterator>(
+        &self,
+        table: &mut TableGenerator<I>,
+        config: &RocketConfig,
+    ) -> Result<(), String> {
+        if self.row_per_packet {
+            table.set_assembly(RowPerPacket);
+        }
+        if let Some(name) = &self.primary_sensor {
+            let Some(sensor) = config.sensors.iter().find(|s| &s.name == name) else {
+                return Err(format!("No sensor named {name}"));
+            };
+            table.set_assembly(PrimarySensor::new(sensor.id));
+        }
+        if let Some(duration) = self.row_window {
+            table.set_assembly(TimeWindow::new(config, duration)?);
+        }
+
+        table.set_fill_policy(self.fill_policy());
+        if self.updated_columns {
+            table.add_updated_columns();
+        }
+
+        Ok(())
+    }
+
+    fn fill_policy(&self) -> FillPolicy {
+        match (self.fill_forward, self.fill_max_age) {
+            (_, Some(max_age)) => FillPolicy::ForwardFillMaxAge(max_age),
+            (true, None) => FillPolicy::ForwardFill,
+            (false, None) => FillPolicy::None,
+        }
+    }
+}
+
+/// Options for resampling CSV output to a fixed rate.
+#[derive(Args)]
+struct ResampleArgs {
+    /// Write rows at this rate in Hz instead of as packets arrive.
+    #[clap(long, value_name = "HZ", conflicts_with = "estimate")]
+    resample: Option<f64>,
+    /// How values are found at the time of each row: hold, linear, nearest
+    /// or mean.
+    #[clap(long, default_value = "hold")]
+    resample_policy: ResamplePolicy,
+    /// The policy of a single column as `column=policy`, such as
+    /// `BMP_pressure=linear`. Can be repeated.
+    #[clap(long = "column-policy")]
+    column_policies: Vec<String>,
+    /// The longest time in seconds between a row and the samples used for it
+    /// before the cell is left empty. Required with the linear and nearest
+    /// policies.
+    #[clap(long, value_name = "SECONDS")]
+    max_staleness: Option<f64>,
+}
+
+impl ResampleArgs {
+    fn options(&self) -> Result<Option<ResampleOptions>, String> {
+        let Some(rate) = self.resample else {
+            return Ok(None);
+        };
+
+        let mut column_policies = HashMap::new();
+        for policy in self.column_policies.iter() {
+            let Some((column, policy)) = policy.split_once('=') else {
+                return Err(format!("Expected column=policy: {policy}"));
+            };
+            column_policies.insert(column.to_string(), policy.parse()?);
+        }
+
+        Ok(Some(ResampleOptions {
+            rate,
+            policy: self.resample_policy,
+            column_policies,
+            max_staleness: self.max_staleness,
+        }))
+    }
+}
+
+/// Options controlling the dialect of CSV output.
+#[derive(Args)]
+struct CsvArgs {
+    /// The character placed between fields.
+    #[clap(long, default_value_t = ',')]
+    delimiter: char,
+    /// When fields are wrapped in quotes.
+    #[clap(long, value_enum, default_value_t = QuoteArg::Minimal)]
+    quote: QuoteArg,
+    /// Write rows with `\r\n` instead of `\n`.
+    #[clap(long)]
+    crlf: bool,
+    /// The number of decimal places written for floats.
+    #[clap(long, default_value_t = 8, conflicts_with = "shortest")]
+    precision: usize,
+    /// Write floats with the shortest representation that round trips.
+    #[clap(long)]
+    shortest: bool,
+    /// The text written for a floating point NaN.
+    #[clap(long)]
+    nan: Option<String>,
+    /// The text written for a cell with no value.
+    #[clap(long, default_value = "")]
+    empty: String,
+    /// Do not write the header row of column names.
+    #[clap(long)]
+    no_header: bool,
+    /// Add columns with the altitude from the barometer, the altitude,
+    /// velocity and acceleration estimated by a Kalman filter, and the
+    /// orientation from the gyroscope.
+    #[clap(long)]
+    estimate: bool,
+}
+
+/// Options for InfluxDB line protocol output.
+#[derive(Args)]
+struct InfluxArgs {
+    /// The value of the `flight` tag.
+    #[clap(long)]
+    flight_id: Option<String>,
+}
+
+/// Options for GPX and KML output.
+#[derive(Args)]
+struct TrackArgs {
+    /// The lowest GPS fix quality included in the track.
+    #[clap(long, default_value_t = 1.0)]
+    min_fix: f64,
+}
+
+/// Options for OpenRocket output.
+#[derive(Args)]
+struct OpenRocketArgs {
+    /// The accelerometer already removes gravity from its readings.
+    #[clap(long)]
+    linear_acceleration: bool,
+}
+
+impl From<OpenRocketArgs> for OpenRocketOptions {
+    fn from(args: OpenRocketArgs) -> Self {
+        OpenRocketOptions {
+            subtract_gravity: !args.linear_acceleration,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum QuoteArg {
+    Minimal,
+    Always,
+    NonNumeric,
+    Never,
+}
+
+impl From<CsvArgs> for CsvOptions {
+    fn from(args: CsvArgs) -> Self {
+        CsvOptions {
+            delimiter: args.delimiter,
+            quoting: match args.quote {
+                QuoteArg::Minimal => QuotePolicy::Minimal,
+                QuoteArg::Always => QuotePolicy::Always,
+                QuoteArg::NonNumeric => QuotePolicy::NonNumeric,
+                QuoteArg::Never => QuotePolicy::Never,
+            },
+            line_terminator: if args.crlf { "\r\n" } else { "\n" }.to_string(),
+            float_format: if args.shortest {
+                FloatFormat::Shortest
+            } else {
+                FloatFormat::Fixed(args.precision)
+            },
+            nan_value: args.nan,
+            empty_value: args.empty,
+            header: !args.no_header,
+        }
+    }
 }
 
 impl Action {
-    pub fn config(&self) -> &Path {
+    pub fn config(&self) -> Option<&Path> {
         match self {
-            Action::Check { config } => config,
-            Action::Convert { config, .. } => config,
-            Action::Report { config, .. } => config,
+            Action::Check { config } => Some(config),
+            Action::Convert { config, .. } => config.as_deref(),
+            Action::Report { config, .. } => Some(config),
+            Action::Stats { config, .. } => config.as_deref(),
+            Action::Events { config, .. } => config.as_deref(),
+            Action::Codegen { config, .. } => Some(config),
+            Action::Import { .. } => None,
+            Action::Schema { .. } => None,
+            Action::Config { .. } => None,
         }
     }
 }
@@ -57,7 +591,7 @@ impl Action {
 fn main() {
     let args = Cli::parse();
 
-    let config = match load_config(args.action.config()) {
+    let config = match args.action.config().map(load_config).transpose() {
         Ok(config) => config,
         Err(e) => {
             eprintln!("Could not load config: {e}");
@@ -66,22 +600,252 @@ fn main() {
     };
 
     match args.action {
-        Action::Check { .. } => check_config(config),
+        Action::Check { .. } => check_config(config.unwrap()),
         Action::Convert {
-            to, data, output, ..
-        } => convert_data(config, to, data, output),
-        Action::Report { data, output, .. } => generate_report(config, data, output),
+            from,
+            to,
+            data,
+            output,
+            formats,
+            ..
+        } => convert_data(config, from, to, data, output, formats),
+        Action::Report {
+            data,
+            output,
+            percentiles,
+            rates,
+            events,
+            orientation,
+            filters,
+            ..
+        } => {
+            let mut config = config.unwrap();
+            if let Err(e) = filters.apply(&mut config) {
+                eprintln!("Invalid filter: {e}");
+                return;
+            }
+            let events = match events.options(&config) {
+                Ok(events) => events,
+                Err(e) => {
+                    eprintln!("Invalid event options: {e}");
+                    return;
+                }
+            };
+            let orientation = match orientation.options(&config) {
+                Ok(orientation) => orientation,
+                Err(e) => {
+                    eprintln!("Invalid orientation options: {e}");
+                    return;
+                }
+            };
+            let altitude = AltitudeOptions {
+                pressure: events.pressure,
+                launch_altitude: events.launch_altitude,
+                ..Default::default()
+            };
+            let options = ReportOptions {
+                percentiles,
+                rates: rates.into(),
+                events,
+                altitude,
+                orientation,
+            };
+            generate_report(config, data, output, options)
+        }
+        Action::Stats { data, rates, .. } => print_stats(config, data, rates.into()),
+        Action::Events {
+            data, json, events, ..
+        } => print_events(config, data, json, events),
+        Action::Codegen {
+            language, output, ..
+        } => generate_code(config.unwrap(), language, output),
+        Action::Import {
+            name,
+            little_endian,
+            header,
+            output,
+        } => import_c_header(name, little_endian, header, output),
+        Action::Schema { output } => write_schema(output),
+        Action::Config {
+            action: ConfigAction::Convert { to, input, output },
+        } => convert_config(to, input, output),
     }
 }
 
-fn convert_data(config: RocketConfig, to: String, data: PathBuf, output: PathBuf) {
+fn convert_data(
+    config: Option<RocketConfig>,
+    from: String,
+    to: String,
+    data: PathBuf,
+    output: PathBuf,
+    formats: FormatArgs,
+) {
     let input_reader = File::open(data).unwrap();
-    let packet_parser = PacketParser::new(input_reader, config.clone());
-    let mut csv_gen = CsvGenerator::new(packet_parser, config.clone());
 
+    match (from.as_str(), config) {
+        ("bin", config) => {
+            let Some(packet_parser) = open_packets(input_reader, config) else {
+                return;
+            };
+            let config = packet_parser.config().clone();
+            convert_packets(config, packet_parser, to, output, formats);
+        }
+        ("cbor", config) => {
+            let archive_reader = match CborReader::new(input_reader) {
+                Ok(archive_reader) => archive_reader,
+                Err(e) => {
+                    eprintln!("Could not read archive: {e}");
+                    return;
+                }
+            };
+            if config.is_some() {
+                eprintln!("Ignoring config file, using the config in the archive");
+            }
+            let config = archive_reader.config().clone();
+            convert_packets(config, archive_reader, to, output, formats);
+        }
+        _ => eprintln!("Unsupported input format: {from}"),
+    }
+}
+
+/// Open a log of packets, printing why it can't be read and warning if the
+/// config doesn't match the one the log was written with.
+fn open_packets<R: Read>(reader: R, config: Option<RocketConfig>) -> Option<PacketParser<R>> {
+    let packet_parser = match PacketParser::open(reader, config) {
+        Ok(packet_parser) => packet_parser,
+        Err(e) => {
+            eprintln!("Could not read data: {e}");
+            return None;
+        }
+    };
+
+    if let (Some(false), Some(header)) = (packet_parser.config_matches(), packet_parser.header()) {
+        eprintln!(
+            "Warning: the config does not match the log: fingerprint {:016x}, log written with {:016x}",
+            packet_parser.config().fingerprint(),
+            header.fingerprint
+        );
+    }
+
+    Some(packet_parser)
+}
+
+fn convert_packets<I: SourceIterator>(
+    mut config: RocketConfig,
+    packet_parser: I,
+    to: String,
+    output: PathBuf,
+    formats: FormatArgs,
+) {
+    if let Err(e) = formats.filters.apply(&mut config) {
+        eprintln!("Invalid filter: {e}");
+        return;
+    }
+    let filtered = config
+        .sensors
+        .iter()
+        .flat_map(|sensor| sensor.values.iter())
+        .any(|value| !value.filters.is_empty());
+    if filtered && !matches!(to.as_str(), "csv" | "xlsx") {
+        eprintln!("Warning: filters are not applied to {to} output");
+    }
+    let resample = match formats.resample.options() {
+        Ok(resample) => resample,
+        Err(e) => {
+            eprintln!("Invalid resampling: {e}");
+            return;
+        }
+    };
+
+    let influx_options = InfluxOptions {
+        flight_id: formats.influx.flight_id,
+        start_time: formats
+            .start_time
+            .unwrap_or_else(|| InfluxOptions::default().start_time),
+    };
+    let track_options = TrackOptions {
+        min_fix_quality: formats.track.min_fix,
+        start_time: formats.start_time,
+    };
+
+    match to.as_str() {
+        "csv" => {
+            let estimate = formats.csv.estimate;
+            let csv_options = formats.csv.into();
+            match resample {
+                Some(resample) => {
+                    convert_resampled_csv(config, packet_parser, output, csv_options, resample)
+                }
+                None => convert_csv(
+                    config,
+                    packet_parser,
+                    output,
+                    csv_options,
+                    estimate,
+                    formats.rows,
+                ),
+            }
+        }
+        "mat" => convert_mat(config, packet_parser, output),
+        "influx" => convert_influx(config, packet_parser, output, influx_options),
+        "gpx" | "kml" => convert_track(config, packet_parser, output, &to, track_options),
+        "xlsx" => convert_xlsx(config, packet_parser, output),
+        "openrocket" => {
+            convert_openrocket(config, packet_parser, output, formats.openrocket.into())
+        }
+        "cbor" => convert_cbor(config, packet_parser, output),
+        _ => eprintln!("Unsupported output format: {to}"),
+    }
+}
+
+fn convert_csv<I: SourceIterator>(
+    config: RocketConfig,
+    packet_parser: I,
+    output: PathBuf,
+    csv_options: CsvOptions,
+    estimate: bool,
+    rows: RowArgs,
+) {
+    let mut table = TableGenerator::new(packet_parser, config.clone());
+    if let Err(e) = rows.apply(&mut table, &config) {
+        eprintln!("Invalid rows: {e}");
+        return;
+    }
+    if estimate {
+        match AltitudeEstimator::new(&config, Default::default()) {
+            Ok(estimator) => table.add_altitude(estimator),
+            Err(e) => eprintln!("Not adding the altitude: {e}"),
+        }
+        match KalmanFilter::new(&config, Default::default()) {
+            Ok(filter) => table.add_kalman(filter),
+            Err(e) => eprintln!("Not adding the Kalman filter estimates: {e}"),
+        }
+        match OrientationEstimator::new(&config, Default::default()) {
+            Ok(estimator) => table.add_orientation(estimator),
+            Err(e) => eprintln!("Not adding the orientation: {e}"),
+        }
+    }
+    write_csv(CsvGenerator::from_rows(table, csv_options), output);
+}
+
+fn convert_resampled_csv<I: SourceIterator>(
+    config: RocketConfig,
+    packet_parser: I,
+    output: PathBuf,
+    csv_options: CsvOptions,
+    resample: ResampleOptions,
+) {
+    match Resampler::new(packet_parser, config, resample) {
+        Ok(resampler) => write_csv(CsvGenerator::from_rows(resampler, csv_options), output),
+        Err(e) => eprintln!("Cannot resample: {e}"),
+    }
+}
+
+fn write_csv<R: RowSource>(csv_gen: CsvGenerator<R>, output: PathBuf) {
+    let line_terminator = csv_gen.options().line_terminator.clone();
     let mut output_writer = BufWriter::new(File::create(output).unwrap());
 
-    while let Some(line) = csv_gen.next() {
+    for line in csv_gen {
         let line = match line {
             Ok(line) => line,
             Err(e) => {
@@ -91,7 +855,110 @@ fn convert_data(config: RocketConfig, to: String, data: PathBuf, output: PathBuf
         };
 
         output_writer.write_all(line.as_bytes()).unwrap();
-        output_writer.write_all(b"\n").unwrap();
+        output_writer.write_all(line_terminator.as_bytes()).unwrap();
+    }
+}
+
+fn convert_mat<I: SourceIterator>(config: RocketConfig, packet_parser: I, output: PathBuf) {
+    let mat_gen = MatGenerator::new(packet_parser, config);
+
+    let mut output_writer = BufWriter::new(File::create(output).unwrap());
+
+    if let Err(e) = mat_gen.write_mat(&mut output_writer) {
+        eprintln!("Error while writing MAT-file: {e}");
+    }
+}
+
+fn convert_influx<I: SourceIterator>(
+    config: RocketConfig,
+    packet_parser: I,
+    output: PathBuf,
+    influx_options: InfluxOptions,
+) {
+    let influx_gen = InfluxGenerator::with_options(packet_parser, config, influx_options);
+
+    let mut output_writer = BufWriter::new(File::create(output).unwrap());
+
+    for line in influx_gen {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Error while parsing packet: {e}");
+                continue;
+            }
+        };
+
+        writeln!(output_writer, "{line}").unwrap();
+    }
+}
+
+fn convert_track<I: SourceIterator>(
+    config: RocketConfig,
+    packet_parser: I,
+    output: PathBuf,
+    to: &str,
+    track_options: TrackOptions,
+) {
+    let track_gen = match TrackGenerator::with_options(packet_parser, config, track_options) {
+        Ok(track_gen) => track_gen,
+        Err(e) => {
+            eprintln!("Could not generate track: {e}");
+            return;
+        }
+    };
+
+    let mut output_writer = BufWriter::new(File::create(output).unwrap());
+
+    let result = if to == "gpx" {
+        track_gen.write_gpx(&mut output_writer)
+    } else {
+        track_gen.write_kml(&mut output_writer)
+    };
+
+    if let Err(e) = result {
+        eprintln!("Error while writing track: {e}");
+    }
+}
+
+fn convert_xlsx<I: SourceIterator>(config: RocketConfig, packet_parser: I, output: PathBuf) {
+    let xlsx_gen = XlsxGenerator::new(packet_parser, config);
+
+    let mut output_writer = BufWriter::new(File::create(output).unwrap());
+
+    if let Err(e) = xlsx_gen.write_xlsx(&mut output_writer) {
+        eprintln!("Error while writing workbook: {e}");
+    }
+}
+
+fn convert_openrocket<I: SourceIterator>(
+    config: RocketConfig,
+    packet_parser: I,
+    output: PathBuf,
+    openrocket_options: OpenRocketOptions,
+) {
+    let openrocket_gen =
+        match OpenRocketGenerator::with_options(packet_parser, config, openrocket_options) {
+            Ok(openrocket_gen) => openrocket_gen,
+            Err(e) => {
+                eprintln!("Could not generate OpenRocket data: {e}");
+                return;
+            }
+        };
+
+    let mut output_writer = BufWriter::new(File::create(output).unwrap());
+
+    if let Err(e) = openrocket_gen.write_openrocket(&mut output_writer) {
+        eprintln!("Error while writing OpenRocket data: {e}");
+    }
+}
+
+fn convert_cbor<I: SourceIterator>(config: RocketConfig, packet_parser: I, output: PathBuf) {
+    let cbor_gen = CborGenerator::new(packet_parser, config);
+
+    let mut output_writer = BufWriter::new(File::create(output).unwrap());
+
+    if let Err(e) = cbor_gen.write_cbor(&mut output_writer) {
+        eprintln!("Error while writing archive: {e}");
     }
 }
 
@@ -99,6 +966,7 @@ fn check_config(config: RocketConfig) {
     println!("Loaded config:");
     println!("  name: {}", config.name);
     println!("  sensors: {}", config.sensors.len());
+    println!("  fingerprint: {:016x}", config.fingerprint());
     println!();
 
     if let Err(msg) = config.validate() {
@@ -113,28 +981,241 @@ fn check_config(config: RocketConfig) {
     for sensor in config.sensors.iter() {
         println!("  [\n    name: {},\n    values: [", sensor.name);
         for value in sensor.values.iter() {
-            println!("      {}: {},", value.name, value.data_type.to_string());
+            println!("      {}: {},", value.name, value.data_type);
         }
         println!("    ]");
         println!("  ]");
     }
 }
 
-fn generate_report(config: RocketConfig, data: PathBuf, output: PathBuf) {
+fn parse_percentile(value: &str) -> Result<f64, String> {
+    let percentile: f64 = value
+        .parse()
+        .map_err(|_| format!("{value} is not a number"))?;
+
+    if (0.0..=100.0).contains(&percentile) {
+        Ok(percentile)
+    } else {
+        Err(format!("{value} is not between 0 and 100"))
+    }
+}
+
+fn generate_report(config: RocketConfig, data: PathBuf, output: PathBuf, options: ReportOptions) {
     let input_reader = File::open(data).unwrap();
-    let packet_parser = PacketParser::new(input_reader, config.clone());
+    let Some(packet_parser) = open_packets(input_reader, Some(config.clone())) else {
+        return;
+    };
 
     let mut output_writer = BufWriter::new(File::create(output).unwrap());
 
-    let report = Report::new(config, packet_parser);
+    let report = Report::with_options(config, packet_parser, options);
 
     report.write(&mut output_writer).unwrap();
 }
 
-fn load_config<P: AsRef<Path>>(path: P) -> Result<RocketConfig, Box<dyn std::error::Error>> {
-    let file_content = std::fs::read_to_string(path)?;
+fn print_stats(config: Option<RocketConfig>, data: PathBuf, options: RateOptions) {
+    let input_reader = File::open(data).unwrap();
+    let Some(packet_parser) = open_packets(input_reader, config) else {
+        return;
+    };
+
+    let config = packet_parser.config().clone();
+    let mut rates = RateAnalysis::new(&config, options);
+
+    for packet in packet_parser {
+        match packet {
+            Ok(packet) => rates.update(&packet),
+            Err(e) => {
+                eprintln!("Error while parsing packet: {e}");
+                return;
+            }
+        }
+    }
+
+    let time_base = rates.time_base();
+    let period_unit = time_base.period_unit();
+    let format = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{v:.3}"));
+
+    match time_base {
+        TimeBase::Seconds => println!("Rates are in Hz and jitter is in seconds."),
+        TimeBase::Packets => println!(
+            "No value has the time role, so rates are per packet in the log and jitter is in packets."
+        ),
+    }
+    println!();
+    println!(
+        "{:<20} {:>8} {:>12} {:>12} {:>12} {:>12} {:>8}",
+        "sensor", "packets", "rate", "min rate", "max rate", "jitter", "dropouts"
+    );
+
+    for sensor in config.sensors.iter() {
+        let Some(rate) = rates.sensor(sensor.id) else {
+            continue;
+        };
+
+        println!(
+            "{:<20} {:>8} {:>12} {:>12} {:>12} {:>12} {:>8}",
+            sensor.name,
+            rate.count(),
+            format(rate.average_rate()),
+            format(rate.min_rate()),
+            format(rate.max_rate()),
+            rate.jitter().map_or("-".to_string(), |j| format!("{j:.5}")),
+            rate.gaps().count()
+        );
+    }
+
+    for sensor in config.sensors.iter() {
+        let Some(rate) = rates.sensor(sensor.id) else {
+            continue;
+        };
+        let mut gaps = rate.gaps().peekable();
+        if gaps.peek().is_none() {
+            continue;
+        }
+
+        println!();
+        println!("Dropouts of {}:", sensor.name);
+        for gap in gaps {
+            println!(
+                "  {:.3} {period_unit} at {:.3} {period_unit}",
+                gap.duration, gap.start
+            );
+        }
+    }
+}
+
+fn print_events(config: Option<RocketConfig>, data: PathBuf, json: bool, args: EventArgs) {
+    let input_reader = File::open(data).unwrap();
+    let Some(packet_parser) = open_packets(input_reader, config) else {
+        return;
+    };
 
-    let config = serde_json::from_str(&file_content)?;
+    let config = packet_parser.config().clone();
+    let detector = args
+        .options(&config)
+        .and_then(|options| EventDetector::new(&config, options));
+    let mut detector = match detector {
+        Ok(detector) => detector,
+        Err(e) => {
+            eprintln!("Could not detect events: {e}");
+            return;
+        }
+    };
 
-    Ok(config)
+    for packet in packet_parser {
+        match packet {
+            Ok(packet) => detector.update(&packet),
+            Err(e) => {
+                eprintln!("Error while parsing packet: {e}");
+                return;
+            }
+        }
+    }
+
+    let events = detector.events();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&events).unwrap());
+        return;
+    }
+
+    println!("{:<20} {:>10} {:>11}", "event", "time (s)", "confidence");
+    for event in events {
+        println!(
+            "{:<20} {:>10.3} {:>10.0}%",
+            event.kind.to_string(),
+            event.time,
+            event.confidence * 100.0
+        );
+    }
+}
+
+fn generate_code(config: RocketConfig, language: String, output: PathBuf) {
+    let mut output_writer = BufWriter::new(File::create(output).unwrap());
+
+    let result = match language.as_str() {
+        "c" => write_c_header(&config, &mut output_writer),
+        "rust" => write_rust_encoder(&config, &mut output_writer),
+        _ => {
+            eprintln!("Unsupported language: {language}");
+            return;
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("Error while generating code: {e}");
+    }
+}
+
+fn import_c_header(name: Option<String>, little_endian: bool, header: PathBuf, output: PathBuf) {
+    let source = match std::fs::read_to_string(&header) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Could not read header: {e}");
+            return;
+        }
+    };
+
+    let name = name.unwrap_or_else(|| {
+        header
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    });
+    let endianess = if little_endian {
+        Endianess::Little
+    } else {
+        Endianess::Big
+    };
+
+    let config = match config_from_c_header(&source, &name, endianess) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Could not import header: {e}");
+            return;
+        }
+    };
+
+    write_config(&config, None, output);
+}
+
+fn write_schema(output: Option<PathBuf>) {
+    let schema = serde_json::to_string_pretty(&json_schema()).unwrap();
+
+    match output {
+        Some(output) => std::fs::write(output, schema + "\n").unwrap(),
+        None => println!("{schema}"),
+    }
+}
+
+fn convert_config(to: Option<String>, input: PathBuf, output: PathBuf) {
+    let config = match load_config(input) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Could not load config: {e}");
+            return;
+        }
+    };
+
+    write_config(&config, to, output);
+}
+
+/// Write a config in the given format, or the format of the output extension.
+fn write_config(config: &RocketConfig, to: Option<String>, output: PathBuf) {
+    let format = match to.as_deref() {
+        Some("json") => ConfigFormat::Json,
+        Some("yaml") | Some("yml") => ConfigFormat::Yaml,
+        Some("toml") => ConfigFormat::Toml,
+        Some(to) => {
+            eprintln!("Unsupported config format: {to}");
+            return;
+        }
+        None => ConfigFormat::from_path(&output).unwrap_or(ConfigFormat::Json),
+    };
+
+    match format.write(config) {
+        Ok(content) => std::fs::write(output, content).unwrap(),
+        Err(e) => eprintln!("Could not write config: {e}"),
+    }
 }
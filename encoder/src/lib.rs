@@ -0,0 +1,111 @@
+//! An allocation free encoder for flight data logs, for use on the flight
+//! computer.
+//!
+//! The functions that log each sensor are generated from the rocket config by
+//! `flight_data_reader::codegen::write_rust_encoder`, usually in the build
+//! script of the firmware:
+//!
+//! ```ignore
+//! // build.rs
+//! let config = flight_data_reader::load_config("rocket.json").unwrap();
+//! let path = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("flight_data.rs");
+//! let mut file = std::fs::File::create(path).unwrap();
+//! flight_data_reader::codegen::write_rust_encoder(&config, &mut file).unwrap();
+//! println!("cargo:rerun-if-changed=rocket.json");
+//! ```
+//!
+//! The generated code is then included in the firmware, and writes packets to
+//! any [`Sink`]:
+//!
+//! ```ignore
+//! include!(concat!(env!("OUT_DIR"), "/flight_data.rs"));
+//!
+//! let mut buffer = [0u8; 512];
+//! let mut writer = flight_data_encoder::SliceWriter::new(&mut buffer);
+//! log_bmp(&mut writer, pressure, temperature)?;
+//! ```
+#![cfg_attr(not(test), no_std)]
+
+// Lets the generated code used in the tests refer to this crate by name.
+#[cfg(test)]
+extern crate self as flight_data_encoder;
+
+#[cfg(test)]
+mod tests;
+
+/// A destination for encoded packets.
+pub trait Sink {
+    /// The error returned if a packet can't be written.
+    type Error;
+
+    /// Write a whole packet.
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// The error returned when a packet doesn't fit in the rest of a buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferTooSmall;
+
+/// A sink that writes packets one after another into a byte buffer.
+///
+/// A packet that doesn't fit is not written at all, so the buffer always
+/// contains whole packets.
+pub struct SliceWriter<'a> {
+    buffer: &'a mut [u8],
+    position: usize,
+}
+
+impl<'a> SliceWriter<'a> {
+    /// Create a new writer at the start of the buffer.
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self {
+            buffer,
+            position: 0,
+        }
+    }
+
+    /// The number of bytes written.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The bytes written so far.
+    pub fn written(&self) -> &[u8] {
+        &self.buffer[..self.position]
+    }
+
+    /// Start writing from the start of the buffer again, for example after the
+    /// written bytes have been flushed to storage.
+    pub fn clear(&mut self) {
+        self.position = 0;
+    }
+}
+
+impl Sink for SliceWriter<'_> {
+    type Error = BufferTooSmall;
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        let end = self.position + bytes.len();
+        let Some(destination) = self.buffer.get_mut(self.position..end) else {
+            return Err(BufferTooSmall);
+        };
+
+        destination.copy_from_slice(bytes);
+        self.position = end;
+
+        Ok(())
+    }
+}
+
+/// A sink that writes packets to an [`embedded_io::Write`] writer.
+#[cfg(feature = "embedded-io")]
+pub struct IoSink<W>(pub W);
+
+#[cfg(feature = "embedded-io")]
+impl<W: embedded_io::Write> Sink for IoSink<W> {
+    type Error = W::Error;
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.0.write_all(bytes)
+    }
+}
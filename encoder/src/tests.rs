@@ -0,0 +1,73 @@
+use flight_data_reader::codegen::write_rust_encoder;
+use flight_data_reader::configuration::RocketConfig;
+use flight_data_reader::data::PacketParser;
+
+use super::*;
+
+/// Code generated from the example config.
+#[rustfmt::skip]
+mod xenia;
+
+const RAW_CONFIG: &str = include_str!("../../example_config.json");
+
+#[test]
+fn test_generated_code_is_current() {
+    let config: RocketConfig = serde_json::from_str(RAW_CONFIG).unwrap();
+
+    let mut code = vec![];
+    write_rust_encoder(&config, &mut code).unwrap();
+
+    assert_eq!(
+        String::from_utf8(code).unwrap(),
+        include_str!("tests/xenia.rs"),
+        "regenerate src/tests/xenia.rs with `codegen -l rust -c example_config.json`"
+    );
+}
+
+#[test]
+fn test_round_trip() {
+    let config: RocketConfig = serde_json::from_str(RAW_CONFIG).unwrap();
+    assert_eq!(xenia::FINGERPRINT, config.fingerprint());
+
+    let mut buffer = [0u8; 64];
+    let mut writer = SliceWriter::new(&mut buffer);
+    writer.write(&xenia::LOG_HEADER).unwrap();
+    xenia::log_lsm(&mut writer, 1.0, -2.5, 3.25).unwrap();
+    xenia::log_bmp(&mut writer, 1013.25, 21.5).unwrap();
+    xenia::log_integer_test(&mut writer, -123456).unwrap();
+
+    let packets = PacketParser::open(writer.written(), Some(config))
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(packets.len(), 3);
+
+    assert_eq!(packets[0].id, xenia::LSM_ID);
+    let lsm: Vec<f32> = packets[0]
+        .values
+        .iter()
+        .map(|v| unsafe { v.float_32 })
+        .collect();
+    assert_eq!(lsm, vec![1.0, -2.5, 3.25]);
+
+    assert_eq!(packets[1].id, xenia::BMP_ID);
+    assert_eq!(unsafe { packets[1].values[0].float_32 }, 1013.25);
+    assert_eq!(unsafe { packets[1].values[1].float_32 }, 21.5);
+
+    assert_eq!(packets[2].id, xenia::INTEGER_TEST_ID);
+    assert_eq!(unsafe { packets[2].values[0].int_32 }, -123456);
+}
+
+#[test]
+fn test_slice_writer_full() {
+    let mut buffer = [0u8; xenia::BMP_PACKET_SIZE + 4];
+    let mut writer = SliceWriter::new(&mut buffer);
+
+    assert_eq!(xenia::log_bmp(&mut writer, 1.0, 2.0), Ok(()));
+    assert_eq!(xenia::log_bmp(&mut writer, 1.0, 2.0), Err(BufferTooSmall));
+    assert_eq!(writer.position(), xenia::BMP_PACKET_SIZE);
+
+    writer.clear();
+    assert_eq!(xenia::log_bmp(&mut writer, 1.0, 2.0), Ok(()));
+}
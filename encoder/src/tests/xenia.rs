@@ -0,0 +1,52 @@
+// Flight data logging for Xenia-2, generated from its config. Do not edit.
+
+/// The fingerprint of the config the log is written with.
+pub const FINGERPRINT: u64 = 0x1978c3f943f5695e;
+
+/// Written once at the start of a log so the reader can check the config.
+pub const LOG_HEADER: [u8; 14] = [0x46, 0x44, 0x52, 0x4c, 0x01, 0x00, 0x5e, 0x69, 0xf5, 0x43, 0xf9, 0xc3, 0x78, 0x19];
+
+/// The ID of the `LSM` sensor.
+pub const LSM_ID: u8 = 1;
+/// The number of bytes in a `LSM` packet.
+pub const LSM_PACKET_SIZE: usize = 13;
+
+/// Write a `LSM` packet to the sink.
+#[allow(clippy::too_many_arguments)]
+pub fn log_lsm<S: ::flight_data_encoder::Sink>(sink: &mut S, x: f32, y: f32, z: f32) -> Result<(), S::Error> {
+    let mut packet = [0u8; LSM_PACKET_SIZE];
+    packet[0] = LSM_ID;
+    packet[1..5].copy_from_slice(&x.to_be_bytes());
+    packet[5..9].copy_from_slice(&y.to_be_bytes());
+    packet[9..13].copy_from_slice(&z.to_be_bytes());
+    sink.write(&packet)
+}
+
+/// The ID of the `BMP` sensor.
+pub const BMP_ID: u8 = 2;
+/// The number of bytes in a `BMP` packet.
+pub const BMP_PACKET_SIZE: usize = 9;
+
+/// Write a `BMP` packet to the sink.
+#[allow(clippy::too_many_arguments)]
+pub fn log_bmp<S: ::flight_data_encoder::Sink>(sink: &mut S, pressure: f32, temperature: f32) -> Result<(), S::Error> {
+    let mut packet = [0u8; BMP_PACKET_SIZE];
+    packet[0] = BMP_ID;
+    packet[1..5].copy_from_slice(&pressure.to_be_bytes());
+    packet[5..9].copy_from_slice(&temperature.to_be_bytes());
+    sink.write(&packet)
+}
+
+/// The ID of the `integer_test` sensor.
+pub const INTEGER_TEST_ID: u8 = 3;
+/// The number of bytes in a `integer_test` packet.
+pub const INTEGER_TEST_PACKET_SIZE: usize = 5;
+
+/// Write a `integer_test` packet to the sink.
+#[allow(clippy::too_many_arguments)]
+pub fn log_integer_test<S: ::flight_data_encoder::Sink>(sink: &mut S, int: i32) -> Result<(), S::Error> {
+    let mut packet = [0u8; INTEGER_TEST_PACKET_SIZE];
+    packet[0] = INTEGER_TEST_ID;
+    packet[1..5].copy_from_slice(&int.to_be_bytes());
+    sink.write(&packet)
+}
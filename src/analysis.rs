@@ -0,0 +1,190 @@
+use crate::configuration::{RocketConfig, ValueConfig, ValueKind, ValueRole};
+use crate::data::{Packet, TypedValue};
+
+pub mod altitude;
+pub mod atmosphere;
+pub mod events;
+pub mod filter;
+pub mod kalman;
+pub mod orientation;
+pub mod rate;
+pub mod stats;
+
+/// A value found by its role that is read from packets in SI units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Channel {
+    /// The ID of the sensor with the value.
+    pub sensor_id: u8,
+    /// The index of the value within the sensor.
+    pub index: usize,
+    /// The kind of the value.
+    pub kind: ValueKind,
+    /// The number that raw values are multiplied by to get SI units.
+    pub scale: f64,
+    /// The number added to scaled values to get SI units.
+    pub offset: f64,
+}
+
+impl Channel {
+    /// Find the value with the given role.
+    ///
+    /// # Returns
+    ///
+    /// The channel, or `None` if no value has the role or the unit of the
+    /// value is not known.
+    pub fn from_role(config: &RocketConfig, role: ValueRole) -> Option<Self> {
+        let (sensor, index) = config.find_role(role)?;
+        let value = &sensor.values[index];
+
+        Some(Self {
+            sensor_id: sensor.id,
+            index,
+            kind: value.data_type,
+            scale: value.si_per_unit()?,
+            offset: value.si_offset(),
+        })
+    }
+
+    /// Find a value by its name, written as `sensor.value`.
+    ///
+    /// The unit of the value is read as if it had the given role, so values
+    /// without a role can be used.
+    ///
+    /// # Returns
+    ///
+    /// The channel, or `None` if there is no such value or its unit is not
+    /// known for the role.
+    pub fn from_name(config: &RocketConfig, name: &str, role: ValueRole) -> Option<Self> {
+        let (sensor_name, value_name) = name.split_once('.')?;
+        let sensor = config.sensors.iter().find(|s| s.name == sensor_name)?;
+        let index = sensor.values.iter().position(|v| v.name == value_name)?;
+        let value = ValueConfig {
+            role: Some(role),
+            ..sensor.values[index].clone()
+        };
+
+        Some(Self {
+            sensor_id: sensor.id,
+            index,
+            kind: value.data_type,
+            scale: value.si_per_unit()?,
+            offset: value.si_offset(),
+        })
+    }
+
+    /// Find the acceleration value along the axis that points towards the
+    /// nose of the rocket, as set by [`RocketConfig::nose_axis`].
+    ///
+    /// The channel is positive when accelerating towards the nose, so it reads
+    /// about 1 g while the rocket is standing on the pad.
+    pub fn axial_acceleration(config: &RocketConfig) -> Option<Self> {
+        let mut channel = Self::from_role(config, config.nose_axis.acceleration_role())?;
+        channel.scale *= config.nose_axis.sign();
+        Some(channel)
+    }
+
+    /// Read the value from a packet in SI units.
+    ///
+    /// # Returns
+    ///
+    /// The value, or `None` if the packet is not from the sensor of this
+    /// channel.
+    pub fn read(&self, packet: &Packet) -> Option<f64> {
+        if packet.id != self.sensor_id {
+            return None;
+        }
+
+        let value = packet.values.get(self.index)?;
+        // The safety is assumed by the packet matching the config.
+        let value = unsafe { TypedValue::new(*value, &self.kind) };
+        Some(value.to_f64() * self.scale + self.offset)
+    }
+}
+
+/// Tracks how long a condition has held.
+#[derive(Debug, Default)]
+pub(crate) struct Debounce {
+    since: Option<f64>,
+}
+
+impl Debounce {
+    /// Update the condition at a time.
+    ///
+    /// # Returns
+    ///
+    /// The time the condition started to hold, if it has held for at least
+    /// `window` seconds.
+    pub(crate) fn update(&mut self, condition: bool, time: f64, window: f64) -> Option<f64> {
+        if !condition {
+            self.since = None;
+            return None;
+        }
+
+        let since = *self.since.get_or_insert(time);
+        (time - since >= window).then_some(since)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::{Axis, Endianess, SensorConfig};
+    use crate::data::Value;
+
+    use super::*;
+
+    #[test]
+    fn test_axial_acceleration_channel() {
+        let config = RocketConfig {
+            name: "test".to_string(),
+            endianess: Endianess::default(),
+            nose_axis: Axis::NegativeY,
+            include: vec![],
+            display_name: None,
+            description: None,
+            kalman: None,
+            sensors: vec![SensorConfig {
+                id: 2,
+                name: "IMU".to_string(),
+                values: vec![ValueConfig {
+                    name: "y".to_string(),
+                    data_type: ValueKind::Int16,
+                    unit: Some("g".to_string()),
+                    role: Some(ValueRole::AccelerationY),
+                    filters: vec![],
+                }],
+            }],
+        };
+
+        let channel = Channel::axial_acceleration(&config).unwrap();
+        let packet = Packet {
+            id: 2,
+            values: vec![Value { int_16: -2 }],
+        };
+
+        assert_eq!(channel.read(&packet), Some(2.0 * 9.80665));
+        assert_eq!(
+            channel.read(&Packet {
+                id: 1,
+                values: vec![]
+            }),
+            None
+        );
+
+        let named = Channel::from_name(&config, "IMU.y", ValueRole::AccelerationX).unwrap();
+        assert_eq!(named.read(&packet), Some(-2.0 * 9.80665));
+        assert_eq!(
+            Channel::from_name(&config, "IMU.w", ValueRole::AccelerationX),
+            None
+        );
+    }
+
+    #[test]
+    fn test_debounce() {
+        let mut debounce = Debounce::default();
+
+        assert_eq!(debounce.update(true, 1.0, 0.5), None);
+        assert_eq!(debounce.update(false, 1.2, 0.5), None);
+        assert_eq!(debounce.update(true, 1.3, 0.5), None);
+        assert_eq!(debounce.update(true, 1.8, 0.5), Some(1.3));
+    }
+}
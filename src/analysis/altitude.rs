@@ -0,0 +1,451 @@
+use std::collections::VecDeque;
+
+use crate::analysis::atmosphere::{pressure_altitude_at, SEA_LEVEL_TEMPERATURE};
+use crate::analysis::{Channel, Debounce};
+use crate::configuration::{RocketConfig, ValueRole};
+use crate::data::Packet;
+use crate::time::PacketClock;
+
+/// Options for deriving the altitude from a barometer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AltitudeOptions {
+    /// The static pressure.
+    ///
+    /// This is found with the [`ValueRole::Pressure`] role if not set.
+    pub pressure: Option<Channel>,
+    /// The air temperature, used for the temperature on the ground.
+    ///
+    /// This is found with the [`ValueRole::Temperature`] role if not set. The
+    /// standard sea level temperature is used if there is no temperature.
+    pub temperature: Option<Channel>,
+    /// How long the samples averaged for the ground reference are, in
+    /// seconds.
+    ///
+    /// The samples are taken from one window before the most recent sample,
+    /// so that the start of the climb is not included when launch is
+    /// detected.
+    pub ground_window: f64,
+    /// How far above the ground the rocket must be for launch, in metres.
+    ///
+    /// The ground reference is fixed once the rocket has launched.
+    pub launch_altitude: f64,
+    /// How long the rocket must stay above the launch altitude for launch,
+    /// in seconds, so a single gust or glitch on the pad isn't a launch.
+    pub debounce: f64,
+    /// The time constant of the filter smoothing the altitude and velocity,
+    /// in seconds.
+    pub smoothing: f64,
+}
+
+impl Default for AltitudeOptions {
+    fn default() -> Self {
+        Self {
+            pressure: None,
+            temperature: None,
+            ground_window: 1.0,
+            launch_altitude: 10.0,
+            debounce: 0.1,
+            smoothing: 0.25,
+        }
+    }
+}
+
+/// The altitude of the rocket at a single barometer reading.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AltitudeSample {
+    /// The time of the reading in seconds.
+    pub time: f64,
+    /// The smoothed altitude above the ground in metres.
+    pub altitude: f64,
+    /// The smoothed vertical velocity in metres per second.
+    pub vertical_velocity: f64,
+}
+
+/// A sample recorded while the rocket is on the pad.
+#[derive(Debug, Clone, Copy)]
+struct PadSample {
+    time: f64,
+    pressure: f64,
+    temperature: Option<f64>,
+}
+
+/// The running sums of some pad samples, to average them.
+#[derive(Debug, Clone, Copy, Default)]
+struct PadSums {
+    count: usize,
+    pressure: f64,
+    temperature_count: usize,
+    temperature: f64,
+}
+
+impl PadSums {
+    fn add(&mut self, sample: &PadSample) {
+        self.count += 1;
+        self.pressure += sample.pressure;
+        if let Some(temperature) = sample.temperature {
+            self.temperature_count += 1;
+            self.temperature += temperature;
+        }
+    }
+
+    fn remove(&mut self, sample: &PadSample) {
+        self.count -= 1;
+        self.pressure -= sample.pressure;
+        if let Some(temperature) = sample.temperature {
+            self.temperature_count -= 1;
+            self.temperature -= temperature;
+        }
+    }
+
+    /// The average pressure and temperature, if there are any samples.
+    fn average(&self) -> Option<(f64, Option<f64>)> {
+        (self.count > 0).then(|| {
+            let temperature = (self.temperature_count > 0)
+                .then(|| self.temperature / self.temperature_count as f64);
+            (self.pressure / self.count as f64, temperature)
+        })
+    }
+}
+
+/// Derives the altitude above the ground and the vertical velocity from a
+/// barometer.
+///
+/// The altitude uses the standard atmosphere, with the pressure and
+/// temperature on the ground averaged from the samples before launch. Packets
+/// are added one at a time with [`AltitudeEstimator::update`], and only a
+/// couple of ground windows of samples are kept.
+pub struct AltitudeEstimator {
+    clock: PacketClock,
+    options: AltitudeOptions,
+    pressure: Channel,
+    temperature: Option<Channel>,
+    /// The most recent temperature read, in kelvin.
+    latest_temperature: Option<f64>,
+    /// The samples from the last ground window, while on the pad.
+    recent_samples: VecDeque<PadSample>,
+    recent_sums: PadSums,
+    /// The samples from one to two ground windows ago, while on the pad.
+    old_samples: VecDeque<PadSample>,
+    old_sums: PadSums,
+    /// The pressure in pascals and temperature in kelvin on the ground, if a
+    /// temperature was read.
+    ground: Option<(f64, Option<f64>)>,
+    launched: bool,
+    /// How long the rocket has been above the launch altitude.
+    launch_debounce: Debounce,
+    altitude: Option<f64>,
+    vertical_velocity: f64,
+    previous_time: Option<f64>,
+    apogee: Option<AltitudeSample>,
+    max_velocity: Option<AltitudeSample>,
+}
+
+impl AltitudeEstimator {
+    /// Create an estimator for packets using the given rocket configuration.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the config doesn't have both a pressure and a
+    /// time value.
+    pub fn new(config: &RocketConfig, options: AltitudeOptions) -> Result<Self, String> {
+        let Some(pressure) = options
+            .pressure
+            .or_else(|| Channel::from_role(config, ValueRole::Pressure))
+        else {
+            return Err("No value has the pressure role".to_string());
+        };
+
+        let clock = PacketClock::new(config);
+        if !clock.has_source() {
+            return Err("No value has the time role".to_string());
+        }
+
+        Ok(Self {
+            clock,
+            temperature: options
+                .temperature
+                .or_else(|| Channel::from_role(config, ValueRole::Temperature)),
+            options,
+            pressure,
+            latest_temperature: None,
+            recent_samples: VecDeque::new(),
+            recent_sums: PadSums::default(),
+            old_samples: VecDeque::new(),
+            old_sums: PadSums::default(),
+            ground: None,
+            launched: false,
+            launch_debounce: Debounce::default(),
+            altitude: None,
+            vertical_velocity: 0.0,
+            previous_time: None,
+            apogee: None,
+            max_velocity: None,
+        })
+    }
+
+    /// Add the next packet in the log.
+    ///
+    /// # Returns
+    ///
+    /// The altitude, if the packet has a pressure reading and a time has been
+    /// read.
+    pub fn update(&mut self, packet: &Packet) -> Option<AltitudeSample> {
+        let time = self.clock.update(packet);
+
+        if let Some(temperature) = self.temperature.and_then(|c| c.read(packet)) {
+            self.latest_temperature = Some(temperature);
+        }
+
+        let pressure = self.pressure.read(packet)?;
+        let time = time?;
+
+        if !self.launched {
+            self.update_ground(PadSample {
+                time,
+                pressure,
+                temperature: self.latest_temperature,
+            });
+        }
+
+        let (ground_pressure, ground_temperature) = self.ground?;
+        let raw_altitude = pressure_altitude_at(
+            pressure,
+            ground_pressure,
+            ground_temperature.unwrap_or(SEA_LEVEL_TEMPERATURE),
+        );
+
+        if !self.launched {
+            let above = raw_altitude > self.options.launch_altitude;
+            if self
+                .launch_debounce
+                .update(above, time, self.options.debounce)
+                .is_some()
+            {
+                self.launched = true;
+                self.recent_samples.clear();
+                self.old_samples.clear();
+            }
+        }
+
+        let altitude = match (self.altitude, self.previous_time) {
+            (Some(altitude), Some(previous)) if time > previous => {
+                let elapsed = time - previous;
+                let weight = elapsed / (self.options.smoothing + elapsed);
+                let smoothed = altitude + weight * (raw_altitude - altitude);
+
+                let velocity = (smoothed - altitude) / elapsed;
+                self.vertical_velocity += weight * (velocity - self.vertical_velocity);
+                smoothed
+            }
+            (Some(altitude), _) => altitude,
+            (None, _) => raw_altitude,
+        };
+        self.altitude = Some(altitude);
+        self.previous_time = Some(time);
+
+        let sample = AltitudeSample {
+            time,
+            altitude,
+            vertical_velocity: self.vertical_velocity,
+        };
+
+        if self.launched {
+            if self.apogee.is_none_or(|apogee| altitude > apogee.altitude) {
+                self.apogee = Some(sample);
+            }
+            if self
+                .max_velocity
+                .is_none_or(|max| sample.vertical_velocity > max.vertical_velocity)
+            {
+                self.max_velocity = Some(sample);
+            }
+        }
+
+        Some(sample)
+    }
+
+    /// Add a sample from the pad and update the ground reference.
+    fn update_ground(&mut self, sample: PadSample) {
+        let window = self.options.ground_window;
+
+        self.recent_samples.push_back(sample);
+        self.recent_sums.add(&sample);
+
+        while let Some(oldest) = self.recent_samples.front().copied() {
+            if sample.time - oldest.time < window {
+                break;
+            }
+            self.recent_samples.pop_front();
+            self.recent_sums.remove(&oldest);
+            self.old_samples.push_back(oldest);
+            self.old_sums.add(&oldest);
+        }
+
+        while let Some(oldest) = self.old_samples.front().copied() {
+            if sample.time - oldest.time <= 2.0 * window {
+                break;
+            }
+            self.old_samples.pop_front();
+            self.old_sums.remove(&oldest);
+        }
+
+        // Use the samples from at least one window ago, or every sample at the
+        // start of the log.
+        self.ground = self
+            .old_sums
+            .average()
+            .or_else(|| self.recent_sums.average());
+    }
+
+    /// The pressure on the ground in pascals, once a pressure has been read.
+    pub fn ground_pressure(&self) -> Option<f64> {
+        self.ground.map(|(pressure, _)| pressure)
+    }
+
+    /// The temperature on the ground in kelvin, if a temperature was read
+    /// before launch.
+    pub fn ground_temperature(&self) -> Option<f64> {
+        self.ground?.1
+    }
+
+    /// Whether the rocket has climbed above the launch altitude.
+    pub fn launched(&self) -> bool {
+        self.launched
+    }
+
+    /// The highest point of the flight, once the rocket has launched.
+    pub fn apogee(&self) -> Option<AltitudeSample> {
+        self.apogee
+    }
+
+    /// The point of the flight with the highest vertical velocity, once the
+    /// rocket has launched.
+    pub fn max_velocity(&self) -> Option<AltitudeSample> {
+        self.max_velocity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::atmosphere::{LAPSE_RATE, SEA_LEVEL_PRESSURE};
+    use crate::configuration::{Axis, Endianess, SensorConfig, ValueConfig, ValueKind};
+    use crate::data::Value;
+
+    use super::*;
+
+    fn value(name: &str, unit: &str, role: ValueRole) -> ValueConfig {
+        ValueConfig {
+            name: name.to_string(),
+            data_type: ValueKind::Float32,
+            unit: Some(unit.to_string()),
+            role: Some(role),
+            filters: vec![],
+        }
+    }
+
+    fn test_config() -> RocketConfig {
+        RocketConfig {
+            name: "test".to_string(),
+            endianess: Endianess::default(),
+            nose_axis: Axis::default(),
+            include: vec![],
+            display_name: None,
+            description: None,
+            kalman: None,
+            sensors: vec![SensorConfig {
+                id: 0,
+                name: "BMP".to_string(),
+                values: vec![
+                    value("time", "s", ValueRole::Time),
+                    value("pressure", "hPa", ValueRole::Pressure),
+                    value("temperature", "C", ValueRole::Temperature),
+                ],
+            }],
+        }
+    }
+
+    /// The pressure at an altitude above a ground at sea level pressure and
+    /// the given temperature in kelvin.
+    fn pressure_at(altitude: f64, temperature: f64) -> f64 {
+        SEA_LEVEL_PRESSURE * (1.0 - altitude * LAPSE_RATE / temperature).powf(1.0 / 0.190_263)
+    }
+
+    fn packet(time: f64, altitude: f64) -> Packet {
+        Packet {
+            id: 0,
+            values: vec![
+                Value {
+                    float_32: time as f32,
+                },
+                Value {
+                    float_32: (pressure_at(altitude, 303.15) / 100.0) as f32,
+                },
+                Value { float_32: 30.0 },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_altitude_estimator() {
+        let mut estimator = AltitudeEstimator::new(&test_config(), Default::default()).unwrap();
+
+        // Three seconds on the pad, then a climb at 50 m/s for 10 seconds and
+        // a descent at 10 m/s.
+        let mut last = None;
+        for i in 0..3000 {
+            let time = i as f64 * 0.01;
+            let altitude = match time {
+                t if t < 3.0 => 0.0,
+                t if t < 13.0 => (t - 3.0) * 50.0,
+                t => 500.0 - (t - 13.0) * 10.0,
+            };
+            last = estimator.update(&packet(time, altitude));
+        }
+
+        assert!(estimator.launched());
+        assert!((estimator.ground_pressure().unwrap() - SEA_LEVEL_PRESSURE).abs() < 1.0);
+        assert!((estimator.ground_temperature().unwrap() - 303.15).abs() < 1e-3);
+
+        let apogee = estimator.apogee().unwrap();
+        assert!((apogee.altitude - 500.0).abs() < 15.0, "{apogee:?}");
+        assert!((apogee.time - 13.0).abs() < 0.5, "{apogee:?}");
+
+        let max_velocity = estimator.max_velocity().unwrap();
+        assert!(
+            (max_velocity.vertical_velocity - 50.0).abs() < 1.0,
+            "{max_velocity:?}"
+        );
+
+        let last = last.unwrap();
+        assert!((last.vertical_velocity + 10.0).abs() < 0.5, "{last:?}");
+        assert!((last.altitude - 330.0).abs() < 5.0, "{last:?}");
+    }
+
+    #[test]
+    fn test_launch_is_debounced() {
+        let mut estimator = AltitudeEstimator::new(&test_config(), Default::default()).unwrap();
+
+        // A glitch of one sample on the pad isn't a launch.
+        for i in 0..300 {
+            let time = i as f64 * 0.01;
+            let altitude = if i == 50 { 50.0 } else { 0.0 };
+            estimator.update(&packet(time, altitude));
+        }
+        assert!(!estimator.launched());
+
+        for i in 300..400 {
+            let time = i as f64 * 0.01;
+            estimator.update(&packet(time, (time - 3.0) * 100.0));
+        }
+        assert!(estimator.launched());
+        assert!((estimator.ground_pressure().unwrap() - SEA_LEVEL_PRESSURE).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_estimator_needs_pressure() {
+        let mut config = test_config();
+        config.sensors[0].values[1].role = None;
+
+        assert!(AltitudeEstimator::new(&config, Default::default()).is_err());
+    }
+}
@@ -0,0 +1,72 @@
+/// The standard pressure at sea level in pascals.
+pub const SEA_LEVEL_PRESSURE: f64 = 101_325.0;
+
+/// The standard temperature at sea level in kelvin.
+pub const SEA_LEVEL_TEMPERATURE: f64 = 288.15;
+
+/// The rate temperature falls with altitude in kelvin per metre.
+pub const LAPSE_RATE: f64 = 0.0065;
+
+/// The exponent of the pressure ratio in the barometric formula, equal to
+/// `R * L / (g * M)`.
+const PRESSURE_EXPONENT: f64 = 0.190_263;
+
+/// Calculate the altitude above a reference pressure level.
+///
+/// This is the altitude above ground level if the reference pressure is the
+/// pressure on the ground, or the pressure altitude if the reference is
+/// [`SEA_LEVEL_PRESSURE`].
+///
+/// # Params
+///
+/// * `pressure` - The measured pressure in pascals.
+/// * `reference_pressure` - The pressure at zero altitude in pascals.
+pub fn pressure_altitude(pressure: f64, reference_pressure: f64) -> f64 {
+    pressure_altitude_at(pressure, reference_pressure, SEA_LEVEL_TEMPERATURE)
+}
+
+/// Calculate the altitude above a reference pressure level, using the
+/// temperature measured at that level instead of the standard temperature.
+///
+/// The temperature still falls at the standard [`LAPSE_RATE`] above the
+/// reference level.
+///
+/// # Params
+///
+/// * `pressure` - The measured pressure in pascals.
+/// * `reference_pressure` - The pressure at zero altitude in pascals.
+/// * `reference_temperature` - The temperature at zero altitude in kelvin.
+pub fn pressure_altitude_at(
+    pressure: f64,
+    reference_pressure: f64,
+    reference_temperature: f64,
+) -> f64 {
+    reference_temperature / LAPSE_RATE
+        * (1.0 - (pressure / reference_pressure).powf(PRESSURE_EXPONENT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pressure_altitude() {
+        assert_eq!(
+            pressure_altitude(SEA_LEVEL_PRESSURE, SEA_LEVEL_PRESSURE),
+            0.0
+        );
+        // Standard atmosphere tables give 89 874.6 Pa at 1000 m.
+        assert!((pressure_altitude(89_874.6, SEA_LEVEL_PRESSURE) - 1000.0).abs() < 0.5);
+        assert!(pressure_altitude(90_000.0, 95_000.0) > 0.0);
+    }
+
+    #[test]
+    fn test_pressure_altitude_at() {
+        let standard = pressure_altitude(89_874.6, SEA_LEVEL_PRESSURE);
+        let warm = pressure_altitude_at(89_874.6, SEA_LEVEL_PRESSURE, 303.15);
+
+        // Warm air is less dense, so the same pressure drop is a larger climb.
+        assert!(warm > standard);
+        assert!((warm / standard - 303.15 / SEA_LEVEL_TEMPERATURE).abs() < 1e-12);
+    }
+}
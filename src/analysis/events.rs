@@ -0,0 +1,624 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::analysis::altitude::{AltitudeEstimator, AltitudeOptions, AltitudeSample};
+use crate::analysis::{Channel, Debounce};
+use crate::configuration::{RocketConfig, STANDARD_GRAVITY};
+use crate::data::Packet;
+use crate::time::PacketClock;
+
+/// How long the acceleration must stay below the deployment threshold after
+/// the drogue deploys before the main deployment can be detected, in seconds.
+const DEPLOYMENT_SEPARATION: f64 = 1.0;
+
+/// A stage of a flight that can be detected from the data.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Launch,
+    Burnout,
+    Apogee,
+    DrogueDeployment,
+    MainDeployment,
+    Landing,
+}
+
+impl EventKind {
+    /// Every kind of event, in the order they happen in a flight.
+    pub const ALL: [Self; 6] = [
+        Self::Launch,
+        Self::Burnout,
+        Self::Apogee,
+        Self::DrogueDeployment,
+        Self::MainDeployment,
+        Self::Landing,
+    ];
+
+    /// Whether the event can be detected from the acceleration.
+    fn seen_by_acceleration(&self) -> bool {
+        !matches!(self, Self::Landing)
+    }
+
+    /// Whether the event can be detected from the pressure.
+    fn seen_by_pressure(&self) -> bool {
+        !matches!(self, Self::DrogueDeployment | Self::MainDeployment)
+    }
+
+    /// Whether the time from the pressure is used when both sensors detect
+    /// the event.
+    fn prefers_pressure(&self) -> bool {
+        matches!(self, Self::Apogee | Self::Landing)
+    }
+}
+
+impl Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Launch => "Launch",
+            Self::Burnout => "Motor burnout",
+            Self::Apogee => "Apogee",
+            Self::DrogueDeployment => "Drogue deployment",
+            Self::MainDeployment => "Main deployment",
+            Self::Landing => "Landing",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// An event detected in a flight.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct FlightEvent {
+    /// What happened.
+    pub kind: EventKind,
+    /// The time of the event in seconds.
+    pub time: f64,
+    /// The fraction of the available sensors able to detect the event that
+    /// detected it, from 0 to 1.
+    ///
+    /// This is 0.5 when only one of the accelerometer and the barometer saw an
+    /// event they could both have seen. Landings are only detected from the
+    /// pressure and parachute deployments only from the acceleration.
+    pub confidence: f64,
+}
+
+/// Options for detecting flight events.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventOptions {
+    /// The acceleration towards the nose of the rocket, including gravity.
+    ///
+    /// This is found with [`Channel::axial_acceleration`] if not set.
+    pub acceleration: Option<Channel>,
+    /// The static pressure.
+    ///
+    /// This is found with the
+    /// [`ValueRole::Pressure`](crate::configuration::ValueRole::Pressure) role
+    /// if not set.
+    pub pressure: Option<Channel>,
+    /// How much the acceleration must be above 1 g for launch, in metres per
+    /// second squared.
+    pub launch_acceleration: f64,
+    /// How far above the ground the rocket must be for launch, in metres.
+    pub launch_altitude: f64,
+    /// The acceleration below which the motor has burnt out, in metres per
+    /// second squared.
+    ///
+    /// An accelerometer reads about 0 g or less while coasting, as it only
+    /// measures drag.
+    pub burnout_acceleration: f64,
+    /// How far below the highest altitude the rocket must fall for apogee,
+    /// in metres.
+    pub apogee_descent: f64,
+    /// The size of the jolt from a parachute opening, in metres per second
+    /// squared.
+    pub deployment_acceleration: f64,
+    /// The vertical speed below which the rocket has landed, in metres per
+    /// second.
+    pub landing_velocity: f64,
+    /// How long the launch and burnout conditions must hold, in seconds.
+    pub debounce: f64,
+    /// How long the landing condition must hold, in seconds.
+    pub landing_window: f64,
+    /// The time constant of the filter smoothing the barometer, in seconds.
+    pub smoothing: f64,
+}
+
+impl Default for EventOptions {
+    fn default() -> Self {
+        Self {
+            acceleration: None,
+            pressure: None,
+            launch_acceleration: 2.0 * STANDARD_GRAVITY,
+            launch_altitude: 10.0,
+            burnout_acceleration: 0.0,
+            apogee_descent: 10.0,
+            deployment_acceleration: 3.0 * STANDARD_GRAVITY,
+            landing_velocity: 1.0,
+            debounce: 0.1,
+            landing_window: 3.0,
+            smoothing: 0.25,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AccelerationPhase {
+    Pad,
+    Boost,
+    Coast,
+    Drogue,
+    Main,
+}
+
+/// Detects events from the acceleration towards the nose.
+struct AccelerationDetector {
+    channel: Channel,
+    phase: AccelerationPhase,
+    debounce: Debounce,
+    /// The vertical velocity since launch, integrated from the acceleration.
+    velocity: f64,
+    previous_time: Option<f64>,
+    /// Whether the main deployment can be detected yet.
+    armed: bool,
+    detections: Vec<(EventKind, f64)>,
+}
+
+impl AccelerationDetector {
+    fn new(channel: Channel) -> Self {
+        Self {
+            channel,
+            phase: AccelerationPhase::Pad,
+            debounce: Debounce::default(),
+            velocity: 0.0,
+            previous_time: None,
+            armed: false,
+            detections: vec![],
+        }
+    }
+
+    fn update(&mut self, time: f64, acceleration: f64, options: &EventOptions) {
+        let elapsed = self
+            .previous_time
+            .map_or(0.0, |previous| (time - previous).max(0.0));
+        self.previous_time = Some(time);
+
+        // This assumes the rocket flies vertically, so the acceleration
+        // towards the nose is vertical.
+        self.velocity += (acceleration - STANDARD_GRAVITY) * elapsed;
+        let jolt = acceleration.abs() > options.deployment_acceleration;
+
+        match self.phase {
+            AccelerationPhase::Pad => {
+                let launched = acceleration > STANDARD_GRAVITY + options.launch_acceleration;
+                if let Some(start) = self.debounce.update(launched, time, options.debounce) {
+                    self.detect(EventKind::Launch, start, AccelerationPhase::Boost);
+                    self.velocity = (acceleration - STANDARD_GRAVITY) * (time - start);
+                }
+            }
+            AccelerationPhase::Boost => {
+                let burnt_out = acceleration < options.burnout_acceleration;
+                if let Some(start) = self.debounce.update(burnt_out, time, options.debounce) {
+                    self.detect(EventKind::Burnout, start, AccelerationPhase::Coast);
+                }
+            }
+            AccelerationPhase::Coast => {
+                if jolt {
+                    self.detect(EventKind::DrogueDeployment, time, AccelerationPhase::Drogue);
+                } else if self.velocity <= 0.0 && !self.detected(EventKind::Apogee) {
+                    self.detections.push((EventKind::Apogee, time));
+                }
+            }
+            AccelerationPhase::Drogue => {
+                let quiet = self.debounce.update(!jolt, time, DEPLOYMENT_SEPARATION);
+                self.armed |= quiet.is_some();
+
+                if self.armed && jolt {
+                    self.detect(EventKind::MainDeployment, time, AccelerationPhase::Main);
+                }
+            }
+            AccelerationPhase::Main => {}
+        }
+    }
+
+    /// Record an event and move on to the next phase of the flight.
+    fn detect(&mut self, kind: EventKind, time: f64, phase: AccelerationPhase) {
+        self.detections.push((kind, time));
+        self.phase = phase;
+        self.debounce = Debounce::default();
+    }
+
+    fn detected(&self, kind: EventKind) -> bool {
+        self.detections.iter().any(|(k, _)| *k == kind)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PressurePhase {
+    Pad,
+    Ascent,
+    Descent,
+    Landed,
+}
+
+/// Detects events from the altitude measured by a barometer.
+struct PressureDetector {
+    estimator: AltitudeEstimator,
+    phase: PressurePhase,
+    debounce: Debounce,
+    /// The time and value of the highest altitude.
+    max_altitude: (f64, f64),
+    /// The time and value of the highest velocity.
+    max_velocity: (f64, f64),
+    detections: Vec<(EventKind, f64)>,
+}
+
+impl PressureDetector {
+    fn new(estimator: AltitudeEstimator) -> Self {
+        Self {
+            estimator,
+            phase: PressurePhase::Pad,
+            debounce: Debounce::default(),
+            max_altitude: (0.0, f64::NEG_INFINITY),
+            max_velocity: (0.0, f64::NEG_INFINITY),
+            detections: vec![],
+        }
+    }
+
+    fn update(&mut self, sample: AltitudeSample, options: &EventOptions) {
+        let AltitudeSample {
+            time,
+            altitude,
+            vertical_velocity: velocity,
+        } = sample;
+
+        match self.phase {
+            PressurePhase::Pad => {
+                let launched = altitude > options.launch_altitude;
+                if let Some(start) = self.debounce.update(launched, time, options.debounce) {
+                    self.detections.push((EventKind::Launch, start));
+                    self.phase = PressurePhase::Ascent;
+                    self.max_altitude = (time, altitude);
+                    self.max_velocity = (time, velocity);
+                }
+            }
+            PressurePhase::Ascent => {
+                if altitude > self.max_altitude.1 {
+                    self.max_altitude = (time, altitude);
+                }
+                // The rocket is fastest when the motor burns out.
+                if velocity > self.max_velocity.1 {
+                    self.max_velocity = (time, velocity);
+                }
+
+                if altitude < self.max_altitude.1 - options.apogee_descent {
+                    self.detections
+                        .push((EventKind::Burnout, self.max_velocity.0));
+                    self.detections
+                        .push((EventKind::Apogee, self.max_altitude.0));
+                    self.phase = PressurePhase::Descent;
+                }
+            }
+            PressurePhase::Descent => {
+                let still = velocity.abs() < options.landing_velocity;
+                if let Some(start) = self.debounce.update(still, time, options.landing_window) {
+                    self.detections.push((EventKind::Landing, start));
+                    self.phase = PressurePhase::Landed;
+                }
+            }
+            PressurePhase::Landed => {}
+        }
+    }
+}
+
+/// Detects the events of a flight, such as launch and apogee.
+///
+/// The acceleration and the pressure are used separately to detect the
+/// events they can, and their results are combined into a timeline where the
+/// confidence of each event is how many of them agree. Packets are added one
+/// at a time with [`EventDetector::update`].
+///
+/// The detection assumes the rocket flies mostly vertically and that the log
+/// starts on the ground. The altitude is found with an [`AltitudeEstimator`].
+pub struct EventDetector {
+    clock: PacketClock,
+    options: EventOptions,
+    acceleration: Option<AccelerationDetector>,
+    pressure: Option<PressureDetector>,
+}
+
+impl EventDetector {
+    /// Create a detector for packets using the given rocket configuration.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the config doesn't have a time value, or has
+    /// neither an acceleration nor a pressure channel.
+    pub fn new(config: &RocketConfig, options: EventOptions) -> Result<Self, String> {
+        let clock = PacketClock::new(config);
+        if !clock.has_source() {
+            return Err("No value has the time role".to_string());
+        }
+
+        let acceleration = options
+            .acceleration
+            .or_else(|| Channel::axial_acceleration(config));
+        let altitude_options = AltitudeOptions {
+            pressure: options.pressure,
+            launch_altitude: options.launch_altitude,
+            debounce: options.debounce,
+            smoothing: options.smoothing,
+            ..Default::default()
+        };
+        let pressure = AltitudeEstimator::new(config, altitude_options).ok();
+
+        if acceleration.is_none() && pressure.is_none() {
+            return Err(
+                "No value has the pressure role or an acceleration role along the nose axis"
+                    .to_string(),
+            );
+        }
+
+        Ok(Self {
+            clock,
+            options,
+            acceleration: acceleration.map(AccelerationDetector::new),
+            pressure: pressure.map(PressureDetector::new),
+        })
+    }
+
+    /// Add the next packet in the log.
+    pub fn update(&mut self, packet: &Packet) {
+        let Some(time) = self.clock.update(packet) else {
+            return;
+        };
+
+        if let Some(detector) = self.acceleration.as_mut() {
+            if let Some(acceleration) = detector.channel.read(packet) {
+                detector.update(time, acceleration, &self.options);
+            }
+        }
+        if let Some(detector) = self.pressure.as_mut() {
+            if let Some(sample) = detector.estimator.update(packet) {
+                detector.update(sample, &self.options);
+            }
+        }
+    }
+
+    /// The events detected so far, in the order they happen in a flight.
+    pub fn events(&self) -> Vec<FlightEvent> {
+        let find = |detections: &[(EventKind, f64)], kind: EventKind| {
+            detections
+                .iter()
+                .find(|(k, _)| *k == kind)
+                .map(|(_, time)| *time)
+        };
+
+        EventKind::ALL
+            .into_iter()
+            .filter_map(|kind| {
+                let by_acceleration = self
+                    .acceleration
+                    .as_ref()
+                    .filter(|_| kind.seen_by_acceleration())
+                    .map(|detector| find(&detector.detections, kind));
+                let by_pressure = self
+                    .pressure
+                    .as_ref()
+                    .filter(|_| kind.seen_by_pressure())
+                    .map(|detector| find(&detector.detections, kind));
+
+                let able = [by_acceleration, by_pressure].iter().flatten().count();
+                let detected = [by_acceleration, by_pressure]
+                    .iter()
+                    .flatten()
+                    .flatten()
+                    .count();
+
+                let (by_acceleration, by_pressure) =
+                    (by_acceleration.flatten(), by_pressure.flatten());
+                let time = match kind.prefers_pressure() {
+                    true => by_pressure.or(by_acceleration),
+                    false => by_acceleration.or(by_pressure),
+                }?;
+
+                Some(FlightEvent {
+                    kind,
+                    time,
+                    confidence: detected as f64 / able as f64,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::atmosphere::{LAPSE_RATE, SEA_LEVEL_PRESSURE, SEA_LEVEL_TEMPERATURE};
+    use crate::configuration::{Axis, Endianess, SensorConfig, ValueConfig, ValueKind, ValueRole};
+    use crate::data::Value;
+
+    use super::*;
+
+    fn value(name: &str, unit: &str, role: ValueRole) -> ValueConfig {
+        ValueConfig {
+            name: name.to_string(),
+            data_type: ValueKind::Float32,
+            unit: Some(unit.to_string()),
+            role: Some(role),
+            filters: vec![],
+        }
+    }
+
+    fn test_config() -> RocketConfig {
+        RocketConfig {
+            name: "test".to_string(),
+            endianess: Endianess::default(),
+            nose_axis: Axis::Z,
+            include: vec![],
+            display_name: None,
+            description: None,
+            kalman: None,
+            sensors: vec![SensorConfig {
+                id: 0,
+                name: "flight".to_string(),
+                values: vec![
+                    value("time", "s", ValueRole::Time),
+                    value("z", "m/s^2", ValueRole::AccelerationZ),
+                    value("pressure", "Pa", ValueRole::Pressure),
+                ],
+            }],
+        }
+    }
+
+    /// The pressure at an altitude above sea level, the inverse of
+    /// [`pressure_altitude`].
+    fn pressure_at(altitude: f64) -> f64 {
+        SEA_LEVEL_PRESSURE
+            * (1.0 - altitude * LAPSE_RATE / SEA_LEVEL_TEMPERATURE).powf(1.0 / 0.190_263)
+    }
+
+    /// The times of the events of a simulated flight.
+    struct Flight {
+        packets: Vec<Packet>,
+        apogee: f64,
+        drogue: f64,
+        main: f64,
+        landing: f64,
+    }
+
+    /// Simulate a flight logged at 100 Hz that launches at 2 s, burns for 2 s,
+    /// deploys the drogue 1 s after apogee and the main at 150 m.
+    fn simulate() -> Flight {
+        let step = 0.01;
+        let (mut altitude, mut velocity) = (0.0, 0.0);
+        let (mut apogee, mut drogue, mut main, mut landing) = (None, None, None, None);
+        let mut packets = vec![];
+
+        for i in 0..8000 {
+            let time = i as f64 * step;
+
+            let in_jolt = |deployment: Option<f64>| deployment.is_some_and(|t| time - t < 0.05);
+            let acceleration = if time < 2.0 || landing.is_some() {
+                STANDARD_GRAVITY
+            } else if time < 4.0 {
+                60.0
+            } else if in_jolt(drogue) || in_jolt(main) {
+                50.0
+            } else if drogue.is_some() {
+                STANDARD_GRAVITY
+            } else {
+                // Drag while coasting.
+                -1.0
+            };
+
+            if (2.0..4.0).contains(&time) || (time >= 4.0 && drogue.is_none()) {
+                velocity += (acceleration - STANDARD_GRAVITY) * step;
+                altitude += velocity * step;
+            } else if landing.is_none() && drogue.is_some() {
+                velocity = if main.is_some() { -5.0 } else { -20.0 };
+                altitude += velocity * step;
+            }
+
+            if time > 4.0 && velocity < 0.0 && apogee.is_none() {
+                apogee = Some(time);
+            }
+            if apogee.is_some_and(|t| time >= t + 1.0) && drogue.is_none() {
+                drogue = Some(time);
+            }
+            if drogue.is_some() && altitude <= 150.0 && main.is_none() {
+                main = Some(time);
+            }
+            if main.is_some() && altitude <= 0.0 && landing.is_none() {
+                altitude = 0.0;
+                landing = Some(time);
+            }
+
+            packets.push(Packet {
+                id: 0,
+                values: vec![
+                    Value {
+                        float_32: time as f32,
+                    },
+                    Value {
+                        float_32: acceleration as f32,
+                    },
+                    Value {
+                        float_32: pressure_at(altitude + 100.0) as f32,
+                    },
+                ],
+            });
+        }
+
+        Flight {
+            packets,
+            apogee: apogee.unwrap(),
+            drogue: drogue.unwrap(),
+            main: main.unwrap(),
+            landing: landing.unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_detect_events() {
+        let flight = simulate();
+        let mut detector = EventDetector::new(&test_config(), EventOptions::default()).unwrap();
+        for packet in flight.packets.iter() {
+            detector.update(packet);
+        }
+
+        let events = detector.events();
+        let kinds: Vec<EventKind> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, EventKind::ALL);
+
+        let expected = [
+            2.0,
+            4.0,
+            flight.apogee,
+            flight.drogue,
+            flight.main,
+            flight.landing,
+        ];
+        let tolerance = [0.02, 0.02, 0.5, 0.02, 0.02, 1.0];
+        for ((event, expected), tolerance) in events.iter().zip(expected).zip(tolerance) {
+            assert!(
+                (event.time - expected).abs() < tolerance,
+                "{} at {} instead of {expected}",
+                event.kind,
+                event.time
+            );
+            assert_eq!(event.confidence, 1.0, "{}", event.kind);
+        }
+    }
+
+    #[test]
+    fn test_detect_events_without_pressure() {
+        let mut config = test_config();
+        config.sensors[0].values[2].role = None;
+
+        let flight = simulate();
+        let mut detector = EventDetector::new(&config, EventOptions::default()).unwrap();
+        for packet in flight.packets.iter() {
+            detector.update(packet);
+        }
+
+        let events = detector.events();
+        let kinds: Vec<EventKind> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, &EventKind::ALL[..5]);
+        assert!(events.iter().all(|e| e.confidence == 1.0));
+    }
+
+    #[test]
+    fn test_detector_needs_channels() {
+        let mut config = test_config();
+        config.sensors[0].values[1].role = None;
+        config.sensors[0].values[2].role = None;
+
+        assert!(EventDetector::new(&config, EventOptions::default()).is_err());
+
+        let options = EventOptions {
+            pressure: Channel::from_name(&config, "flight.pressure", ValueRole::Pressure),
+            ..Default::default()
+        };
+        assert!(EventDetector::new(&config, options).is_ok());
+    }
+}
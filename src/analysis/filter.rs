@@ -0,0 +1,555 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use crate::configuration::FilterConfig;
+
+/// A filter that is given the samples of a value one at a time.
+///
+/// NaN and infinite samples are passed through without changing the state of
+/// the filter, so a single bad sample doesn't spread to the ones after it.
+pub trait Filter {
+    /// Add the next sample and get the filtered value.
+    fn apply(&mut self, sample: f64) -> f64;
+}
+
+/// Create the filter described by a config.
+///
+/// The config should be checked with [`FilterConfig::validate`] first, as
+/// filters with invalid parameters produce NaN or pass samples through
+/// unchanged.
+pub fn from_config(config: &FilterConfig) -> Box<dyn Filter> {
+    match *config {
+        FilterConfig::MovingAverage { window } => Box::new(MovingAverage::new(window)),
+        FilterConfig::Median { window } => Box::new(Median::new(window)),
+        FilterConfig::Despike { window, threshold } => Box::new(Despike::new(window, threshold)),
+        FilterConfig::Exponential { alpha } => Box::new(Exponential::new(alpha)),
+        FilterConfig::LowPass {
+            cutoff,
+            sample_rate,
+        } => Box::new(Biquad::low_pass(cutoff, sample_rate)),
+        FilterConfig::HighPass {
+            cutoff,
+            sample_rate,
+        } => Box::new(Biquad::high_pass(cutoff, sample_rate)),
+        FilterConfig::SavitzkyGolay {
+            window,
+            order,
+            derivative,
+            sample_rate,
+        } => Box::new(SavitzkyGolay::new(window, order, derivative, sample_rate)),
+    }
+}
+
+/// Several filters applied one after the other.
+#[derive(Default)]
+pub struct FilterChain {
+    filters: Vec<Box<dyn Filter>>,
+}
+
+impl FilterChain {
+    /// Create the chain of filters described by the configs, in order.
+    pub fn new(configs: &[FilterConfig]) -> Self {
+        Self {
+            filters: configs.iter().map(from_config).collect(),
+        }
+    }
+
+    /// Add a filter to the end of the chain.
+    pub fn push(&mut self, filter: Box<dyn Filter>) {
+        self.filters.push(filter);
+    }
+
+    /// Whether the chain has no filters, so samples are not changed.
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+}
+
+impl Filter for FilterChain {
+    fn apply(&mut self, sample: f64) -> f64 {
+        self.filters
+            .iter_mut()
+            .fold(sample, |sample, filter| filter.apply(sample))
+    }
+}
+
+/// The most recent samples, up to a fixed number.
+struct Window {
+    samples: VecDeque<f64>,
+    size: usize,
+}
+
+impl Window {
+    fn new(size: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(size),
+            size: size.max(1),
+        }
+    }
+
+    fn push(&mut self, sample: f64) {
+        if self.samples.len() == self.size {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    fn median(&self) -> f64 {
+        let mut sorted: Vec<f64> = self.samples.iter().copied().collect();
+        median_of(&mut sorted)
+    }
+}
+
+/// The median of some samples, which must not contain NaN.
+fn median_of(samples: &mut [f64]) -> f64 {
+    samples.sort_by(f64::total_cmp);
+    let middle = samples.len() / 2;
+    match samples.len() % 2 {
+        0 => (samples[middle - 1] + samples[middle]) / 2.0,
+        _ => samples[middle],
+    }
+}
+
+/// The mean of the most recent samples, or of every sample until there are
+/// enough to fill the window.
+pub struct MovingAverage {
+    window: Window,
+    sum: f64,
+}
+
+impl MovingAverage {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: Window::new(window),
+            sum: 0.0,
+        }
+    }
+}
+
+impl Filter for MovingAverage {
+    fn apply(&mut self, sample: f64) -> f64 {
+        if !sample.is_finite() {
+            return sample;
+        }
+
+        if self.window.samples.len() == self.window.size {
+            self.sum -= self.window.samples[0];
+        }
+        self.window.push(sample);
+        self.sum += sample;
+
+        self.sum / self.window.samples.len() as f64
+    }
+}
+
+/// The median of the most recent samples.
+pub struct Median {
+    window: Window,
+}
+
+impl Median {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: Window::new(window),
+        }
+    }
+}
+
+impl Filter for Median {
+    fn apply(&mut self, sample: f64) -> f64 {
+        if !sample.is_finite() {
+            return sample;
+        }
+
+        self.window.push(sample);
+        self.window.median()
+    }
+}
+
+/// Replaces samples far from the median of the most recent samples with the
+/// median, and leaves the rest unchanged.
+///
+/// The spread of the samples is estimated from their median absolute
+/// deviation, so the spikes being removed don't affect it.
+pub struct Despike {
+    window: Window,
+    threshold: f64,
+}
+
+impl Despike {
+    /// The ratio of the standard deviation to the median absolute deviation
+    /// of normally distributed samples.
+    const MAD_SCALE: f64 = 1.4826;
+
+    pub fn new(window: usize, threshold: f64) -> Self {
+        Self {
+            window: Window::new(window),
+            threshold,
+        }
+    }
+}
+
+impl Filter for Despike {
+    fn apply(&mut self, sample: f64) -> f64 {
+        if !sample.is_finite() {
+            return sample;
+        }
+
+        self.window.push(sample);
+        let median = self.window.median();
+        let mut deviations: Vec<f64> = self
+            .window
+            .samples
+            .iter()
+            .map(|s| (s - median).abs())
+            .collect();
+        let spread = Self::MAD_SCALE * median_of(&mut deviations);
+
+        match (sample - median).abs() > self.threshold * spread {
+            true => median,
+            false => sample,
+        }
+    }
+}
+
+/// An exponential moving average, starting from the first sample.
+pub struct Exponential {
+    alpha: f64,
+    value: Option<f64>,
+}
+
+impl Exponential {
+    pub fn new(alpha: f64) -> Self {
+        Self { alpha, value: None }
+    }
+}
+
+impl Filter for Exponential {
+    fn apply(&mut self, sample: f64) -> f64 {
+        if !sample.is_finite() {
+            return sample;
+        }
+
+        let value = match self.value {
+            Some(value) => value + self.alpha * (sample - value),
+            None => sample,
+        };
+        self.value = Some(value);
+        value
+    }
+}
+
+/// A second order IIR filter.
+///
+/// The filter starts as if every sample before the first was the same as
+/// it, so there is no jump at the start of the log.
+pub struct Biquad {
+    /// The feedforward coefficients.
+    b: [f64; 3],
+    /// The feedback coefficients, without the leading 1.
+    a: [f64; 2],
+    /// The previous two inputs and outputs, once a sample has been added.
+    history: Option<([f64; 2], [f64; 2])>,
+}
+
+impl Biquad {
+    /// The quality factor of a second order Butterworth filter.
+    const BUTTERWORTH_Q: f64 = std::f64::consts::FRAC_1_SQRT_2;
+
+    /// A Butterworth low-pass filter.
+    pub fn low_pass(cutoff: f64, sample_rate: f64) -> Self {
+        let (cos, alpha) = Self::prewarp(cutoff, sample_rate);
+        let b1 = 1.0 - cos;
+        Self::normalized(
+            [b1 / 2.0, b1, b1 / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// A Butterworth high-pass filter.
+    pub fn high_pass(cutoff: f64, sample_rate: f64) -> Self {
+        let (cos, alpha) = Self::prewarp(cutoff, sample_rate);
+        let b1 = 1.0 + cos;
+        Self::normalized(
+            [b1 / 2.0, -b1, b1 / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// The cosine of the cutoff as an angle per sample, and the bandwidth
+    /// term of the filter.
+    fn prewarp(cutoff: f64, sample_rate: f64) -> (f64, f64) {
+        let omega = 2.0 * PI * cutoff / sample_rate;
+        (omega.cos(), omega.sin() / (2.0 * Self::BUTTERWORTH_Q))
+    }
+
+    fn normalized(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self {
+            b: b.map(|b| b / a[0]),
+            a: [a[1] / a[0], a[2] / a[0]],
+            history: None,
+        }
+    }
+
+    /// The output for a constant input of 1.
+    fn dc_gain(&self) -> f64 {
+        self.b.iter().sum::<f64>() / (1.0 + self.a[0] + self.a[1])
+    }
+}
+
+impl Filter for Biquad {
+    fn apply(&mut self, sample: f64) -> f64 {
+        if !sample.is_finite() {
+            return sample;
+        }
+
+        let steady = sample * self.dc_gain();
+        let (inputs, outputs) = self.history.get_or_insert(([sample; 2], [steady; 2]));
+
+        let output = self.b[0] * sample + self.b[1] * inputs[0] + self.b[2] * inputs[1]
+            - self.a[0] * outputs[0]
+            - self.a[1] * outputs[1];
+
+        *inputs = [sample, inputs[0]];
+        *outputs = [output, outputs[0]];
+        output
+    }
+}
+
+/// Fits a polynomial to the most recent samples by least squares and takes
+/// its value or a derivative at the latest sample.
+///
+/// Until the window is full, the first sample is repeated to fill it.
+pub struct SavitzkyGolay {
+    /// The weights of the samples in the window, oldest first.
+    weights: Vec<f64>,
+    window: Window,
+}
+
+impl SavitzkyGolay {
+    /// Create a filter fitting a polynomial of the given order to a window of
+    /// samples.
+    ///
+    /// Derivatives are per second if the sample rate is in hertz. The order
+    /// must be less than the window and at least the derivative, otherwise
+    /// every output is NaN.
+    pub fn new(window: usize, order: usize, derivative: usize, sample_rate: f64) -> Self {
+        let window = window.max(1);
+        let weights = match order < window && derivative <= order {
+            true => Self::weights(window, order, derivative, sample_rate),
+            false => vec![f64::NAN; window],
+        };
+
+        Self {
+            weights,
+            window: Window::new(window),
+        }
+    }
+
+    /// The weights that give the derivative of the fitted polynomial at the
+    /// latest sample.
+    ///
+    /// The samples are at times `-(window - 1)..=0`. The coefficients of the
+    /// polynomial are `(AᵀA)⁻¹Aᵀy`, where `A` has the powers of the times,
+    /// and the derivative at 0 is its coefficient times the factorial.
+    fn weights(window: usize, order: usize, derivative: usize, sample_rate: f64) -> Vec<f64> {
+        let terms = order + 1;
+        let times: Vec<f64> = (0..window)
+            .map(|i| i as f64 - (window - 1) as f64)
+            .collect();
+        let powers: Vec<Vec<f64>> = times
+            .iter()
+            .map(|t| (0..terms).map(|k| t.powi(k as i32)).collect())
+            .collect();
+
+        // The normal equations, with one right hand side per sample.
+        let mut normal = vec![vec![0.0; terms + window]; terms];
+        for (row, normal_row) in normal.iter_mut().enumerate() {
+            for column in 0..terms {
+                normal_row[column] = powers.iter().map(|p| p[row] * p[column]).sum();
+            }
+            for (sample, p) in powers.iter().enumerate() {
+                normal_row[terms + sample] = p[row];
+            }
+        }
+        solve(&mut normal, terms);
+
+        let factorial: f64 = (1..=derivative).map(|i| i as f64).product();
+        let scale = factorial * sample_rate.powi(derivative as i32);
+        normal[derivative][terms..]
+            .iter()
+            .map(|weight| weight * scale)
+            .collect()
+    }
+}
+
+impl Filter for SavitzkyGolay {
+    fn apply(&mut self, sample: f64) -> f64 {
+        if !sample.is_finite() {
+            return sample;
+        }
+
+        if self.window.samples.is_empty() {
+            for _ in 1..self.window.size {
+                self.window.push(sample);
+            }
+        }
+        self.window.push(sample);
+
+        self.window
+            .samples
+            .iter()
+            .zip(&self.weights)
+            .map(|(sample, weight)| sample * weight)
+            .sum()
+    }
+}
+
+/// Solve a system of equations in place with Gauss-Jordan elimination.
+///
+/// The first `size` columns of the matrix are the system, and the rest are
+/// right hand sides, which are replaced with the solutions.
+fn solve(matrix: &mut [Vec<f64>], size: usize) {
+    for pivot in 0..size {
+        let best = (pivot..size)
+            .max_by(|&a, &b| matrix[a][pivot].abs().total_cmp(&matrix[b][pivot].abs()))
+            .unwrap_or(pivot);
+        matrix.swap(pivot, best);
+
+        let divisor = matrix[pivot][pivot];
+        for value in matrix[pivot].iter_mut() {
+            *value /= divisor;
+        }
+
+        for row in 0..size {
+            if row == pivot {
+                continue;
+            }
+            let factor = matrix[row][pivot];
+            let pivot_row = matrix[pivot].clone();
+            for (value, pivot_value) in matrix[row].iter_mut().zip(pivot_row) {
+                *value -= factor * pivot_value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(filter: &mut dyn Filter, samples: &[f64]) -> Vec<f64> {
+        samples.iter().map(|&s| filter.apply(s)).collect()
+    }
+
+    #[test]
+    fn test_moving_average_and_median() {
+        let samples = [1.0, 2.0, 3.0, 100.0, 5.0];
+
+        assert_eq!(
+            run(&mut MovingAverage::new(2), &samples),
+            [1.0, 1.5, 2.5, 51.5, 52.5]
+        );
+        assert_eq!(
+            run(&mut Median::new(3), &samples),
+            [1.0, 1.5, 2.0, 3.0, 5.0]
+        );
+        assert_eq!(
+            run(&mut Exponential::new(0.5), &samples),
+            [1.0, 1.5, 2.25, 51.125, 28.0625]
+        );
+    }
+
+    #[test]
+    fn test_despike() {
+        let samples = [1.0, 1.1, 0.9, 1.0, 50.0, 1.1, 0.9, 1.0];
+        let result = run(&mut Despike::new(5, 3.0), &samples);
+
+        assert_eq!(result[4], 1.0);
+        assert_eq!(result[..4], samples[..4]);
+        assert_eq!(result[5..], samples[5..]);
+    }
+
+    #[test]
+    fn test_nan_passes_through() {
+        let mut filter = MovingAverage::new(2);
+
+        assert_eq!(filter.apply(1.0), 1.0);
+        assert!(filter.apply(f64::NAN).is_nan());
+        assert_eq!(filter.apply(3.0), 2.0);
+    }
+
+    #[test]
+    fn test_infinity_passes_through() {
+        let filters: [Box<dyn Filter>; 4] = [
+            Box::new(MovingAverage::new(2)),
+            Box::new(Exponential::new(0.5)),
+            Box::new(Biquad::low_pass(5.0, 100.0)),
+            Box::new(SavitzkyGolay::new(3, 1, 0, 1.0)),
+        ];
+
+        for mut filter in filters {
+            filter.apply(1.0);
+            assert_eq!(filter.apply(f64::INFINITY), f64::INFINITY);
+            for _ in 0..5 {
+                assert!(filter.apply(1.0).is_finite());
+            }
+        }
+    }
+
+    #[test]
+    fn test_butterworth() {
+        let (sample_rate, cutoff) = (100.0, 5.0);
+        let sine = |frequency: f64| -> Vec<f64> {
+            (0..1000)
+                .map(|i| 1.0 + (2.0 * PI * frequency * i as f64 / sample_rate).sin())
+                .collect()
+        };
+        let amplitude = |samples: &[f64]| {
+            let tail = &samples[500..];
+            let max = tail.iter().copied().fold(f64::MIN, f64::max);
+            let min = tail.iter().copied().fold(f64::MAX, f64::min);
+            (max - min) / 2.0
+        };
+
+        let low = run(&mut Biquad::low_pass(cutoff, sample_rate), &sine(0.5));
+        assert!((amplitude(&low) - 1.0).abs() < 0.01);
+        assert!((low[0] - 1.0).abs() < 1e-12);
+
+        let low = run(&mut Biquad::low_pass(cutoff, sample_rate), &sine(25.0));
+        assert!(amplitude(&low) < 0.05);
+
+        let high = run(&mut Biquad::high_pass(cutoff, sample_rate), &sine(25.0));
+        assert!((amplitude(&high) - 1.0).abs() < 0.05);
+        // The constant offset is removed.
+        assert!(high[500..].iter().sum::<f64>().abs() / 500.0 < 0.05);
+    }
+
+    #[test]
+    fn test_savitzky_golay() {
+        // A quadratic is fitted exactly by a second order polynomial.
+        let samples: Vec<f64> = (0..20).map(|i| 0.5 * (i as f64 / 10.0).powi(2)).collect();
+
+        let smoothed = run(&mut SavitzkyGolay::new(7, 2, 0, 10.0), &samples);
+        assert!((smoothed[19] - samples[19]).abs() < 1e-9);
+
+        let velocity = run(&mut SavitzkyGolay::new(7, 2, 1, 10.0), &samples);
+        assert!((velocity[19] - 1.9).abs() < 1e-9);
+
+        let acceleration = run(&mut SavitzkyGolay::new(7, 2, 2, 10.0), &samples);
+        assert!((acceleration[19] - 1.0).abs() < 1e-9);
+
+        assert!(SavitzkyGolay::new(3, 3, 0, 1.0).apply(1.0).is_nan());
+    }
+
+    #[test]
+    fn test_filter_chain() {
+        let configs = [
+            "median,window=3".parse().unwrap(),
+            "moving_average,window=2".parse().unwrap(),
+        ];
+        let mut chain = FilterChain::new(&configs);
+
+        assert_eq!(run(&mut chain, &[1.0, 9.0, 3.0, 5.0]), [1.0, 3.0, 4.0, 4.0]);
+    }
+}
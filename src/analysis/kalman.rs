@@ -0,0 +1,446 @@
+use crate::analysis::altitude::{AltitudeEstimator, AltitudeOptions};
+use crate::analysis::Channel;
+use crate::configuration::{KalmanConfig, RocketConfig, STANDARD_GRAVITY};
+use crate::data::Packet;
+use crate::time::PacketClock;
+
+/// The index of the altitude in the state.
+const ALTITUDE: usize = 0;
+/// The index of the vertical velocity in the state.
+const VELOCITY: usize = 1;
+/// The index of the vertical acceleration in the state.
+const ACCELERATION: usize = 2;
+
+type Matrix = [[f64; 3]; 3];
+
+/// Options for the Kalman filter.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KalmanOptions {
+    /// The acceleration towards the nose of the rocket, including gravity.
+    ///
+    /// This is found with [`Channel::axial_acceleration`] if not set.
+    pub acceleration: Option<Channel>,
+    /// The static pressure.
+    ///
+    /// This is found with the
+    /// [`ValueRole::Pressure`](crate::configuration::ValueRole::Pressure) role
+    /// if not set.
+    pub pressure: Option<Channel>,
+    /// The noise of the filter.
+    ///
+    /// This is taken from [`RocketConfig::kalman`] if not set, and the
+    /// defaults of [`KalmanConfig`] are used if the config doesn't have it
+    /// either.
+    pub noise: Option<KalmanConfig>,
+}
+
+/// The estimated vertical motion of the rocket at a single time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KalmanState {
+    /// The time of the estimate in seconds.
+    pub time: f64,
+    /// The altitude above the ground in metres.
+    pub altitude: f64,
+    /// The vertical velocity in metres per second.
+    pub velocity: f64,
+    /// The vertical acceleration in metres per second squared, without
+    /// gravity.
+    pub acceleration: f64,
+    /// The standard deviation of the altitude estimate in metres.
+    pub altitude_std_dev: f64,
+    /// The standard deviation of the velocity estimate in metres per second.
+    pub velocity_std_dev: f64,
+}
+
+/// Estimates the altitude, velocity and acceleration of the rocket by fusing
+/// the barometer and the accelerometer.
+///
+/// This is a one dimensional filter along the vertical, using a constant
+/// acceleration model. The altitude from the barometer is found with an
+/// [`AltitudeEstimator`] without smoothing, and the acceleration towards the
+/// nose is taken as vertical, so the estimate is only good while the rocket
+/// flies mostly vertically. Either sensor can be missing, but the velocity
+/// drifts without a barometer and reacts slowly without an accelerometer.
+///
+/// Packets are added one at a time with [`KalmanFilter::update`].
+pub struct KalmanFilter {
+    clock: PacketClock,
+    noise: KalmanConfig,
+    acceleration: Option<Channel>,
+    altitude: Option<AltitudeEstimator>,
+    /// The altitude, velocity and acceleration.
+    state: [f64; 3],
+    covariance: Matrix,
+    /// The time of the state.
+    time: Option<f64>,
+    apogee: Option<KalmanState>,
+}
+
+impl KalmanFilter {
+    /// Create a filter for packets using the given rocket configuration.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the config doesn't have a time value, has
+    /// neither an acceleration nor a pressure channel, or the noise isn't
+    /// positive.
+    pub fn new(config: &RocketConfig, options: KalmanOptions) -> Result<Self, String> {
+        let clock = PacketClock::new(config);
+        if !clock.has_source() {
+            return Err("No value has the time role".to_string());
+        }
+
+        let acceleration = options
+            .acceleration
+            .or_else(|| Channel::axial_acceleration(config));
+        let altitude_options = AltitudeOptions {
+            pressure: options.pressure,
+            smoothing: 0.0,
+            ..Default::default()
+        };
+        let altitude = AltitudeEstimator::new(config, altitude_options).ok();
+
+        if acceleration.is_none() && altitude.is_none() {
+            return Err(
+                "No value has the pressure role or an acceleration role along the nose axis"
+                    .to_string(),
+            );
+        }
+
+        let noise = options.noise.or(config.kalman).unwrap_or_default();
+        noise.validate()?;
+
+        Ok(Self {
+            clock,
+            noise,
+            acceleration,
+            altitude,
+            state: [0.0; 3],
+            // The rocket starts on the ground, so only the altitude is
+            // uncertain until the barometer is read.
+            covariance: diagonal([noise.pressure_noise.powi(2), 1.0, 1.0]),
+            time: None,
+            apogee: None,
+        })
+    }
+
+    /// Add the next packet in the log.
+    ///
+    /// # Returns
+    ///
+    /// The estimate, if the packet has a pressure or acceleration reading and
+    /// a time has been read.
+    pub fn update(&mut self, packet: &Packet) -> Option<KalmanState> {
+        let time = self.clock.update(packet);
+        let altitude = self
+            .altitude
+            .as_mut()
+            .and_then(|estimator| estimator.update(packet))
+            .map(|sample| sample.altitude);
+        let acceleration = self
+            .acceleration
+            .and_then(|channel| channel.read(packet))
+            .map(|acceleration| acceleration - STANDARD_GRAVITY);
+        let time = time?;
+
+        if altitude.is_none() && acceleration.is_none() {
+            return None;
+        }
+
+        self.predict(time);
+        if let Some(altitude) = altitude {
+            self.correct(ALTITUDE, altitude, self.noise.pressure_noise.powi(2));
+        }
+        if let Some(acceleration) = acceleration {
+            self.correct(
+                ACCELERATION,
+                acceleration,
+                self.noise.acceleration_noise.powi(2),
+            );
+        }
+
+        let state = self.state(time);
+        if self
+            .apogee
+            .is_none_or(|apogee| state.altitude > apogee.altitude)
+        {
+            self.apogee = Some(state);
+        }
+
+        Some(state)
+    }
+
+    /// Move the state forward to the given time.
+    fn predict(&mut self, time: f64) {
+        let previous = self.time.replace(time);
+        // Time going backwards means the clock was reset, so the state is
+        // kept as it is.
+        let Some(dt) = previous.map(|p| time - p).filter(|&dt| dt > 0.0) else {
+            return;
+        };
+
+        let transition = [[1.0, dt, dt * dt / 2.0], [0.0, 1.0, dt], [0.0, 0.0, 1.0]];
+        self.state = multiply_vector(&transition, &self.state);
+
+        // The acceleration changes by white noise, integrated over the step.
+        let q = self.noise.process_noise.powi(2);
+        let (dt2, dt3) = (dt * dt, dt * dt * dt);
+        let process = [
+            [dt3 * dt2 / 20.0, dt2 * dt2 / 8.0, dt3 / 6.0],
+            [dt2 * dt2 / 8.0, dt3 / 3.0, dt2 / 2.0],
+            [dt3 / 6.0, dt2 / 2.0, dt],
+        ];
+
+        let predicted = multiply(
+            &multiply(&transition, &self.covariance),
+            &transpose(&transition),
+        );
+        for (i, row) in self.covariance.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = predicted[i][j] + q * process[i][j];
+            }
+        }
+    }
+
+    /// Correct the state with a measurement of one of its elements.
+    fn correct(&mut self, index: usize, measurement: f64, variance: f64) {
+        let innovation = measurement - self.state[index];
+        let innovation_variance = self.covariance[index][index] + variance;
+        // The gain can't be computed, so the measurement is ignored.
+        if !(innovation_variance > 0.0 && innovation_variance.is_finite()) {
+            return;
+        }
+        let gain = self.covariance.map(|row| row[index] / innovation_variance);
+
+        for (value, gain) in self.state.iter_mut().zip(gain) {
+            *value += gain * innovation;
+        }
+
+        let measured_row = self.covariance[index];
+        for (row, gain) in self.covariance.iter_mut().zip(gain) {
+            for (value, measured) in row.iter_mut().zip(measured_row) {
+                *value -= gain * measured;
+            }
+        }
+    }
+
+    fn state(&self, time: f64) -> KalmanState {
+        KalmanState {
+            time,
+            altitude: self.state[ALTITUDE],
+            velocity: self.state[VELOCITY],
+            acceleration: self.state[ACCELERATION],
+            altitude_std_dev: self.covariance[ALTITUDE][ALTITUDE].max(0.0).sqrt(),
+            velocity_std_dev: self.covariance[VELOCITY][VELOCITY].max(0.0).sqrt(),
+        }
+    }
+
+    /// The noise used by the filter.
+    pub fn noise(&self) -> &KalmanConfig {
+        &self.noise
+    }
+
+    /// The most recent estimate, once a time and a measurement have been read.
+    pub fn current(&self) -> Option<KalmanState> {
+        self.time.map(|time| self.state(time))
+    }
+
+    /// The estimate with the highest altitude.
+    pub fn apogee(&self) -> Option<KalmanState> {
+        self.apogee
+    }
+}
+
+fn diagonal(values: [f64; 3]) -> Matrix {
+    let mut matrix = [[0.0; 3]; 3];
+    for (i, value) in values.into_iter().enumerate() {
+        matrix[i][i] = value;
+    }
+    matrix
+}
+
+fn transpose(matrix: &Matrix) -> Matrix {
+    let mut result = [[0.0; 3]; 3];
+    for (i, row) in matrix.iter().enumerate() {
+        for (j, value) in row.iter().enumerate() {
+            result[j][i] = *value;
+        }
+    }
+    result
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut result = [[0.0; 3]; 3];
+    for (i, row) in result.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    result
+}
+
+fn multiply_vector(matrix: &Matrix, vector: &[f64; 3]) -> [f64; 3] {
+    matrix.map(|row| row.iter().zip(vector).map(|(a, b)| a * b).sum())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::atmosphere::{LAPSE_RATE, SEA_LEVEL_PRESSURE, SEA_LEVEL_TEMPERATURE};
+    use crate::configuration::{Axis, Endianess, SensorConfig, ValueConfig, ValueKind, ValueRole};
+    use crate::data::Value;
+
+    use super::*;
+
+    fn value(name: &str, unit: &str, role: ValueRole) -> ValueConfig {
+        ValueConfig {
+            name: name.to_string(),
+            data_type: ValueKind::Float32,
+            unit: Some(unit.to_string()),
+            role: Some(role),
+            filters: vec![],
+        }
+    }
+
+    fn test_config() -> RocketConfig {
+        RocketConfig {
+            name: "test".to_string(),
+            endianess: Endianess::default(),
+            nose_axis: Axis::default(),
+            include: vec![],
+            display_name: None,
+            description: None,
+            kalman: None,
+            sensors: vec![SensorConfig {
+                id: 0,
+                name: "flight".to_string(),
+                values: vec![
+                    value("time", "s", ValueRole::Time),
+                    value("z", "m/s^2", ValueRole::AccelerationZ),
+                    value("pressure", "Pa", ValueRole::Pressure),
+                ],
+            }],
+        }
+    }
+
+    fn pressure_at(altitude: f64) -> f64 {
+        SEA_LEVEL_PRESSURE
+            * (1.0 - altitude * LAPSE_RATE / SEA_LEVEL_TEMPERATURE).powf(1.0 / 0.190_263)
+    }
+
+    /// The true altitude, velocity and acceleration of a flight with two
+    /// seconds on the pad and a two second burn, without drag.
+    fn trajectory(time: f64) -> (f64, f64, f64) {
+        const THRUST: f64 = 50.0;
+        let burnout_velocity = THRUST * 2.0;
+        let burnout_altitude = THRUST * 2.0;
+
+        match time {
+            t if t < 2.0 => (0.0, 0.0, 0.0),
+            t if t < 4.0 => {
+                let t = t - 2.0;
+                (THRUST * t * t / 2.0, THRUST * t, THRUST)
+            }
+            t => {
+                let t = t - 4.0;
+                (
+                    burnout_altitude + burnout_velocity * t - STANDARD_GRAVITY * t * t / 2.0,
+                    burnout_velocity - STANDARD_GRAVITY * t,
+                    -STANDARD_GRAVITY,
+                )
+            }
+        }
+    }
+
+    #[test]
+    fn test_kalman_filter_tracks_flight() {
+        let mut filter = KalmanFilter::new(&test_config(), Default::default()).unwrap();
+
+        // A deterministic stand in for the noise of the barometer, up to 3 m.
+        let mut seed = 1u32;
+        let mut noise = || {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (seed >> 8) as f64 / (1u32 << 24) as f64 * 6.0 - 3.0
+        };
+
+        let mut last = None;
+        for i in 0..1200 {
+            let time = i as f64 * 0.01;
+            let (altitude, _, acceleration) = trajectory(time);
+            let packet = Packet {
+                id: 0,
+                values: vec![
+                    Value {
+                        float_32: time as f32,
+                    },
+                    Value {
+                        float_32: (acceleration + STANDARD_GRAVITY) as f32,
+                    },
+                    Value {
+                        float_32: pressure_at(altitude + noise()) as f32,
+                    },
+                ],
+            };
+            last = filter.update(&packet);
+        }
+
+        let last = last.unwrap();
+        let (altitude, velocity, acceleration) = trajectory(last.time);
+        assert!((last.altitude - altitude).abs() < 2.0, "{last:?}");
+        assert!((last.velocity - velocity).abs() < 1.0, "{last:?}");
+        assert!((last.acceleration - acceleration).abs() < 0.5, "{last:?}");
+        assert!(last.altitude_std_dev < filter.noise().pressure_noise);
+
+        // The true apogee is at 14.2 s, after the end of the log.
+        let apogee = filter.apogee().unwrap();
+        assert_eq!(apogee.time, last.time);
+    }
+
+    #[test]
+    fn test_kalman_noise_from_config() {
+        let mut config = test_config();
+        let noise = KalmanConfig {
+            process_noise: 1.0,
+            ..Default::default()
+        };
+        config.kalman = Some(noise);
+
+        let filter = KalmanFilter::new(&config, Default::default()).unwrap();
+        assert_eq!(filter.noise(), &noise);
+
+        let options = KalmanOptions {
+            noise: Some(KalmanConfig::default()),
+            ..Default::default()
+        };
+        let filter = KalmanFilter::new(&config, options).unwrap();
+        assert_eq!(filter.noise(), &KalmanConfig::default());
+    }
+
+    #[test]
+    fn test_kalman_noise_must_be_positive() {
+        let options = KalmanOptions {
+            noise: Some(KalmanConfig {
+                pressure_noise: 0.0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(KalmanFilter::new(&test_config(), options).is_err());
+    }
+
+    #[test]
+    fn test_correct_skips_zero_variance() {
+        let mut filter = KalmanFilter::new(&test_config(), Default::default()).unwrap();
+        filter.covariance = diagonal([0.0; 3]);
+
+        filter.correct(ALTITUDE, 10.0, 0.0);
+        assert_eq!(filter.state, [0.0; 3]);
+    }
+
+    #[test]
+    fn test_kalman_filter_needs_a_sensor() {
+        let mut config = test_config();
+        config.sensors[0].values.truncate(1);
+
+        assert!(KalmanFilter::new(&config, Default::default()).is_err());
+    }
+}
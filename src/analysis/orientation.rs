@@ -0,0 +1,701 @@
+use crate::analysis::Channel;
+use crate::configuration::{RocketConfig, ValueRole, STANDARD_GRAVITY};
+use crate::data::Packet;
+use crate::time::PacketClock;
+
+/// The algorithm used to fuse the sensors into an orientation.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FusionAlgorithm {
+    /// Madgwick's gradient descent filter, with the gain applied to the
+    /// gradient step.
+    ///
+    /// This is the default value.
+    #[default]
+    Madgwick,
+    /// Mahony's complementary filter, with proportional and integral gains
+    /// applied to the error from the references.
+    Mahony,
+}
+
+/// Options for estimating the orientation of the rocket.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrientationOptions {
+    /// The angular velocity about the X, Y and Z axes.
+    ///
+    /// These are found with the [`ValueRole::AngularVelocityX`] roles if not
+    /// set.
+    pub gyro: Option<[Channel; 3]>,
+    /// The acceleration along the X, Y and Z axes, including gravity.
+    ///
+    /// These are found with the [`ValueRole::AccelerationX`] roles if not set.
+    /// A gravity vector from the sensor can be used instead.
+    pub acceleration: Option<[Channel; 3]>,
+    /// The magnetic field along the X, Y and Z axes.
+    ///
+    /// These are found with the [`ValueRole::MagneticFieldX`] roles if not
+    /// set. Without a magnetometer the heading is relative to the heading at
+    /// the start of the log.
+    pub magnetic_field: Option<[Channel; 3]>,
+    /// The filter used to fuse the sensors.
+    pub algorithm: FusionAlgorithm,
+    /// How strongly the references correct the gyroscope, as the Madgwick
+    /// gain or the Mahony proportional gain.
+    pub gain: f64,
+    /// The Mahony integral gain, which corrects the bias of the gyroscope.
+    pub integral_gain: f64,
+    /// How far the magnitude of the acceleration can be from 1 g, as a
+    /// fraction of 1 g, for it to be used as the direction of gravity.
+    ///
+    /// Under thrust or drag the accelerometer doesn't measure gravity, so the
+    /// gyroscope is used alone.
+    pub acceleration_tolerance: f64,
+    /// The tilt from vertical above which the rocket is considered to have
+    /// tumbled, in degrees.
+    pub tumble_tilt: f64,
+}
+
+impl Default for OrientationOptions {
+    fn default() -> Self {
+        Self {
+            gyro: None,
+            acceleration: None,
+            magnetic_field: None,
+            algorithm: FusionAlgorithm::default(),
+            gain: 0.1,
+            integral_gain: 0.0,
+            acceleration_tolerance: 0.1,
+            tumble_tilt: 90.0,
+        }
+    }
+}
+
+/// A rotation, from the axes of the sensors to the world.
+///
+/// The world has Z pointing up and, with a magnetometer, X pointing to
+/// magnetic north.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Quaternion {
+    /// The rotation that does nothing.
+    pub const IDENTITY: Self = Self {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    /// The shortest rotation that turns the direction `from` into `to`.
+    pub fn between(from: [f64; 3], to: [f64; 3]) -> Self {
+        let (from, to) = (normalize(from), normalize(to));
+        let [x, y, z] = cross(from, to);
+        let w = 1.0 + dot(from, to);
+
+        // Opposite directions can rotate about any perpendicular axis.
+        if w < 1e-9 {
+            let axis = match from[0].abs() < 0.9 {
+                true => cross(from, [1.0, 0.0, 0.0]),
+                false => cross(from, [0.0, 1.0, 0.0]),
+            };
+            let [x, y, z] = normalize(axis);
+            return Self { w: 0.0, x, y, z };
+        }
+
+        Self { w, x, y, z }.normalized()
+    }
+
+    /// The Hamilton product of this quaternion and `other`.
+    pub fn multiply(&self, other: &Self) -> Self {
+        Self {
+            w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+            x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+        }
+    }
+
+    /// The quaternion scaled to a length of 1.
+    pub fn normalized(&self) -> Self {
+        let length = (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+        if length == 0.0 {
+            return Self::IDENTITY;
+        }
+
+        Self {
+            w: self.w / length,
+            x: self.x / length,
+            y: self.y / length,
+            z: self.z / length,
+        }
+    }
+
+    /// Rotate a vector from the axes of the sensors to the world.
+    pub fn rotate(&self, vector: [f64; 3]) -> [f64; 3] {
+        let [vx, vy, vz] = vector;
+        let Self { w, x, y, z } = *self;
+
+        [
+            (1.0 - 2.0 * (y * y + z * z)) * vx
+                + 2.0 * (x * y - w * z) * vy
+                + 2.0 * (x * z + w * y) * vz,
+            2.0 * (x * y + w * z) * vx
+                + (1.0 - 2.0 * (x * x + z * z)) * vy
+                + 2.0 * (y * z - w * x) * vz,
+            2.0 * (x * z - w * y) * vx
+                + 2.0 * (y * z + w * x) * vy
+                + (1.0 - 2.0 * (x * x + y * y)) * vz,
+        ]
+    }
+
+    /// The roll, pitch and yaw in degrees, applied about the X, Y and Z axes
+    /// in the order yaw, pitch, roll.
+    pub fn euler_angles(&self) -> [f64; 3] {
+        let Self { w, x, y, z } = *self;
+
+        let roll = (2.0 * (w * x + y * z)).atan2(1.0 - 2.0 * (x * x + y * y));
+        let pitch = (2.0 * (w * y - z * x)).clamp(-1.0, 1.0).asin();
+        let yaw = (2.0 * (w * z + x * y)).atan2(1.0 - 2.0 * (y * y + z * z));
+
+        [roll.to_degrees(), pitch.to_degrees(), yaw.to_degrees()]
+    }
+}
+
+/// The orientation of the rocket at a single gyroscope reading.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrientationSample {
+    /// The time of the reading in seconds.
+    pub time: f64,
+    /// The rotation from the axes of the sensors to the world.
+    pub quaternion: Quaternion,
+    /// The roll, pitch and yaw from [`Quaternion::euler_angles`], in degrees.
+    pub euler_angles: [f64; 3],
+    /// The angle between the nose of the rocket and straight up, in degrees.
+    pub tilt: f64,
+}
+
+/// Estimates the orientation of the rocket from a gyroscope, an accelerometer
+/// and optionally a magnetometer.
+///
+/// The orientation starts from the direction of gravity in the first
+/// acceleration read, which assumes the rocket is still at the start of the
+/// log. Every gyroscope reading then turns the orientation, corrected towards
+/// the direction of gravity and magnetic north when they can be measured. The
+/// nose of the rocket is given by [`RocketConfig::nose_axis`].
+///
+/// Packets are added one at a time with [`OrientationEstimator::update`].
+pub struct OrientationEstimator {
+    clock: PacketClock,
+    options: OrientationOptions,
+    gyro: [Channel; 3],
+    acceleration: [Channel; 3],
+    magnetic_field: Option<[Channel; 3]>,
+    nose: [f64; 3],
+    /// The orientation, once the first acceleration is read.
+    quaternion: Option<Quaternion>,
+    /// The acceleration read since the last gyroscope reading, normalized.
+    latest_acceleration: Option<[f64; 3]>,
+    /// The magnetic field read since the last gyroscope reading, normalized.
+    latest_magnetic_field: Option<[f64; 3]>,
+    /// The Mahony integral of the error.
+    integral: [f64; 3],
+    previous_time: Option<f64>,
+    max_tilt: Option<OrientationSample>,
+    tumble: Option<OrientationSample>,
+}
+
+impl OrientationEstimator {
+    /// Create an estimator for packets using the given rocket configuration.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the config doesn't have a time value, or is
+    /// missing the gyroscope or accelerometer.
+    pub fn new(config: &RocketConfig, options: OrientationOptions) -> Result<Self, String> {
+        let clock = PacketClock::new(config);
+        if !clock.has_source() {
+            return Err("No value has the time role".to_string());
+        }
+
+        let Some(gyro) = options.gyro.or_else(|| {
+            roles(
+                config,
+                [
+                    ValueRole::AngularVelocityX,
+                    ValueRole::AngularVelocityY,
+                    ValueRole::AngularVelocityZ,
+                ],
+            )
+        }) else {
+            return Err("No values have the angular velocity roles".to_string());
+        };
+        let Some(acceleration) = options.acceleration.or_else(|| {
+            roles(
+                config,
+                [
+                    ValueRole::AccelerationX,
+                    ValueRole::AccelerationY,
+                    ValueRole::AccelerationZ,
+                ],
+            )
+        }) else {
+            return Err("No values have the acceleration roles".to_string());
+        };
+        let magnetic_field = options.magnetic_field.or_else(|| {
+            roles(
+                config,
+                [
+                    ValueRole::MagneticFieldX,
+                    ValueRole::MagneticFieldY,
+                    ValueRole::MagneticFieldZ,
+                ],
+            )
+        });
+
+        Ok(Self {
+            clock,
+            options,
+            gyro,
+            acceleration,
+            magnetic_field,
+            nose: config.nose_axis.vector(),
+            quaternion: None,
+            latest_acceleration: None,
+            latest_magnetic_field: None,
+            integral: [0.0; 3],
+            previous_time: None,
+            max_tilt: None,
+            tumble: None,
+        })
+    }
+
+    /// Add the next packet in the log.
+    ///
+    /// # Returns
+    ///
+    /// The orientation, if the packet has a gyroscope reading, a time has
+    /// been read and the starting orientation is known.
+    pub fn update(&mut self, packet: &Packet) -> Option<OrientationSample> {
+        let time = self.clock.update(packet);
+
+        if let Some(acceleration) = read(&self.acceleration, packet) {
+            let magnitude = length(acceleration);
+            let tolerance = self.options.acceleration_tolerance * STANDARD_GRAVITY;
+            if (magnitude - STANDARD_GRAVITY).abs() <= tolerance {
+                let up = normalize(acceleration);
+                self.latest_acceleration = Some(up);
+                self.quaternion
+                    .get_or_insert_with(|| Quaternion::between(up, [0.0, 0.0, 1.0]));
+            }
+        }
+        if let Some(magnetic_field) = self.magnetic_field.and_then(|m| read(&m, packet)) {
+            if length(magnetic_field) > 0.0 {
+                self.latest_magnetic_field = Some(normalize(magnetic_field));
+            }
+        }
+
+        let gyro = read(&self.gyro, packet)?;
+        let time = time?;
+        let quaternion = self.quaternion?;
+
+        let previous = self.previous_time.replace(time);
+        let quaternion = match previous.map(|p| time - p).filter(|&dt| dt > 0.0) {
+            Some(dt) => {
+                let acceleration = self.latest_acceleration.take();
+                let magnetic_field = self.latest_magnetic_field.take();
+                match self.options.algorithm {
+                    FusionAlgorithm::Madgwick => {
+                        self.madgwick(quaternion, gyro, acceleration, magnetic_field, dt)
+                    }
+                    FusionAlgorithm::Mahony => {
+                        self.mahony(quaternion, gyro, acceleration, magnetic_field, dt)
+                    }
+                }
+            }
+            None => quaternion,
+        };
+        self.quaternion = Some(quaternion);
+
+        let nose = quaternion.rotate(self.nose);
+        let sample = OrientationSample {
+            time,
+            quaternion,
+            euler_angles: quaternion.euler_angles(),
+            tilt: nose[2].clamp(-1.0, 1.0).acos().to_degrees(),
+        };
+
+        if self.max_tilt.is_none_or(|max| sample.tilt > max.tilt) {
+            self.max_tilt = Some(sample);
+        }
+        if self.tumble.is_none() && sample.tilt > self.options.tumble_tilt {
+            self.tumble = Some(sample);
+        }
+
+        Some(sample)
+    }
+
+    /// One step of Madgwick's filter.
+    fn madgwick(
+        &self,
+        q: Quaternion,
+        gyro: [f64; 3],
+        acceleration: Option<[f64; 3]>,
+        magnetic_field: Option<[f64; 3]>,
+        dt: f64,
+    ) -> Quaternion {
+        let Quaternion { w, x, y, z } = q;
+        let mut gradient = [0.0; 4];
+
+        if let Some([ax, ay, az]) = acceleration {
+            // The difference between the expected and measured direction of
+            // gravity, and its Jacobian.
+            let f = [
+                2.0 * (x * z - w * y) - ax,
+                2.0 * (w * x + y * z) - ay,
+                2.0 * (0.5 - x * x - y * y) - az,
+            ];
+            let jacobian = [
+                [-2.0 * y, 2.0 * z, -2.0 * w, 2.0 * x],
+                [2.0 * x, 2.0 * w, 2.0 * z, 2.0 * y],
+                [0.0, -4.0 * x, -4.0 * y, 0.0],
+            ];
+            add_gradient(&mut gradient, &jacobian, &f);
+        }
+
+        if let (Some(_), Some(m)) = (acceleration, magnetic_field) {
+            let [bx, bz] = reference_field(&q, m);
+            let [mx, my, mz] = m;
+            let f = [
+                2.0 * bx * (0.5 - y * y - z * z) + 2.0 * bz * (x * z - w * y) - mx,
+                2.0 * bx * (x * y - w * z) + 2.0 * bz * (w * x + y * z) - my,
+                2.0 * bx * (w * y + x * z) + 2.0 * bz * (0.5 - x * x - y * y) - mz,
+            ];
+            let jacobian = [
+                [
+                    -2.0 * bz * y,
+                    2.0 * bz * z,
+                    -4.0 * bx * y - 2.0 * bz * w,
+                    -4.0 * bx * z + 2.0 * bz * x,
+                ],
+                [
+                    -2.0 * bx * z + 2.0 * bz * x,
+                    2.0 * bx * y + 2.0 * bz * w,
+                    2.0 * bx * x + 2.0 * bz * z,
+                    -2.0 * bx * w + 2.0 * bz * y,
+                ],
+                [
+                    2.0 * bx * y,
+                    2.0 * bx * z - 4.0 * bz * x,
+                    2.0 * bx * w - 4.0 * bz * y,
+                    2.0 * bx * x,
+                ],
+            ];
+            add_gradient(&mut gradient, &jacobian, &f);
+        }
+
+        let rate = q.multiply(&Quaternion {
+            w: 0.0,
+            x: gyro[0] / 2.0,
+            y: gyro[1] / 2.0,
+            z: gyro[2] / 2.0,
+        });
+        let norm = gradient.iter().map(|g| g * g).sum::<f64>().sqrt();
+        let step = match norm > 0.0 {
+            true => gradient.map(|g| self.options.gain * g / norm),
+            false => [0.0; 4],
+        };
+
+        Quaternion {
+            w: w + (rate.w - step[0]) * dt,
+            x: x + (rate.x - step[1]) * dt,
+            y: y + (rate.y - step[2]) * dt,
+            z: z + (rate.z - step[3]) * dt,
+        }
+        .normalized()
+    }
+
+    /// One step of Mahony's filter.
+    fn mahony(
+        &mut self,
+        q: Quaternion,
+        gyro: [f64; 3],
+        acceleration: Option<[f64; 3]>,
+        magnetic_field: Option<[f64; 3]>,
+        dt: f64,
+    ) -> Quaternion {
+        let Quaternion { w, x, y, z } = q;
+        let mut error = [0.0; 3];
+
+        if let Some(acceleration) = acceleration {
+            // The expected direction of gravity in the axes of the sensors.
+            let up = [
+                2.0 * (x * z - w * y),
+                2.0 * (w * x + y * z),
+                w * w - x * x - y * y + z * z,
+            ];
+            error = add(error, cross(acceleration, up));
+
+            if let Some(m) = magnetic_field {
+                let [bx, bz] = reference_field(&q, m);
+                let field = [
+                    2.0 * bx * (0.5 - y * y - z * z) + 2.0 * bz * (x * z - w * y),
+                    2.0 * bx * (x * y - w * z) + 2.0 * bz * (w * x + y * z),
+                    2.0 * bx * (w * y + x * z) + 2.0 * bz * (0.5 - x * x - y * y),
+                ];
+                error = add(error, cross(m, field));
+            }
+        }
+
+        let ki = self.options.integral_gain;
+        if ki > 0.0 {
+            self.integral = add(self.integral, error.map(|e| e * ki * dt));
+        }
+        let kp = self.options.gain;
+        let corrected = [0, 1, 2].map(|i| gyro[i] + kp * error[i] + self.integral[i]);
+
+        let rate = q.multiply(&Quaternion {
+            w: 0.0,
+            x: corrected[0] / 2.0,
+            y: corrected[1] / 2.0,
+            z: corrected[2] / 2.0,
+        });
+
+        Quaternion {
+            w: w + rate.w * dt,
+            x: x + rate.x * dt,
+            y: y + rate.y * dt,
+            z: z + rate.z * dt,
+        }
+        .normalized()
+    }
+
+    /// The sample with the largest tilt from vertical.
+    pub fn max_tilt(&self) -> Option<OrientationSample> {
+        self.max_tilt
+    }
+
+    /// The first sample with a tilt above [`OrientationOptions::tumble_tilt`],
+    /// if the rocket tumbled.
+    pub fn tumble(&self) -> Option<OrientationSample> {
+        self.tumble
+    }
+
+    /// The options used for the estimate.
+    pub fn options(&self) -> &OrientationOptions {
+        &self.options
+    }
+}
+
+/// Find the values with three roles.
+fn roles(config: &RocketConfig, roles: [ValueRole; 3]) -> Option<[Channel; 3]> {
+    let [x, y, z] = roles.map(|role| Channel::from_role(config, role));
+    Some([x?, y?, z?])
+}
+
+/// Read a vector from a packet, if it has all three values.
+fn read(channels: &[Channel; 3], packet: &Packet) -> Option<[f64; 3]> {
+    let [x, y, z] = channels.map(|channel| channel.read(packet));
+    Some([x?, y?, z?])
+}
+
+/// The horizontal and vertical parts of the measured magnetic field in the
+/// world, so that the field is taken to point towards world X.
+fn reference_field(q: &Quaternion, magnetic_field: [f64; 3]) -> [f64; 2] {
+    let [hx, hy, hz] = q.rotate(magnetic_field);
+    [(hx * hx + hy * hy).sqrt(), hz]
+}
+
+/// Add the gradient `jacobianᵀ f` of one reference.
+fn add_gradient(gradient: &mut [f64; 4], jacobian: &[[f64; 4]; 3], f: &[f64; 3]) {
+    for (row, f) in jacobian.iter().zip(f) {
+        for (g, j) in gradient.iter_mut().zip(row) {
+            *g += j * f;
+        }
+    }
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn add(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn length(vector: [f64; 3]) -> f64 {
+    dot(vector, vector).sqrt()
+}
+
+fn normalize(vector: [f64; 3]) -> [f64; 3] {
+    let length = length(vector);
+    vector.map(|v| v / length)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::{Axis, Endianess, SensorConfig, ValueConfig, ValueKind};
+    use crate::data::Value;
+
+    use super::*;
+
+    fn value(name: &str, unit: &str, role: ValueRole) -> ValueConfig {
+        ValueConfig {
+            name: name.to_string(),
+            data_type: ValueKind::Float32,
+            unit: Some(unit.to_string()),
+            role: Some(role),
+            filters: vec![],
+        }
+    }
+
+    fn test_config() -> RocketConfig {
+        RocketConfig {
+            name: "test".to_string(),
+            endianess: Endianess::default(),
+            nose_axis: Axis::Z,
+            include: vec![],
+            display_name: None,
+            description: None,
+            kalman: None,
+            sensors: vec![SensorConfig {
+                id: 0,
+                name: "IMU".to_string(),
+                values: vec![
+                    value("time", "s", ValueRole::Time),
+                    value("ax", "g", ValueRole::AccelerationX),
+                    value("ay", "g", ValueRole::AccelerationY),
+                    value("az", "g", ValueRole::AccelerationZ),
+                    value("gx", "deg/s", ValueRole::AngularVelocityX),
+                    value("gy", "deg/s", ValueRole::AngularVelocityY),
+                    value("gz", "deg/s", ValueRole::AngularVelocityZ),
+                    value("mx", "uT", ValueRole::MagneticFieldX),
+                    value("my", "uT", ValueRole::MagneticFieldY),
+                    value("mz", "uT", ValueRole::MagneticFieldZ),
+                ],
+            }],
+        }
+    }
+
+    fn packet(time: f64, acceleration: [f64; 3], gyro: [f64; 3], field: [f64; 3]) -> Packet {
+        let mut values = vec![Value {
+            float_32: time as f32,
+        }];
+        values.extend(
+            [acceleration, gyro, field]
+                .iter()
+                .flatten()
+                .map(|&float_32| Value {
+                    float_32: float_32 as f32,
+                }),
+        );
+        Packet { id: 0, values }
+    }
+
+    /// Roll the rocket about X at 30 degrees per second for 4 seconds after
+    /// a second on the pad, with no gravity measured as in free fall.
+    fn run(algorithm: FusionAlgorithm) -> OrientationEstimator {
+        let options = OrientationOptions {
+            algorithm,
+            ..Default::default()
+        };
+        let mut estimator = OrientationEstimator::new(&test_config(), options).unwrap();
+
+        for i in 0..=500 {
+            let time = i as f64 * 0.01;
+            let sample = match time {
+                t if t < 1.0 => packet(t, [0.0, 0.0, 1.0], [0.0; 3], [0.0; 3]),
+                t => packet(t, [0.0; 3], [30.0, 0.0, 0.0], [0.0; 3]),
+            };
+            estimator.update(&sample);
+        }
+
+        estimator
+    }
+
+    #[test]
+    fn test_orientation_from_gyro() {
+        for algorithm in [FusionAlgorithm::Madgwick, FusionAlgorithm::Mahony] {
+            let estimator = run(algorithm);
+
+            let max_tilt = estimator.max_tilt().unwrap();
+            assert!((max_tilt.tilt - 120.0).abs() < 0.5, "{max_tilt:?}");
+            assert!(
+                (max_tilt.euler_angles[0] - 120.0).abs() < 0.5,
+                "{max_tilt:?}"
+            );
+            assert!((max_tilt.time - 5.0).abs() < 0.02, "{max_tilt:?}");
+
+            let tumble = estimator.tumble().unwrap();
+            assert!((tumble.time - 4.0).abs() < 0.02, "{tumble:?}");
+        }
+    }
+
+    #[test]
+    fn test_orientation_corrected_by_gravity() {
+        let mut estimator = OrientationEstimator::new(&test_config(), Default::default()).unwrap();
+
+        // A gyroscope with a bias, on the pad.
+        let mut last = None;
+        for i in 0..1000 {
+            let time = i as f64 * 0.01;
+            last = estimator.update(&packet(time, [0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0; 3]));
+        }
+
+        assert!(last.unwrap().tilt < 2.0, "{last:?}");
+    }
+
+    #[test]
+    fn test_heading_from_magnetometer() {
+        // The field of [45, 0, -20] uT in the world, measured by sensors
+        // turned 30 degrees to the left.
+        let (sin, cos) = 30f64.to_radians().sin_cos();
+        let field = [45.0 * cos, -45.0 * sin, -20.0];
+
+        for algorithm in [FusionAlgorithm::Madgwick, FusionAlgorithm::Mahony] {
+            let options = OrientationOptions {
+                algorithm,
+                ..Default::default()
+            };
+            let mut estimator = OrientationEstimator::new(&test_config(), options).unwrap();
+
+            let mut last = None;
+            for i in 0..6000 {
+                let time = i as f64 * 0.01;
+                last = estimator.update(&packet(time, [0.0, 0.0, 1.0], [0.0; 3], field));
+            }
+
+            let last = last.unwrap();
+            assert!((last.euler_angles[2] - 30.0).abs() < 1.0, "{last:?}");
+            assert!(last.tilt < 1.0, "{last:?}");
+        }
+    }
+
+    #[test]
+    fn test_quaternion_between() {
+        let q = Quaternion::between([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+        let rotated = q.rotate([1.0, 0.0, 0.0]);
+        assert!((rotated[1] - 1.0).abs() < 1e-12);
+        assert!((q.euler_angles()[2] - 90.0).abs() < 1e-9);
+
+        let q = Quaternion::between([0.0, 0.0, 1.0], [0.0, 0.0, -1.0]);
+        assert!((q.rotate([0.0, 0.0, 1.0])[2] + 1.0).abs() < 1e-12);
+    }
+}
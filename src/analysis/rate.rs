@@ -0,0 +1,413 @@
+use std::collections::HashMap;
+
+use crate::analysis::stats::{P2Quantile, RunningStats};
+use crate::configuration::RocketConfig;
+use crate::data::Packet;
+use crate::time::PacketClock;
+
+/// The most intervals of a sensor kept as possible dropouts.
+const MAX_GAP_CANDIDATES: usize = 1000;
+
+/// What the times of packets are measured in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeBase {
+    /// Seconds, from the values with the time role.
+    Seconds,
+    /// The position of the packet in the log, used when the config has no
+    /// time values.
+    Packets,
+}
+
+impl TimeBase {
+    /// The unit of times and periods.
+    pub fn period_unit(&self) -> &'static str {
+        match self {
+            Self::Seconds => "s",
+            Self::Packets => "packets",
+        }
+    }
+
+    /// The unit of rates.
+    pub fn rate_unit(&self) -> &'static str {
+        match self {
+            Self::Seconds => "Hz",
+            Self::Packets => "per packet in the log",
+        }
+    }
+}
+
+/// Options for analysing the data rate of sensors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateOptions {
+    /// How many times longer than the nominal period of a sensor the time
+    /// between two of its packets must be to count as a dropout.
+    pub gap_factor: f64,
+}
+
+impl Default for RateOptions {
+    fn default() -> Self {
+        Self { gap_factor: 3.0 }
+    }
+}
+
+/// A time where a sensor logged no packets for longer than expected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gap {
+    /// The time of the last packet before the gap.
+    pub start: f64,
+    /// The time until the next packet.
+    pub duration: f64,
+}
+
+/// The data rate of a single sensor.
+#[derive(Debug, Clone)]
+pub struct SensorRate {
+    /// The number of packets from the sensor.
+    count: u64,
+    /// The time of the first packet with a known time.
+    first: Option<f64>,
+    /// The time of the last packet with a known time.
+    last: Option<f64>,
+    /// The statistics of the time between packets.
+    intervals: RunningStats,
+    /// The shortest time between packets.
+    min_interval: Option<f64>,
+    /// The longest time between packets.
+    max_interval: Option<f64>,
+    /// The estimate of the median time between packets.
+    period: P2Quantile,
+    /// The longest intervals, at most [`MAX_GAP_CANDIDATES`] once trimmed.
+    ///
+    /// These are only checked against the final period, as the estimate is
+    /// rough at the start of the log.
+    gap_candidates: Vec<Gap>,
+    /// The shortest interval that can still be one of the longest, since that
+    /// many longer intervals have been kept.
+    min_gap_candidate: f64,
+    /// See [`RateOptions::gap_factor`].
+    gap_factor: f64,
+}
+
+impl SensorRate {
+    fn new(gap_factor: f64) -> Self {
+        Self {
+            count: 0,
+            first: None,
+            last: None,
+            intervals: RunningStats::default(),
+            min_interval: None,
+            max_interval: None,
+            period: P2Quantile::new(0.5),
+            gap_candidates: vec![],
+            min_gap_candidate: 0.0,
+            gap_factor,
+        }
+    }
+
+    /// Record a packet from the sensor, with its time if it is known.
+    fn record(&mut self, time: Option<f64>) {
+        self.count += 1;
+
+        let Some(time) = time else {
+            return;
+        };
+
+        // Time going backwards means the clock was reset or wrapped around, so
+        // there is no interval to record.
+        if let Some(last) = self.last.filter(|&last| time >= last) {
+            let interval = time - last;
+
+            if interval > self.min_gap_candidate {
+                self.add_gap_candidate(Gap {
+                    start: last,
+                    duration: interval,
+                });
+            }
+
+            self.intervals.add(interval);
+            self.period.add(interval);
+            self.min_interval = Some(self.min_interval.map_or(interval, |i| i.min(interval)));
+            self.max_interval = Some(self.max_interval.map_or(interval, |i| i.max(interval)));
+        }
+
+        self.first.get_or_insert(time);
+        self.last = Some(time);
+    }
+
+    /// Keep an interval that may be a dropout.
+    ///
+    /// The candidates are trimmed to the longest ones when there are twice as
+    /// many as kept, so adding a candidate takes constant time on average.
+    fn add_gap_candidate(&mut self, gap: Gap) {
+        self.gap_candidates.push(gap);
+
+        if self.gap_candidates.len() >= 2 * MAX_GAP_CANDIDATES {
+            self.gap_candidates
+                .sort_by(|a, b| b.duration.total_cmp(&a.duration));
+            self.gap_candidates.truncate(MAX_GAP_CANDIDATES);
+            self.min_gap_candidate = self.gap_candidates[MAX_GAP_CANDIDATES - 1].duration;
+        }
+    }
+
+    /// The number of packets from the sensor.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The time between the first and last packet.
+    pub fn duration(&self) -> Option<f64> {
+        Some(self.last? - self.first?)
+    }
+
+    /// The average number of packets per unit of time.
+    pub fn average_rate(&self) -> Option<f64> {
+        let duration = self.duration().filter(|&d| d > 0.0)?;
+        Some(self.intervals.count() as f64 / duration)
+    }
+
+    /// The rate from the longest time between two packets.
+    pub fn min_rate(&self) -> Option<f64> {
+        self.max_interval.filter(|&i| i > 0.0).map(|i| 1.0 / i)
+    }
+
+    /// The rate from the shortest time between two packets, or `None` if two
+    /// packets had the same time.
+    pub fn max_rate(&self) -> Option<f64> {
+        self.min_interval.filter(|&i| i > 0.0).map(|i| 1.0 / i)
+    }
+
+    /// The estimated median time between packets.
+    pub fn nominal_period(&self) -> Option<f64> {
+        self.period.estimate()
+    }
+
+    /// The standard deviation of the time between packets.
+    pub fn jitter(&self) -> Option<f64> {
+        self.intervals.std_dev()
+    }
+
+    /// The times the sensor logged no packets for longer than the gap factor
+    /// times its nominal period, in the order they happened.
+    ///
+    /// Only the longest [`MAX_GAP_CANDIDATES`] intervals are kept, so a log
+    /// with more dropouts than that only lists the longest ones.
+    pub fn gaps(&self) -> impl Iterator<Item = &Gap> + '_ {
+        let threshold = self.nominal_period().map(|period| self.gap_factor * period);
+
+        let mut gaps: Vec<&Gap> = self
+            .gap_candidates
+            .iter()
+            .filter(|gap| threshold.is_some_and(|t| t > 0.0 && gap.duration > t))
+            .collect();
+        gaps.sort_by(|a, b| b.duration.total_cmp(&a.duration));
+        gaps.truncate(MAX_GAP_CANDIDATES);
+        gaps.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+        gaps.into_iter()
+    }
+}
+
+/// The data rate of every sensor in a log.
+///
+/// Packets are added one at a time with [`RateAnalysis::update`], so the
+/// analysis can be done while the packets are used for something else.
+/// Packets from sensors without their own time value are given the most
+/// recent time read, as described in [`PacketClock`], so their rates can only
+/// be as precise as the sensor providing the time.
+pub struct RateAnalysis {
+    clock: PacketClock,
+    time_base: TimeBase,
+    options: RateOptions,
+    /// The number of packets read.
+    position: u64,
+    sensors: HashMap<u8, SensorRate>,
+}
+
+impl RateAnalysis {
+    /// Create an analysis for packets using the given rocket configuration.
+    pub fn new(config: &RocketConfig, options: RateOptions) -> Self {
+        let clock = PacketClock::new(config);
+        let time_base = match clock.has_source() {
+            true => TimeBase::Seconds,
+            false => TimeBase::Packets,
+        };
+        let sensors = config
+            .sensors
+            .iter()
+            .map(|sensor| (sensor.id, SensorRate::new(options.gap_factor)))
+            .collect();
+
+        Self {
+            clock,
+            time_base,
+            options,
+            position: 0,
+            sensors,
+        }
+    }
+
+    /// Add the next packet in the log.
+    ///
+    /// Packets from sensors that are not in the config are ignored.
+    pub fn update(&mut self, packet: &Packet) {
+        let time = match self.time_base {
+            TimeBase::Seconds => self.clock.update(packet),
+            TimeBase::Packets => Some(self.position as f64),
+        };
+        self.position += 1;
+
+        if let Some(sensor) = self.sensors.get_mut(&packet.id) {
+            sensor.record(time);
+        }
+    }
+
+    /// What the times of packets are measured in.
+    pub fn time_base(&self) -> TimeBase {
+        self.time_base
+    }
+
+    /// The options used for the analysis.
+    pub fn options(&self) -> &RateOptions {
+        &self.options
+    }
+
+    /// Get the data rate of a sensor by its ID.
+    pub fn sensor(&self, id: u8) -> Option<&SensorRate> {
+        self.sensors.get(&id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::{Axis, Endianess, SensorConfig, ValueConfig, ValueKind, ValueRole};
+    use crate::data::Value;
+
+    use super::*;
+
+    fn test_config(role: Option<ValueRole>) -> RocketConfig {
+        RocketConfig {
+            name: "test".to_string(),
+            endianess: Endianess::default(),
+            nose_axis: Axis::default(),
+            include: vec![],
+            display_name: None,
+            description: None,
+            kalman: None,
+            sensors: vec![
+                SensorConfig {
+                    id: 0,
+                    name: "clock".to_string(),
+                    values: vec![ValueConfig {
+                        name: "time".to_string(),
+                        data_type: ValueKind::UInt32,
+                        unit: Some("ms".to_string()),
+                        role,
+                        filters: vec![],
+                    }],
+                },
+                SensorConfig {
+                    id: 1,
+                    name: "other".to_string(),
+                    values: vec![],
+                },
+            ],
+        }
+    }
+
+    fn time_packet(ms: u32) -> Packet {
+        Packet {
+            id: 0,
+            values: vec![Value { uint_32: ms }],
+        }
+    }
+
+    #[test]
+    fn test_rates_and_gaps() {
+        let mut analysis =
+            RateAnalysis::new(&test_config(Some(ValueRole::Time)), Default::default());
+        assert_eq!(analysis.time_base(), TimeBase::Seconds);
+
+        // 100 Hz for a second, with a dropout of 50 ms after 0.5 s.
+        let times = (0..=100u32)
+            .map(|i| i * 10)
+            .filter(|ms| !(510..550).contains(ms));
+        for ms in times {
+            analysis.update(&time_packet(ms));
+        }
+
+        let rate = analysis.sensor(0).unwrap();
+        assert_eq!(rate.count(), 97);
+        assert_eq!(rate.duration(), Some(1.0));
+        assert!((rate.average_rate().unwrap() - 96.0).abs() < 1e-9);
+        assert!((rate.max_rate().unwrap() - 100.0).abs() < 1e-9);
+        assert!((rate.min_rate().unwrap() - 20.0).abs() < 1e-9);
+        assert!((rate.nominal_period().unwrap() - 0.01).abs() < 1e-4);
+        assert!(rate.jitter().unwrap() > 0.0);
+
+        let gaps: Vec<&Gap> = rate.gaps().collect();
+        assert_eq!(gaps.len(), 1);
+        assert!((gaps[0].start - 0.5).abs() < 1e-9);
+        assert!((gaps[0].duration - 0.05).abs() < 1e-9);
+
+        assert_eq!(analysis.sensor(1).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_gap_at_start() {
+        let mut analysis =
+            RateAnalysis::new(&test_config(Some(ValueRole::Time)), Default::default());
+
+        // A second without packets, then 200 packets at 100 Hz.
+        analysis.update(&time_packet(0));
+        for i in 0..200 {
+            analysis.update(&time_packet(1000 + i * 10));
+        }
+
+        let gaps: Vec<&Gap> = analysis.sensor(0).unwrap().gaps().collect();
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].start, 0.0);
+        assert!((gaps[0].duration - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_many_gaps_keep_longest() {
+        let mut rate = SensorRate::new(3.0);
+
+        // Every 10 packets 10 ms apart are followed by a longer dropout.
+        let mut time = 0.0;
+        for i in 0..(3 * MAX_GAP_CANDIDATES) {
+            for _ in 0..10 {
+                rate.record(Some(time));
+                time += 0.01;
+            }
+            time += 1.0 + i as f64 * 0.001;
+        }
+
+        let gaps: Vec<&Gap> = rate.gaps().collect();
+        assert_eq!(gaps.len(), MAX_GAP_CANDIDATES);
+        assert!(gaps.windows(2).all(|w| w[0].start < w[1].start));
+        assert!(gaps[0].duration > 1.0 + 1.9 * MAX_GAP_CANDIDATES as f64 * 0.001);
+    }
+
+    #[test]
+    fn test_packet_time_base() {
+        let mut analysis = RateAnalysis::new(&test_config(None), Default::default());
+        assert_eq!(analysis.time_base(), TimeBase::Packets);
+
+        let other = Packet {
+            id: 1,
+            values: vec![],
+        };
+        for _ in 0..10 {
+            analysis.update(&time_packet(0));
+            analysis.update(&other);
+        }
+
+        let rate = analysis.sensor(1).unwrap();
+        assert_eq!(rate.count(), 10);
+        assert_eq!(rate.nominal_period(), Some(2.0));
+        assert_eq!(rate.average_rate(), Some(0.5));
+        assert_eq!(rate.jitter(), Some(0.0));
+        assert_eq!(rate.gaps().count(), 0);
+    }
+}
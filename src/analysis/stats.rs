@@ -0,0 +1,235 @@
+/// The running mean and variance of samples using Welford's algorithm.
+#[derive(Debug, Clone, Default)]
+pub struct RunningStats {
+    /// The number of samples added.
+    count: u64,
+    /// The mean of the samples.
+    mean: f64,
+    /// The sum of squared differences from the mean.
+    squared_error: f64,
+}
+
+impl RunningStats {
+    /// Add a sample.
+    pub fn add(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.squared_error += delta * (x - self.mean);
+    }
+
+    /// The number of samples added.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The mean of the samples, or `None` if there are none.
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.mean)
+    }
+
+    /// The sample standard deviation, or `None` if there are fewer than two
+    /// samples.
+    pub fn std_dev(&self) -> Option<f64> {
+        (self.count > 1).then(|| (self.squared_error / (self.count - 1) as f64).sqrt())
+    }
+}
+
+/// A streaming estimate of a quantile using the P² algorithm.
+///
+/// Only five markers are stored no matter how many samples are added, so the
+/// memory used stays the same for long logs. The estimate is exact until five
+/// samples have been added.
+///
+/// See Jain and Chlamtac, "The P² algorithm for dynamic calculation of
+/// quantiles and histograms without storing observations" (1985).
+#[derive(Debug, Clone)]
+pub struct P2Quantile {
+    /// The quantile being estimated, between 0 and 1.
+    p: f64,
+    /// The number of samples added.
+    count: usize,
+    /// The heights of the markers.
+    heights: [f64; 5],
+    /// The actual positions of the markers.
+    positions: [f64; 5],
+    /// The desired positions of the markers.
+    desired: [f64; 5],
+    /// The amount the desired positions move for every sample.
+    increments: [f64; 5],
+}
+
+impl P2Quantile {
+    /// Create an estimator for the quantile `p`, which is clamped between 0
+    /// and 1.
+    pub fn new(p: f64) -> Self {
+        let p = p.clamp(0.0, 1.0);
+
+        Self {
+            p,
+            count: 0,
+            heights: [0.0; 5],
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 1.0 + 2.0 * p, 1.0 + 4.0 * p, 3.0 + 2.0 * p, 5.0],
+            increments: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+        }
+    }
+
+    /// Add a sample. Samples must not be NaN.
+    pub fn add(&mut self, x: f64) {
+        if self.count < 5 {
+            self.heights[self.count] = x;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(f64::total_cmp);
+            }
+            return;
+        }
+        self.count += 1;
+
+        // The cell the sample falls in, extending the outer markers if needed.
+        let cell = if x < self.heights[0] {
+            self.heights[0] = x;
+            0
+        } else if x >= self.heights[4] {
+            self.heights[4] = x;
+            3
+        } else {
+            (0..4).find(|&i| x < self.heights[i + 1]).unwrap_or(3)
+        };
+
+        for position in self.positions[cell + 1..].iter_mut() {
+            *position += 1.0;
+        }
+        for (desired, increment) in self.desired.iter_mut().zip(self.increments) {
+            *desired += increment;
+        }
+
+        for i in 1..4 {
+            let offset = self.desired[i] - self.positions[i];
+            let can_move_up = self.positions[i + 1] - self.positions[i] > 1.0;
+            let can_move_down = self.positions[i - 1] - self.positions[i] < -1.0;
+
+            if (offset >= 1.0 && can_move_up) || (offset <= -1.0 && can_move_down) {
+                let direction = offset.signum();
+                let height = self.parabolic(i, direction);
+
+                self.heights[i] = if self.heights[i - 1] < height && height < self.heights[i + 1] {
+                    height
+                } else {
+                    self.linear(i, direction)
+                };
+                self.positions[i] += direction;
+            }
+        }
+    }
+
+    /// The estimated quantile, or `None` if no samples have been added.
+    pub fn estimate(&self) -> Option<f64> {
+        match self.count {
+            0 => None,
+            1..=5 => {
+                let mut samples = self.heights[..self.count].to_vec();
+                samples.sort_by(f64::total_cmp);
+
+                let rank = self.p * (samples.len() - 1) as f64;
+                let lower = rank.floor() as usize;
+                let upper = rank.ceil() as usize;
+                let fraction = rank - lower as f64;
+
+                Some(samples[lower] + (samples[upper] - samples[lower]) * fraction)
+            }
+            _ => Some(self.heights[2]),
+        }
+    }
+
+    /// The new height of marker `i` from the parabolic prediction.
+    fn parabolic(&self, i: usize, direction: f64) -> f64 {
+        let (q, n) = (&self.heights, &self.positions);
+
+        q[i] + direction / (n[i + 1] - n[i - 1])
+            * ((n[i] - n[i - 1] + direction) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                + (n[i + 1] - n[i] - direction) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]))
+    }
+
+    /// The new height of marker `i` from the linear prediction.
+    fn linear(&self, i: usize, direction: f64) -> f64 {
+        let (q, n) = (&self.heights, &self.positions);
+        let j = if direction > 0.0 { i + 1 } else { i - 1 };
+
+        q[i] + direction * (q[j] - q[i]) / (n[j] - n[i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Numbers from 0 to `count - 1` in a scrambled order.
+    fn scrambled(count: u64) -> impl Iterator<Item = f64> {
+        // 7919 is prime, so this visits every number once when count is not a
+        // multiple of it.
+        (0..count).map(move |i| ((i * 7919) % count) as f64)
+    }
+
+    #[test]
+    fn test_running_stats() {
+        let mut stats = RunningStats::default();
+        assert_eq!(stats.mean(), None);
+
+        for x in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+            stats.add(x);
+        }
+
+        assert_eq!(stats.count(), 8);
+        assert_eq!(stats.mean(), Some(5.0));
+        assert!((stats.std_dev().unwrap() - (32.0f64 / 7.0).sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(P2Quantile::new(0.5).estimate(), None);
+    }
+
+    #[test]
+    fn test_exact_for_few_samples() {
+        let mut median = P2Quantile::new(0.5);
+        for x in [4.0, 1.0, 3.0, 2.0] {
+            median.add(x);
+        }
+        assert_eq!(median.estimate(), Some(2.5));
+
+        let mut p25 = P2Quantile::new(0.25);
+        for x in [10.0, 0.0, 20.0] {
+            p25.add(x);
+        }
+        assert_eq!(p25.estimate(), Some(5.0));
+    }
+
+    #[test]
+    fn test_estimates_quantiles() {
+        for p in [0.01, 0.05, 0.5, 0.95, 0.99] {
+            let mut quantile = P2Quantile::new(p);
+            for x in scrambled(10_000) {
+                quantile.add(x);
+            }
+
+            let estimate = quantile.estimate().unwrap();
+            let expected = p * 9_999.0;
+            assert!(
+                (estimate - expected).abs() < 100.0,
+                "p{}: {estimate} is not close to {expected}",
+                p * 100.0
+            );
+        }
+    }
+
+    #[test]
+    fn test_constant_samples() {
+        let mut quantile = P2Quantile::new(0.95);
+        for _ in 0..1000 {
+            quantile.add(3.5);
+        }
+        assert_eq!(quantile.estimate(), Some(3.5));
+    }
+}
@@ -0,0 +1,404 @@
+use std::error::Error;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+
+use ciborium::value::{Integer, Value as CborValue};
+use serde::{Deserialize, Serialize};
+
+use crate::configuration::{RocketConfig, ValueKind};
+use crate::data::{Packet, PacketError, TypedValue, Value};
+use crate::result_table::SourceIterator;
+
+/// The name of the format in the header of every archive.
+pub const FORMAT_NAME: &str = "flight_data_reader";
+
+/// The version of the archive format that is written.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The first item in an archive.
+#[derive(Serialize, Deserialize)]
+struct ArchiveHeader {
+    format: String,
+    version: u32,
+    config: RocketConfig,
+}
+
+/// Writer for self-describing CBOR archives of decoded flights.
+///
+/// An archive is a CBOR sequence. The first item is a header map containing
+/// the rocket configuration, which is followed by one item per packet. Each
+/// packet is an array of the sensor ID and an array of its values, stored as
+/// CBOR integers and floats in the smallest form that doesn't lose precision.
+///
+/// Archives can be read again with [`CborReader`] without the original config
+/// file.
+pub struct CborGenerator<I: SourceIterator> {
+    iter: I,
+    config: RocketConfig,
+}
+
+impl<I: SourceIterator> CborGenerator<I> {
+    /// Create a new archive generator given a Packet iterator and a rocket
+    /// configuration.
+    pub fn new(iter: I, config: RocketConfig) -> Self {
+        Self { iter, config }
+    }
+
+    /// Consume the iterator and write the archive to the given writer.
+    ///
+    /// # Errors
+    ///
+    /// The two cases for error are either an IO error if the writer fails to
+    /// write or a packet error if the iterator fails to yield a valid packet.
+    pub fn write_cbor<W: Write>(self, writer: &mut W) -> Result<(), Box<dyn Error>> {
+        let header = ArchiveHeader {
+            format: FORMAT_NAME.to_string(),
+            version: FORMAT_VERSION,
+            config: self.config,
+        };
+        ciborium::into_writer(&header, &mut *writer)?;
+
+        for packet in self.iter {
+            let packet = packet?;
+
+            let Some(sensor) = header.config.get_sensor_by_id(packet.id) else {
+                return Err(PacketError::InvalidId(packet.id).into());
+            };
+
+            if packet.values.len() != sensor.values.len() {
+                return Err(PacketError::InvalidValueCount {
+                    expected: sensor.values.len(),
+                    actual: packet.values.len(),
+                }
+                .into());
+            }
+
+            let values: Vec<CborValue> = sensor
+                .values
+                .iter()
+                .zip(packet.values.iter())
+                .map(|(spec, value)| {
+                    encode_value(unsafe { &TypedValue::new(*value, &spec.data_type) })
+                })
+                .collect();
+
+            ciborium::into_writer(&(packet.id, values), &mut *writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A parser for reading packets from a CBOR archive.
+///
+/// The rocket configuration is read from the header of the archive when the
+/// reader is created, and is available from [`CborReader::config`]. Like the
+/// [`crate::data::PacketParser`], the iterator ends at the end of the stream,
+/// but an archive that ends part way through a packet is an error.
+pub struct CborReader<R: Read> {
+    reader: BufReader<R>,
+    config: RocketConfig,
+}
+
+impl<R: Read> CborReader<R> {
+    /// Create a new archive reader, reading the header from the reader.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the header can't be read, or if it is not an
+    /// archive of a known version.
+    pub fn new(reader: R) -> Result<Self, String> {
+        let mut reader = BufReader::new(reader);
+
+        let header: ArchiveHeader =
+            ciborium::from_reader(&mut reader).map_err(|e| format!("Invalid archive: {e}"))?;
+
+        if header.format != FORMAT_NAME {
+            return Err(format!("Unknown archive format: {}", header.format));
+        }
+        if header.version > FORMAT_VERSION {
+            return Err(format!("Unsupported archive version: {}", header.version));
+        }
+
+        Ok(Self {
+            reader,
+            config: header.config,
+        })
+    }
+
+    /// The rocket configuration stored in the archive.
+    pub fn config(&self) -> &RocketConfig {
+        &self.config
+    }
+}
+
+impl<R: Read> Iterator for CborReader<R> {
+    type Item = Result<Packet, PacketError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The stream may only end between packets.
+        match self.reader.fill_buf() {
+            Ok([]) => return None,
+            Ok(_) => {}
+            Err(e) => return Some(Err(PacketError::Malformed(e.to_string()))),
+        }
+
+        let (id, values): (u8, Vec<CborValue>) = match ciborium::from_reader(&mut self.reader) {
+            Ok(item) => item,
+            Err(ciborium::de::Error::Io(e)) if e.kind() == ErrorKind::UnexpectedEof => {
+                return Some(Err(PacketError::Malformed(
+                    "The archive ends part way through a packet".to_string(),
+                )))
+            }
+            Err(e) => return Some(Err(PacketError::Malformed(e.to_string()))),
+        };
+
+        let Some(sensor) = self.config.get_sensor_by_id(id) else {
+            return Some(Err(PacketError::InvalidId(id)));
+        };
+
+        if values.len() != sensor.values.len() {
+            return Some(Err(PacketError::InvalidValueCount {
+                expected: sensor.values.len(),
+                actual: values.len(),
+            }));
+        }
+
+        let mut result = Vec::with_capacity(values.len());
+        for (spec, value) in sensor.values.iter().zip(values) {
+            let Some(value) = decode_value(&value, &spec.data_type) else {
+                return Some(Err(PacketError::Malformed(format!(
+                    "{} is not a valid {} for {}_{}",
+                    describe(&value),
+                    spec.data_type,
+                    sensor.name,
+                    spec.name
+                ))));
+            };
+            result.push(value);
+        }
+
+        Some(Ok(Packet { id, values: result }))
+    }
+}
+
+/// Convert a value to the CBOR value it is stored as.
+fn encode_value(value: &TypedValue) -> CborValue {
+    let raw = value.value();
+
+    // The safety is assumed by the constructor of the typed value.
+    unsafe {
+        match value.kind() {
+            ValueKind::Int8 => CborValue::Integer(raw.int_8.into()),
+            ValueKind::Int16 => CborValue::Integer(raw.int_16.into()),
+            ValueKind::Int32 => CborValue::Integer(raw.int_32.into()),
+            ValueKind::Int64 => CborValue::Integer(raw.int_64.into()),
+            ValueKind::UInt8 => CborValue::Integer(raw.uint_8.into()),
+            ValueKind::UInt16 => CborValue::Integer(raw.uint_16.into()),
+            ValueKind::UInt32 => CborValue::Integer(raw.uint_32.into()),
+            ValueKind::UInt64 => CborValue::Integer(raw.uint_64.into()),
+            ValueKind::Float32 => CborValue::Float(raw.float_32.into()),
+            ValueKind::Float64 => CborValue::Float(raw.float_64),
+        }
+    }
+}
+
+/// Convert a stored CBOR value back to a value of the given kind.
+///
+/// Returns `None` if the CBOR value is not of the right type or is out of the
+/// range of the kind.
+fn decode_value(value: &CborValue, kind: &ValueKind) -> Option<Value> {
+    match (value, kind) {
+        (CborValue::Integer(i), ValueKind::Int8) => Some(Value {
+            int_8: integer(*i)?,
+        }),
+        (CborValue::Integer(i), ValueKind::Int16) => Some(Value {
+            int_16: integer(*i)?,
+        }),
+        (CborValue::Integer(i), ValueKind::Int32) => Some(Value {
+            int_32: integer(*i)?,
+        }),
+        (CborValue::Integer(i), ValueKind::Int64) => Some(Value {
+            int_64: integer(*i)?,
+        }),
+        (CborValue::Integer(i), ValueKind::UInt8) => Some(Value {
+            uint_8: integer(*i)?,
+        }),
+        (CborValue::Integer(i), ValueKind::UInt16) => Some(Value {
+            uint_16: integer(*i)?,
+        }),
+        (CborValue::Integer(i), ValueKind::UInt32) => Some(Value {
+            uint_32: integer(*i)?,
+        }),
+        (CborValue::Integer(i), ValueKind::UInt64) => Some(Value {
+            uint_64: integer(*i)?,
+        }),
+        // Floats are stored without losing precision, so this is exact.
+        (CborValue::Float(f), ValueKind::Float32) => Some(Value {
+            float_32: *f as f32,
+        }),
+        (CborValue::Float(f), ValueKind::Float64) => Some(Value { float_64: *f }),
+        _ => None,
+    }
+}
+
+/// Convert a CBOR integer to a primitive integer if it is in range.
+fn integer<T: TryFrom<i128>>(value: Integer) -> Option<T> {
+    T::try_from(i128::from(value)).ok()
+}
+
+/// A short description of a CBOR value for error messages.
+fn describe(value: &CborValue) -> String {
+    match value {
+        CborValue::Integer(i) => i128::from(*i).to_string(),
+        CborValue::Float(f) => f.to_string(),
+        _ => format!("{value:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::{Axis, Endianess, SensorConfig, ValueConfig};
+
+    use super::*;
+
+    fn value(name: &str, data_type: ValueKind) -> ValueConfig {
+        ValueConfig {
+            name: name.to_string(),
+            data_type,
+            unit: None,
+            role: None,
+            filters: vec![],
+        }
+    }
+
+    fn test_config() -> RocketConfig {
+        RocketConfig {
+            name: "test".to_string(),
+            endianess: Endianess::Little,
+            nose_axis: Axis::default(),
+            include: vec![],
+            display_name: Some("Test".to_string()),
+            description: None,
+            kalman: None,
+            sensors: vec![SensorConfig {
+                id: 7,
+                name: "mixed".to_string(),
+                values: vec![
+                    value("a", ValueKind::Int8),
+                    value("b", ValueKind::UInt64),
+                    value("c", ValueKind::Float32),
+                    value("d", ValueKind::Float64),
+                ],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_cbor_round_trip() {
+        let packets = vec![
+            Packet {
+                id: 7,
+                values: vec![
+                    Value { int_8: -5 },
+                    Value { uint_64: u64::MAX },
+                    Value { float_32: 0.1 },
+                    Value { float_64: -1e300 },
+                ],
+            },
+            Packet {
+                id: 7,
+                values: vec![
+                    Value { int_8: 0 },
+                    Value { uint_64: 0 },
+                    Value { float_32: f32::NAN },
+                    Value { float_64: 0.5 },
+                ],
+            },
+        ];
+
+        let mut archive = vec![];
+        CborGenerator::new(packets.clone().into_iter().map(Ok), test_config())
+            .write_cbor(&mut archive)
+            .unwrap();
+
+        let reader = CborReader::new(archive.as_slice()).unwrap();
+        assert_eq!(reader.config().name, "test");
+        assert_eq!(reader.config().display_name(), "Test");
+        assert!(!reader.config().endianess.is_big());
+
+        let config = reader.config().clone();
+        let decoded = reader
+            .collect::<Result<Vec<Packet>, PacketError>>()
+            .unwrap();
+
+        assert_eq!(decoded.len(), 2);
+        for (expected, actual) in packets.iter().zip(decoded.iter()) {
+            assert_eq!(actual.id, expected.id);
+            for ((spec, a), b) in config.sensors[0]
+                .values
+                .iter()
+                .zip(&actual.values)
+                .zip(&expected.values)
+            {
+                let a = unsafe { a.to_bytes(&spec.data_type, Endianess::Big) };
+                let b = unsafe { b.to_bytes(&spec.data_type, Endianess::Big) };
+                assert_eq!(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cbor_reader_rejects_truncated_packet() {
+        let packets = vec![Packet {
+            id: 7,
+            values: vec![
+                Value { int_8: 1 },
+                Value { uint_64: 2 },
+                Value { float_32: 3.0 },
+                Value { float_64: 4.0 },
+            ],
+        }];
+        let mut archive = vec![];
+        CborGenerator::new(packets.into_iter().map(Ok), test_config())
+            .write_cbor(&mut archive)
+            .unwrap();
+        archive.pop();
+
+        let mut reader = CborReader::new(archive.as_slice()).unwrap();
+
+        assert!(matches!(
+            reader.next(),
+            Some(Err(PacketError::Malformed(_)))
+        ));
+    }
+
+    #[test]
+    fn test_cbor_reader_rejects_other_data() {
+        assert!(CborReader::new(b"not cbor".as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_cbor_reader_rejects_wrong_type() {
+        let mut archive = vec![];
+        CborGenerator::new(
+            std::iter::empty::<Result<Packet, PacketError>>(),
+            test_config(),
+        )
+        .write_cbor(&mut archive)
+        .unwrap();
+        let values = vec![
+            CborValue::Float(1.0),
+            CborValue::Integer(0.into()),
+            CborValue::Float(0.0),
+            CborValue::Float(0.0),
+        ];
+        ciborium::into_writer(&(7u8, values), &mut archive).unwrap();
+
+        let mut reader = CborReader::new(archive.as_slice()).unwrap();
+
+        assert!(matches!(
+            reader.next(),
+            Some(Err(PacketError::Malformed(_)))
+        ));
+    }
+}
@@ -0,0 +1,542 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::io::Write;
+
+use crate::configuration::{Endianess, RocketConfig, ValueKind};
+use crate::header::LogHeader;
+
+/// Reserved words that can't be used as identifiers in C.
+const C_KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+    "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register",
+    "restrict", "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef",
+    "union", "unsigned", "void", "volatile", "while", "bool", "true", "false",
+];
+
+/// Reserved words that can't be used as identifiers in Rust.
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use",
+    "where", "while", "abstract", "become", "box", "do", "final", "gen", "macro", "override",
+    "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+
+/// Names used by the generated Rust logging functions, which values can't
+/// have.
+const RUST_RESERVED_ARGUMENTS: &[&str] = &["sink", "packet"];
+
+/// Write a C header for the flight computer that logs data in the layout of
+/// the config.
+///
+/// The header contains:
+///
+/// * `SENSOR_<NAME>_ID` and `SENSOR_<NAME>_PACKET_SIZE` defines for every
+///   sensor.
+/// * A packed `<name>_packet_t` struct for every sensor, with a field for each
+///   value.
+/// * An inline `log_<name>()` function for every sensor that encodes a packet
+///   into a buffer, returning the number of bytes written. The values are
+///   written in the endianess of the config, regardless of the endianess of
+///   the flight computer.
+/// * The [`RocketConfig::fingerprint`] and the bytes of a [`LogHeader`] that
+///   can be written at the start of the log.
+///
+/// # Errors
+///
+/// An error is returned if the writer fails, or if two sensors or two values
+/// of a sensor have names that are the same once made into C identifiers.
+pub fn write_c_header<W: Write>(
+    config: &RocketConfig,
+    writer: &mut W,
+) -> Result<(), Box<dyn Error>> {
+    let guard = format!(
+        "{}_FLIGHT_DATA_H",
+        c_identifier(&config.name).to_uppercase()
+    );
+
+    writeln!(
+        writer,
+        "/* Flight data logging for {}, generated from its config. Do not edit. */",
+        config.display_name()
+    )?;
+    writeln!(writer, "#ifndef {guard}")?;
+    writeln!(writer, "#define {guard}")?;
+    writeln!(writer)?;
+    writeln!(writer, "#include <stddef.h>")?;
+    writeln!(writer, "#include <stdint.h>")?;
+    writeln!(writer, "#include <string.h>")?;
+    writeln!(writer)?;
+
+    writeln!(
+        writer,
+        "#define FLIGHT_DATA_FINGERPRINT 0x{:016x}ULL",
+        config.fingerprint()
+    )?;
+    writeln!(writer)?;
+
+    let mut header = vec![];
+    LogHeader::new(config, false).write(&mut header)?;
+    writeln!(
+        writer,
+        "/* Written once at the start of a log so the reader can check the config. */"
+    )?;
+    writeln!(
+        writer,
+        "static const uint8_t FLIGHT_DATA_LOG_HEADER[{}] = {{{}}};",
+        header.len(),
+        header
+            .iter()
+            .map(|b| format!("0x{b:02x}"))
+            .collect::<Vec<String>>()
+            .join(", ")
+    )?;
+    writeln!(writer)?;
+
+    write_put_functions(writer, config.endianess)?;
+
+    let mut sensor_names = HashSet::new();
+
+    for sensor in config.sensors.iter() {
+        let name = c_identifier(&sensor.name);
+        if !sensor_names.insert(name.to_lowercase()) {
+            return Err(format!("Multiple sensors with the C name: {name}").into());
+        }
+
+        let mut value_names = HashSet::new();
+        let mut fields = Vec::with_capacity(sensor.values.len());
+        for value in sensor.values.iter() {
+            let field = c_identifier(&value.name);
+            if !value_names.insert(field.clone()) {
+                return Err(format!("Multiple values of {name} with the C name: {field}").into());
+            }
+            fields.push((field, value.data_type));
+        }
+
+        let macro_name = format!("SENSOR_{}", name.to_uppercase());
+        let type_name = format!("{}_packet_t", name.to_lowercase());
+        let packet_size: usize = 1 + fields.iter().map(|(_, kind)| kind.size()).sum::<usize>();
+
+        writeln!(writer, "/* {} */", sensor.name)?;
+        writeln!(writer, "#define {macro_name}_ID {}", sensor.id)?;
+        writeln!(writer, "#define {macro_name}_PACKET_SIZE {packet_size}")?;
+        writeln!(writer)?;
+
+        writeln!(writer, "typedef struct __attribute__((packed)) {{")?;
+        for (field, kind) in fields.iter() {
+            writeln!(writer, "    {} {field};", c_type(kind))?;
+        }
+        if fields.is_empty() {
+            // Empty structs aren't allowed in C.
+            writeln!(writer, "    uint8_t unused;")?;
+        }
+        writeln!(writer, "}} {type_name};")?;
+        writeln!(writer)?;
+
+        writeln!(
+            writer,
+            "/* Encode a packet into buf, which must hold {macro_name}_PACKET_SIZE bytes. */"
+        )?;
+        writeln!(
+            writer,
+            "static inline size_t log_{}(uint8_t *buf, const {type_name} *packet) {{",
+            name.to_lowercase()
+        )?;
+        if fields.is_empty() {
+            writeln!(writer, "    (void)packet;")?;
+        }
+        writeln!(writer, "    buf[0] = {macro_name}_ID;")?;
+        let mut offset = 1;
+        for (field, kind) in fields.iter() {
+            writeln!(
+                writer,
+                "    fdr_put_{}(buf + {offset}, {}packet->{field});",
+                put_suffix(kind),
+                put_cast(kind)
+            )?;
+            offset += kind.size();
+        }
+        writeln!(writer, "    return {macro_name}_PACKET_SIZE;")?;
+        writeln!(writer, "}}")?;
+        writeln!(writer)?;
+    }
+
+    writeln!(writer, "#endif /* {guard} */")?;
+
+    Ok(())
+}
+
+/// Write Rust code for the flight computer that logs data in the layout of the
+/// config.
+///
+/// The code uses the `no_std` `flight_data_encoder` crate and is meant to be
+/// written to `OUT_DIR` by the build script of the firmware and included with
+/// `include!`. It contains:
+///
+/// * `<NAME>_ID` and `<NAME>_PACKET_SIZE` constants for every sensor.
+/// * A `log_<name>()` function for every sensor taking each value as an
+///   argument, which writes a whole packet to a `flight_data_encoder::Sink`.
+///   The values are written in the endianess of the config. Values whose
+///   names have no letters or digits are named `value_<index>`.
+/// * The [`RocketConfig::fingerprint`] and the bytes of a [`LogHeader`] that
+///   can be written at the start of the log.
+///
+/// # Errors
+///
+/// An error is returned if the writer fails, or if two sensors or two values
+/// of a sensor have names that are the same once made into Rust identifiers,
+/// or a value is named `sink` or `packet`, which the functions use.
+pub fn write_rust_encoder<W: Write>(
+    config: &RocketConfig,
+    writer: &mut W,
+) -> Result<(), Box<dyn Error>> {
+    writeln!(
+        writer,
+        "// Flight data logging for {}, generated from its config. Do not edit.",
+        config.display_name()
+    )?;
+    writeln!(writer)?;
+
+    writeln!(
+        writer,
+        "/// The fingerprint of the config the log is written with."
+    )?;
+    writeln!(
+        writer,
+        "pub const FINGERPRINT: u64 = 0x{:016x};",
+        config.fingerprint()
+    )?;
+    writeln!(writer)?;
+
+    let mut header = vec![];
+    LogHeader::new(config, false).write(&mut header)?;
+    writeln!(
+        writer,
+        "/// Written once at the start of a log so the reader can check the config."
+    )?;
+    writeln!(
+        writer,
+        "pub const LOG_HEADER: [u8; {}] = [{}];",
+        header.len(),
+        header
+            .iter()
+            .map(|b| format!("0x{b:02x}"))
+            .collect::<Vec<String>>()
+            .join(", ")
+    )?;
+
+    let to_bytes = if config.endianess.is_big() {
+        "to_be_bytes"
+    } else {
+        "to_le_bytes"
+    };
+
+    let mut sensor_names = HashSet::new();
+
+    for sensor in config.sensors.iter() {
+        let name = rust_identifier(&sensor.name);
+        if !sensor_names.insert(name.clone()) {
+            return Err(format!("Multiple sensors with the Rust name: {name}").into());
+        }
+
+        let mut value_names = HashSet::new();
+        let mut arguments = Vec::with_capacity(sensor.values.len());
+        for (index, value) in sensor.values.iter().enumerate() {
+            // A name of only underscores can't be used to refer to the
+            // argument, so it is named by its position instead.
+            let argument = match rust_identifier(&value.name) {
+                argument if argument.trim_matches('_').is_empty() => format!("value_{index}"),
+                argument => argument,
+            };
+            if RUST_RESERVED_ARGUMENTS.contains(&argument.as_str()) {
+                return Err(
+                    format!("The Rust name of a value of {name} is reserved: {argument}").into(),
+                );
+            }
+            if !value_names.insert(argument.clone()) {
+                return Err(
+                    format!("Multiple values of {name} with the Rust name: {argument}").into(),
+                );
+            }
+            arguments.push((argument, value.data_type));
+        }
+
+        let constant = name.to_uppercase();
+        let packet_size: usize = 1 + arguments.iter().map(|(_, kind)| kind.size()).sum::<usize>();
+
+        writeln!(writer)?;
+        writeln!(writer, "/// The ID of the `{}` sensor.", sensor.name)?;
+        writeln!(writer, "pub const {constant}_ID: u8 = {};", sensor.id)?;
+        writeln!(
+            writer,
+            "/// The number of bytes in a `{}` packet.",
+            sensor.name
+        )?;
+        writeln!(
+            writer,
+            "pub const {constant}_PACKET_SIZE: usize = {packet_size};"
+        )?;
+        writeln!(writer)?;
+
+        let parameters: String = arguments
+            .iter()
+            .map(|(argument, kind)| format!(", {argument}: {}", rust_type(kind)))
+            .collect();
+
+        writeln!(writer, "/// Write a `{}` packet to the sink.", sensor.name)?;
+        writeln!(writer, "#[allow(clippy::too_many_arguments)]")?;
+        writeln!(
+            writer,
+            "pub fn log_{name}<S: ::flight_data_encoder::Sink>(sink: &mut S{parameters}) -> Result<(), S::Error> {{"
+        )?;
+        writeln!(
+            writer,
+            "    let mut packet = [0u8; {constant}_PACKET_SIZE];"
+        )?;
+        writeln!(writer, "    packet[0] = {constant}_ID;")?;
+        let mut offset = 1;
+        for (argument, kind) in arguments.iter() {
+            let end = offset + kind.size();
+            writeln!(
+                writer,
+                "    packet[{offset}..{end}].copy_from_slice(&{argument}.{to_bytes}());"
+            )?;
+            offset = end;
+        }
+        writeln!(writer, "    sink.write(&packet)")?;
+        writeln!(writer, "}}")?;
+    }
+
+    Ok(())
+}
+
+/// Write the functions that encode a single value in the given endianess.
+fn write_put_functions<W: Write>(writer: &mut W, endianess: Endianess) -> std::io::Result<()> {
+    for bits in [8, 16, 32, 64] {
+        let bytes = bits / 8;
+
+        writeln!(
+            writer,
+            "static inline void fdr_put_u{bits}(uint8_t *buf, uint{bits}_t value) {{"
+        )?;
+        for i in 0..bytes {
+            let shift = if endianess.is_big() {
+                8 * (bytes - 1 - i)
+            } else {
+                8 * i
+            };
+            if shift == 0 {
+                writeln!(writer, "    buf[{i}] = (uint8_t)value;")?;
+            } else {
+                writeln!(writer, "    buf[{i}] = (uint8_t)(value >> {shift});")?;
+            }
+        }
+        writeln!(writer, "}}")?;
+        writeln!(writer)?;
+    }
+
+    for (bits, float_type) in [(32, "float"), (64, "double")] {
+        writeln!(
+            writer,
+            "static inline void fdr_put_f{bits}(uint8_t *buf, {float_type} value) {{"
+        )?;
+        writeln!(writer, "    uint{bits}_t bits;")?;
+        writeln!(writer, "    memcpy(&bits, &value, sizeof(bits));")?;
+        writeln!(writer, "    fdr_put_u{bits}(buf, bits);")?;
+        writeln!(writer, "}}")?;
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// The C type of a value.
+fn c_type(kind: &ValueKind) -> &'static str {
+    match kind {
+        ValueKind::Int8 => "int8_t",
+        ValueKind::Int16 => "int16_t",
+        ValueKind::Int32 => "int32_t",
+        ValueKind::Int64 => "int64_t",
+        ValueKind::UInt8 => "uint8_t",
+        ValueKind::UInt16 => "uint16_t",
+        ValueKind::UInt32 => "uint32_t",
+        ValueKind::UInt64 => "uint64_t",
+        ValueKind::Float32 => "float",
+        ValueKind::Float64 => "double",
+    }
+}
+
+/// The Rust type of a value.
+fn rust_type(kind: &ValueKind) -> &'static str {
+    match kind {
+        ValueKind::Int8 => "i8",
+        ValueKind::Int16 => "i16",
+        ValueKind::Int32 => "i32",
+        ValueKind::Int64 => "i64",
+        ValueKind::UInt8 => "u8",
+        ValueKind::UInt16 => "u16",
+        ValueKind::UInt32 => "u32",
+        ValueKind::UInt64 => "u64",
+        ValueKind::Float32 => "f32",
+        ValueKind::Float64 => "f64",
+    }
+}
+
+/// The suffix of the `fdr_put_` function that encodes a value.
+fn put_suffix(kind: &ValueKind) -> &'static str {
+    match kind {
+        ValueKind::Int8 | ValueKind::UInt8 => "u8",
+        ValueKind::Int16 | ValueKind::UInt16 => "u16",
+        ValueKind::Int32 | ValueKind::UInt32 => "u32",
+        ValueKind::Int64 | ValueKind::UInt64 => "u64",
+        ValueKind::Float32 => "f32",
+        ValueKind::Float64 => "f64",
+    }
+}
+
+/// The cast needed to pass a value to its `fdr_put_` function.
+///
+/// Signed integers are converted to the unsigned type of the same size, which
+/// keeps the two's complement bits.
+fn put_cast(kind: &ValueKind) -> &'static str {
+    match kind {
+        ValueKind::Int8 => "(uint8_t)",
+        ValueKind::Int16 => "(uint16_t)",
+        ValueKind::Int32 => "(uint32_t)",
+        ValueKind::Int64 => "(uint64_t)",
+        _ => "",
+    }
+}
+
+/// Make a name usable as a C identifier.
+///
+/// Invalid characters are replaced with underscores, names that start with a
+/// digit are prefixed with an underscore, and C keywords have an underscore
+/// appended.
+pub fn c_identifier(name: &str) -> String {
+    let mut result: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    if !result.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        result.insert(0, '_');
+    }
+
+    if C_KEYWORDS.contains(&result.as_str()) {
+        result.push('_');
+    }
+
+    result
+}
+
+/// Make a name usable as a Rust function or argument name.
+///
+/// The name is made lowercase, invalid characters are replaced with
+/// underscores, names that start with a digit are prefixed with an underscore,
+/// and Rust keywords have an underscore appended.
+fn rust_identifier(name: &str) -> String {
+    let mut result: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if !result.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        result.insert(0, '_');
+    }
+
+    if RUST_KEYWORDS.contains(&result.as_str()) {
+        result.push('_');
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RAW_CONFIG: &str = include_str!("../example_config.json");
+
+    #[test]
+    fn test_c_identifier() {
+        assert_eq!(c_identifier("LSM"), "LSM");
+        assert_eq!(c_identifier("accel x"), "accel_x");
+        assert_eq!(c_identifier("9dof"), "_9dof");
+        assert_eq!(c_identifier("int"), "int_");
+        assert_eq!(c_identifier(""), "_");
+    }
+
+    #[test]
+    fn test_write_c_header() {
+        let config: RocketConfig = serde_json::from_str(RAW_CONFIG).unwrap();
+
+        let mut result = vec![];
+        write_c_header(&config, &mut result).unwrap();
+        let result = String::from_utf8(result).unwrap();
+
+        assert!(result.contains("#ifndef XENIA_2_FLIGHT_DATA_H"));
+        assert!(result.contains("#define SENSOR_BMP_ID 2\n#define SENSOR_BMP_PACKET_SIZE 9\n"));
+        assert!(result.contains("    float pressure;\n    float temperature;\n} bmp_packet_t;"));
+        assert!(result
+            .contains("static inline size_t log_bmp(uint8_t *buf, const bmp_packet_t *packet) {"));
+        assert!(result.contains("    fdr_put_f32(buf + 5, packet->temperature);"));
+        assert!(result.contains("    fdr_put_u32(buf + 1, (uint32_t)packet->int_);"));
+        // The example config is big endian.
+        assert!(
+            result.contains("    buf[0] = (uint8_t)(value >> 8);\n    buf[1] = (uint8_t)value;")
+        );
+    }
+
+    #[test]
+    fn test_rust_identifier() {
+        assert_eq!(rust_identifier("LSM"), "lsm");
+        assert_eq!(rust_identifier("integer test"), "integer_test");
+        assert_eq!(rust_identifier("type"), "type_");
+        assert_eq!(rust_identifier("int"), "int");
+        assert_eq!(rust_identifier(""), "_");
+    }
+
+    #[test]
+    fn test_write_rust_encoder_unnamed_values() {
+        let mut config: RocketConfig = serde_json::from_str(RAW_CONFIG).unwrap();
+        config.sensors[0].values[0].name = String::new();
+        config.sensors[0].values[1].name = "?".to_string();
+
+        let mut result = vec![];
+        write_rust_encoder(&config, &mut result).unwrap();
+        let result = String::from_utf8(result).unwrap();
+
+        assert!(result.contains("sink: &mut S, value_0: "));
+        assert!(result.contains(", value_1: "));
+        assert!(result.contains("&value_0.to_"));
+    }
+
+    #[test]
+    fn test_write_rust_encoder_reserved_names() {
+        let mut config: RocketConfig = serde_json::from_str(RAW_CONFIG).unwrap();
+
+        for reserved in ["packet", "Sink"] {
+            config.sensors[0].values[0].name = reserved.to_string();
+
+            let mut result = vec![];
+            let error = write_rust_encoder(&config, &mut result).unwrap_err();
+            assert!(error.to_string().contains("is reserved"), "{error}");
+        }
+    }
+
+    #[test]
+    fn test_write_c_header_duplicate_names() {
+        let mut config: RocketConfig = serde_json::from_str(RAW_CONFIG).unwrap();
+        config.sensors[0].values[1].name = "x".to_string();
+
+        let mut result = vec![];
+        assert!(write_c_header(&config, &mut result).is_err());
+    }
+}
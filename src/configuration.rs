@@ -1,12 +1,24 @@
 use std::collections::HashSet;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use std::path::Path;
 
 use serde::{Deserialize, Serialize};
 
+use include::{Include, SourcedSensor};
+
+pub mod format;
+pub mod include;
 #[cfg(test)]
 mod tests;
 
+/// The standard acceleration due to gravity in metres per second squared.
+pub const STANDARD_GRAVITY: f64 = 9.80665;
+
 /// The type of a single scalar value.
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum ValueKind {
     #[serde(rename = "int_8")]
     Int8,
@@ -30,9 +42,37 @@ pub enum ValueKind {
     Float64,
 }
 
-impl ToString for ValueKind {
-    fn to_string(&self) -> String {
+impl ValueKind {
+    /// The number of bytes the value is encoded as.
+    pub fn size(&self) -> usize {
         match self {
+            ValueKind::Int8 | ValueKind::UInt8 => 1,
+            ValueKind::Int16 | ValueKind::UInt16 => 2,
+            ValueKind::Int32 | ValueKind::UInt32 | ValueKind::Float32 => 4,
+            ValueKind::Int64 | ValueKind::UInt64 | ValueKind::Float64 => 8,
+        }
+    }
+
+    /// A stable number for the kind, used by [`RocketConfig::fingerprint`].
+    fn code(&self) -> u8 {
+        match self {
+            ValueKind::Int8 => 0,
+            ValueKind::Int16 => 1,
+            ValueKind::Int32 => 2,
+            ValueKind::Int64 => 3,
+            ValueKind::UInt8 => 4,
+            ValueKind::UInt16 => 5,
+            ValueKind::UInt32 => 6,
+            ValueKind::UInt64 => 7,
+            ValueKind::Float32 => 8,
+            ValueKind::Float64 => 9,
+        }
+    }
+}
+
+impl Display for ValueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
             ValueKind::Int8 => "int_8",
             ValueKind::Int16 => "int_16",
             ValueKind::Int32 => "int_32",
@@ -43,13 +83,147 @@ impl ToString for ValueKind {
             ValueKind::UInt64 => "uint_64",
             ValueKind::Float32 => "float_32",
             ValueKind::Float64 => "float_64",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A special meaning given to a value.
+///
+/// Roles let analysis and export code find the right values without relying
+/// on the names that were chosen for sensors and values.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ValueRole {
+    /// The time the packet was recorded.
+    ///
+    /// The unit of the value must be one of `s`, `ms`, `us` or `ns`, and is
+    /// milliseconds if not set. Packets from sensors without a time value are
+    /// considered to be recorded at the most recent time read.
+    Time,
+    /// The latitude of a GPS fix, in degrees.
+    Latitude,
+    /// The longitude of a GPS fix, in degrees.
+    Longitude,
+    /// The altitude above mean sea level of a GPS fix.
+    ///
+    /// The unit of the value must be either `m` or `ft`, and is metres if not
+    /// set.
+    Altitude,
+    /// The quality of a GPS fix, where higher is better.
+    ///
+    /// This is normally the NMEA fix quality, which is 0 for an invalid fix.
+    FixQuality,
+    /// The static pressure measured by a barometer.
+    ///
+    /// The unit of the value must be one of `Pa`, `hPa`, `mbar` or `kPa`, and
+    /// is pascals if not set.
+    Pressure,
+    /// The acceleration measured along the X axis of the accelerometer.
+    ///
+    /// The unit of the value must be either `m/s^2` or `g`, and is metres per
+    /// second squared if not set. Like any accelerometer reading, this
+    /// includes gravity.
+    AccelerationX,
+    /// The acceleration measured along the Y axis of the accelerometer.
+    ///
+    /// The units are the same as [`ValueRole::AccelerationX`].
+    AccelerationY,
+    /// The acceleration measured along the Z axis of the accelerometer.
+    ///
+    /// The units are the same as [`ValueRole::AccelerationX`].
+    AccelerationZ,
+    /// The air temperature, normally measured by the barometer.
+    ///
+    /// The unit of the value must be one of `C`, `K` or `F`, and is degrees
+    /// Celsius if not set.
+    Temperature,
+    /// The angular velocity about the X axis measured by the gyroscope.
+    ///
+    /// The unit of the value must be one of `rad/s`, `deg/s` or `dps`, and is
+    /// radians per second if not set. The axes of the gyroscope must be the
+    /// same as the axes of the accelerometer.
+    AngularVelocityX,
+    /// The angular velocity about the Y axis measured by the gyroscope.
+    ///
+    /// The units are the same as [`ValueRole::AngularVelocityX`].
+    AngularVelocityY,
+    /// The angular velocity about the Z axis measured by the gyroscope.
+    ///
+    /// The units are the same as [`ValueRole::AngularVelocityX`].
+    AngularVelocityZ,
+    /// The magnetic field along the X axis measured by the magnetometer.
+    ///
+    /// The unit of the value must be one of `T`, `mT`, `uT`, `nT`, `G` or
+    /// `mG`, and is microtesla if not set. The axes of the magnetometer must
+    /// be the same as the axes of the accelerometer.
+    MagneticFieldX,
+    /// The magnetic field along the Y axis measured by the magnetometer.
+    ///
+    /// The units are the same as [`ValueRole::MagneticFieldX`].
+    MagneticFieldY,
+    /// The magnetic field along the Z axis measured by the magnetometer.
+    ///
+    /// The units are the same as [`ValueRole::MagneticFieldX`].
+    MagneticFieldZ,
+}
+
+/// A direction along one of the axes of the sensors.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Axis {
+    #[serde(rename = "x")]
+    X,
+    #[serde(rename = "y")]
+    Y,
+    /// This is the default value.
+    #[default]
+    #[serde(rename = "z")]
+    Z,
+    #[serde(rename = "-x")]
+    NegativeX,
+    #[serde(rename = "-y")]
+    NegativeY,
+    #[serde(rename = "-z")]
+    NegativeZ,
+}
+
+impl Axis {
+    /// The role of the acceleration value measured along this axis.
+    pub fn acceleration_role(&self) -> ValueRole {
+        match self {
+            Axis::X | Axis::NegativeX => ValueRole::AccelerationX,
+            Axis::Y | Axis::NegativeY => ValueRole::AccelerationY,
+            Axis::Z | Axis::NegativeZ => ValueRole::AccelerationZ,
+        }
+    }
+
+    /// The unit vector along this axis.
+    pub fn vector(&self) -> [f64; 3] {
+        let mut vector = [0.0; 3];
+        let index = match self {
+            Axis::X | Axis::NegativeX => 0,
+            Axis::Y | Axis::NegativeY => 1,
+            Axis::Z | Axis::NegativeZ => 2,
+        };
+        vector[index] = self.sign();
+        vector
+    }
+
+    /// Either 1 or -1 depending on whether this is the positive or negative
+    /// direction of the axis.
+    pub fn sign(&self) -> f64 {
+        match self {
+            Axis::X | Axis::Y | Axis::Z => 1.0,
+            Axis::NegativeX | Axis::NegativeY | Axis::NegativeZ => -1.0,
         }
-        .to_string()
     }
 }
 
 /// A single value.
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ValueConfig {
     /// The name of the value.
     pub name: String,
@@ -58,6 +232,301 @@ pub struct ValueConfig {
     /// This is important as different kinds of value may have a different
     /// number of bytes.
     pub data_type: ValueKind,
+    /// The unit of the value, such as `ms` or `hPa`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    /// The special meaning of this value, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<ValueRole>,
+    /// The filters applied to the value when it is put in a table, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<FilterConfig>,
+}
+
+/// A filter that cleans up the samples of a value.
+///
+/// Every filter is causal, so the output for a sample only depends on it and
+/// the samples before it. Windows and rates are counted in samples of the
+/// value, not in time.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum FilterConfig {
+    /// The mean of the most recent samples.
+    MovingAverage {
+        /// The number of samples averaged.
+        window: usize,
+    },
+    /// The median of the most recent samples.
+    Median {
+        /// The number of samples the median is taken from.
+        window: usize,
+    },
+    /// Replace samples far from the median of the most recent samples with
+    /// the median.
+    Despike {
+        /// The number of samples the median is taken from.
+        window: usize,
+        /// How many standard deviations from the median a sample must be to
+        /// be replaced, estimated from the median absolute deviation.
+        #[serde(default = "FilterConfig::default_despike_threshold")]
+        threshold: f64,
+    },
+    /// An exponential moving average.
+    Exponential {
+        /// How much of each new sample is added, between 0 and 1.
+        alpha: f64,
+    },
+    /// A second order Butterworth low-pass filter.
+    LowPass {
+        /// The cutoff frequency in hertz.
+        cutoff: f64,
+        /// The rate of the samples in hertz.
+        sample_rate: f64,
+    },
+    /// A second order Butterworth high-pass filter.
+    HighPass {
+        /// The cutoff frequency in hertz.
+        cutoff: f64,
+        /// The rate of the samples in hertz.
+        sample_rate: f64,
+    },
+    /// A Savitzky-Golay filter, which fits a polynomial to the most recent
+    /// samples and takes its value or a derivative at the latest sample.
+    SavitzkyGolay {
+        /// The number of samples the polynomial is fitted to.
+        window: usize,
+        /// The order of the polynomial.
+        order: usize,
+        /// Which derivative is taken, where 0 smooths the value.
+        #[serde(default)]
+        derivative: usize,
+        /// The rate of the samples in hertz, which scales derivatives.
+        #[serde(default = "FilterConfig::default_sample_rate")]
+        sample_rate: f64,
+    },
+}
+
+impl FilterConfig {
+    fn default_despike_threshold() -> f64 {
+        3.0
+    }
+
+    fn default_sample_rate() -> f64 {
+        1.0
+    }
+
+    /// Check that the parameters of the filter make sense.
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            FilterConfig::MovingAverage { window }
+            | FilterConfig::Median { window }
+            | FilterConfig::Despike { window, .. }
+                if window == 0 =>
+            {
+                Err("The window of a filter must have at least one sample".to_string())
+            }
+            FilterConfig::Despike { threshold, .. } if threshold.is_nan() || threshold <= 0.0 => {
+                Err("The despike threshold must be positive".to_string())
+            }
+            FilterConfig::Exponential { alpha } if !(alpha > 0.0 && alpha <= 1.0) => {
+                Err("The exponential filter alpha must be above 0 and at most 1".to_string())
+            }
+            FilterConfig::LowPass {
+                cutoff,
+                sample_rate,
+            }
+            | FilterConfig::HighPass {
+                cutoff,
+                sample_rate,
+            } if !(cutoff > 0.0 && cutoff < sample_rate / 2.0) => Err(format!(
+                "The cutoff must be between 0 and half the sample rate, {} Hz",
+                sample_rate / 2.0
+            )),
+            FilterConfig::SavitzkyGolay {
+                window,
+                order,
+                derivative,
+                sample_rate,
+            } => {
+                if order >= window {
+                    Err("The Savitzky-Golay order must be less than the window".to_string())
+                } else if derivative > order {
+                    Err("The Savitzky-Golay derivative must be at most the order".to_string())
+                } else if sample_rate.is_nan() || sample_rate <= 0.0 {
+                    Err("The sample rate must be positive".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FromStr for FilterConfig {
+    type Err = String;
+
+    /// Read a filter written as its type followed by its parameters, such as
+    /// `low_pass,cutoff=5,sample_rate=100`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',');
+        let kind = parts.next().unwrap_or_default().trim();
+
+        let mut fields = serde_json::Map::new();
+        fields.insert("type".to_string(), kind.into());
+        for part in parts {
+            let Some((key, value)) = part.split_once('=') else {
+                return Err(format!("Expected a parameter as key=value: {part}"));
+            };
+            let value: serde_json::Value = value
+                .trim()
+                .parse::<serde_json::Number>()
+                .map_err(|_| format!("Expected a number for {key}: {value}"))?
+                .into();
+            fields.insert(key.trim().to_string(), value);
+        }
+
+        let filter: FilterConfig = serde_json::from_value(fields.into())
+            .map_err(|e| format!("Invalid filter {s}: {e}"))?;
+        filter.validate()?;
+        Ok(filter)
+    }
+}
+
+impl ValueConfig {
+    /// The number of seconds in one unit of a time value.
+    ///
+    /// Values without a unit are assumed to be in milliseconds. This is `None`
+    /// if the unit is not a known unit of time.
+    pub fn seconds_per_unit(&self) -> Option<f64> {
+        match self.unit.as_deref() {
+            Some("s") => Some(1.0),
+            Some("ms") | None => Some(1e-3),
+            Some("us") | Some("µs") => Some(1e-6),
+            Some("ns") => Some(1e-9),
+            Some(_) => None,
+        }
+    }
+
+    /// The number of metres in one unit of a length value.
+    ///
+    /// Values without a unit are assumed to be in metres. This is `None` if
+    /// the unit is not a known unit of length.
+    pub fn metres_per_unit(&self) -> Option<f64> {
+        match self.unit.as_deref() {
+            Some("m") | None => Some(1.0),
+            Some("ft") => Some(0.3048),
+            Some(_) => None,
+        }
+    }
+
+    /// The number of pascals in one unit of a pressure value.
+    ///
+    /// Values without a unit are assumed to be in pascals. This is `None` if
+    /// the unit is not a known unit of pressure.
+    pub fn pascals_per_unit(&self) -> Option<f64> {
+        match self.unit.as_deref() {
+            Some("Pa") | None => Some(1.0),
+            Some("hPa") | Some("mbar") => Some(100.0),
+            Some("kPa") => Some(1000.0),
+            Some(_) => None,
+        }
+    }
+
+    /// The number of metres per second squared in one unit of an acceleration
+    /// value.
+    ///
+    /// Values without a unit are assumed to be in metres per second squared.
+    /// This is `None` if the unit is not a known unit of acceleration.
+    pub fn metres_per_second_squared_per_unit(&self) -> Option<f64> {
+        match self.unit.as_deref() {
+            Some("m/s^2") | Some("m/s²") | None => Some(1.0),
+            Some("g") => Some(STANDARD_GRAVITY),
+            Some(_) => None,
+        }
+    }
+
+    /// The number of radians per second in one unit of an angular velocity
+    /// value.
+    ///
+    /// Values without a unit are assumed to be in radians per second. This is
+    /// `None` if the unit is not a known unit of angular velocity.
+    pub fn radians_per_second_per_unit(&self) -> Option<f64> {
+        match self.unit.as_deref() {
+            Some("rad/s") | None => Some(1.0),
+            Some("deg/s") | Some("°/s") | Some("dps") => Some(std::f64::consts::PI / 180.0),
+            Some(_) => None,
+        }
+    }
+
+    /// The number of tesla in one unit of a magnetic field value.
+    ///
+    /// Values without a unit are assumed to be in microtesla. This is `None`
+    /// if the unit is not a known unit of magnetic field.
+    pub fn tesla_per_unit(&self) -> Option<f64> {
+        match self.unit.as_deref() {
+            Some("T") => Some(1.0),
+            Some("mT") => Some(1e-3),
+            Some("uT") | Some("µT") | None => Some(1e-6),
+            Some("nT") => Some(1e-9),
+            Some("G") => Some(1e-4),
+            Some("mG") => Some(1e-7),
+            Some(_) => None,
+        }
+    }
+
+    /// The number of kelvin in one degree of a temperature value.
+    ///
+    /// Values without a unit are assumed to be in degrees Celsius. This is
+    /// `None` if the unit is not a known unit of temperature.
+    pub fn kelvin_per_unit(&self) -> Option<f64> {
+        match self.unit.as_deref() {
+            Some("C") | Some("°C") | Some("K") | None => Some(1.0),
+            Some("F") | Some("°F") => Some(5.0 / 9.0),
+            Some(_) => None,
+        }
+    }
+
+    /// The number of SI units in one unit of this value, based on its role.
+    ///
+    /// Values without a role, or with a role that has no unit, are not scaled.
+    /// This is `None` if the unit is not known for the role of the value.
+    pub fn si_per_unit(&self) -> Option<f64> {
+        match self.role {
+            Some(ValueRole::Time) => self.seconds_per_unit(),
+            Some(ValueRole::Altitude) => self.metres_per_unit(),
+            Some(ValueRole::Pressure) => self.pascals_per_unit(),
+            Some(ValueRole::AccelerationX)
+            | Some(ValueRole::AccelerationY)
+            | Some(ValueRole::AccelerationZ) => self.metres_per_second_squared_per_unit(),
+            Some(ValueRole::Temperature) => self.kelvin_per_unit(),
+            Some(ValueRole::AngularVelocityX)
+            | Some(ValueRole::AngularVelocityY)
+            | Some(ValueRole::AngularVelocityZ) => self.radians_per_second_per_unit(),
+            Some(ValueRole::MagneticFieldX)
+            | Some(ValueRole::MagneticFieldY)
+            | Some(ValueRole::MagneticFieldZ) => self.tesla_per_unit(),
+            _ => Some(1.0),
+        }
+    }
+
+    /// The SI value at zero in the unit of this value, after scaling by
+    /// [`ValueConfig::si_per_unit`].
+    ///
+    /// This is only needed for temperatures, as the zero of Celsius and
+    /// Fahrenheit is not absolute zero.
+    pub fn si_offset(&self) -> f64 {
+        if self.role != Some(ValueRole::Temperature) {
+            return 0.0;
+        }
+
+        match self.unit.as_deref() {
+            Some("C") | Some("°C") | None => 273.15,
+            Some("F") | Some("°F") => 459.67 * 5.0 / 9.0,
+            _ => 0.0,
+        }
+    }
 }
 
 /// Configuration for data from a sensor.
@@ -65,6 +534,7 @@ pub struct ValueConfig {
 /// A sensor in the sense of this file format is simply a collection of values
 /// that are read at the same time.
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct SensorConfig {
     /// The name of the sensor.
     pub name: String,
@@ -80,6 +550,7 @@ pub struct SensorConfig {
 /// of the chip can be used instead of forcing the limited resources of the
 /// embedded device to do the conversion.
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Endianess {
     /// Multi byte values are reversed.
     Little,
@@ -104,8 +575,56 @@ impl Default for Endianess {
     }
 }
 
+/// The noise used by the Kalman filter that estimates the altitude of the
+/// rocket.
+///
+/// Every field is optional and is a standard deviation. Larger process noise
+/// makes the filter follow the measurements more closely, and larger
+/// measurement noise makes it trust them less.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct KalmanConfig {
+    /// How much the acceleration is expected to change, in metres per second
+    /// cubed.
+    pub process_noise: f64,
+    /// The noise of the altitude from the barometer, in metres.
+    pub pressure_noise: f64,
+    /// The noise of the accelerometer, in metres per second squared.
+    pub acceleration_noise: f64,
+}
+
+impl Default for KalmanConfig {
+    fn default() -> Self {
+        Self {
+            process_noise: 10.0,
+            pressure_noise: 2.0,
+            acceleration_noise: 0.5,
+        }
+    }
+}
+
+impl KalmanConfig {
+    /// Check that every noise is a positive number.
+    pub fn validate(&self) -> Result<(), String> {
+        let noises = [
+            ("process", self.process_noise),
+            ("pressure", self.pressure_noise),
+            ("acceleration", self.acceleration_noise),
+        ];
+        for (name, noise) in noises {
+            if !(noise.is_finite() && noise > 0.0) {
+                return Err(format!("The Kalman {name} noise must be positive: {noise}"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// Configuration for a single rocket.
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct RocketConfig {
     /// The name of the rocket that is launching.
     ///
@@ -117,24 +636,50 @@ pub struct RocketConfig {
     /// The endianess of all values in the data log binary file.
     #[serde(default = "Endianess::default")]
     pub endianess: Endianess,
+    /// The axis of the accelerometer that points towards the nose of the
+    /// rocket.
+    #[serde(default)]
+    pub nose_axis: Axis,
+    /// Files of shared sensor definitions whose sensors are added to this
+    /// config.
+    ///
+    /// These are resolved by [`RocketConfig::resolve_includes`], which
+    /// [`crate::load_config`] does when loading a file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<Include>,
     /// The display name of the rocket, if different from the name field.
     ///
     /// This field is only used for report generating purposes.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub display_name: Option<String>,
     /// A description of the rocket.
     ///
     /// This should start with a complete sentence so that it can be placed in
     /// a sentence in the generated report and fit in. For example starting with
     /// "The rocket is..." or "Xenia-2 is...", or something to that effect.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    /// The noise used by the Kalman filter, if different from the defaults.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kalman: Option<KalmanConfig>,
+}
+
+/// The JSON Schema of config files, generated from [`RocketConfig`].
+#[cfg(feature = "schema")]
+pub fn json_schema() -> serde_json::Value {
+    schemars::schema_for!(RocketConfig).to_value()
 }
 
 impl RocketConfig {
     /// Validate the configuration.
     ///
-    /// Currently this checks that there are no duplicate sensor IDs.
+    /// Currently this checks that there are no duplicate sensor IDs, that
+    /// roles other than time are only given to one value, that values with a
+    /// role have a known unit, that the parameters of filters make sense and
+    /// that the Kalman filter noises are positive.
     pub fn validate(&self) -> Result<(), String> {
         let mut ids: HashSet<u8> = HashSet::new();
+        let mut roles: HashSet<ValueRole> = HashSet::new();
 
         for sensor in self.sensors.iter() {
             if ids.contains(&sensor.id) {
@@ -142,11 +687,92 @@ impl RocketConfig {
             } else {
                 ids.insert(sensor.id);
             }
+
+            for value in sensor.values.iter() {
+                match value.role {
+                    Some(ValueRole::Time) | None => {}
+                    Some(role) => {
+                        if !roles.insert(role) {
+                            return Err(format!("Multiple values with role: {role:?}"));
+                        }
+                    }
+                }
+
+                if value.si_per_unit().is_none() {
+                    return Err(format!(
+                        "Unknown unit for {}_{}: {}",
+                        sensor.name,
+                        value.name,
+                        value.unit.as_deref().unwrap_or_default()
+                    ));
+                }
+            }
+        }
+
+        self.validate_filters()?;
+
+        if let Some(kalman) = &self.kalman {
+            kalman.validate()?;
         }
 
         Ok(())
     }
 
+    /// Check that the parameters of every filter make sense.
+    ///
+    /// This is part of [`RocketConfig::validate`], and is also done when a
+    /// config is loaded, since a filter with invalid parameters doesn't fail
+    /// when it is applied.
+    pub fn validate_filters(&self) -> Result<(), String> {
+        for sensor in self.sensors.iter() {
+            for value in sensor.values.iter() {
+                for filter in value.filters.iter() {
+                    filter.validate().map_err(|e| {
+                        format!("Invalid filter for {}_{}: {e}", sensor.name, value.name)
+                    })?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add the sensors of the included files to the config.
+    ///
+    /// The included sensors come before the sensors of the config, and the
+    /// include list is empty afterwards.
+    ///
+    /// # Params
+    ///
+    /// * `path` - The path of this config file, which include paths are
+    ///   relative to.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if a file can't be loaded, the files include each
+    /// other, an override names a sensor that doesn't exist, or two sensors
+    /// end up with the same ID or name.
+    pub fn resolve_includes<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
+        if self.include.is_empty() {
+            return Ok(());
+        }
+
+        let path = path.as_ref();
+        let mut stack: Vec<_> = path.canonicalize().into_iter().collect();
+
+        let mut sensors = include::resolve(&self.include, path, &mut stack)?;
+        sensors.extend(self.sensors.drain(..).map(|sensor| SourcedSensor {
+            sensor,
+            source: path.to_path_buf(),
+        }));
+        include::check_conflicts(&sensors)?;
+
+        self.sensors = sensors.into_iter().map(|s| s.sensor).collect();
+        self.include.clear();
+
+        Ok(())
+    }
+
     /// Get the display name of the rocket from either the optional display name
     /// field or the name field if the display name field is not set.
     pub fn display_name(&self) -> &String {
@@ -156,9 +782,55 @@ impl RocketConfig {
         }
     }
 
+    /// Find the first value with the given role.
+    ///
+    /// # Returns
+    ///
+    /// The sensor containing the value and the index of the value within the
+    /// sensor, or `None` if no value has the role.
+    pub fn find_role(&self, role: ValueRole) -> Option<(&SensorConfig, usize)> {
+        self.sensors.iter().find_map(|sensor| {
+            let index = sensor.values.iter().position(|v| v.role == Some(role))?;
+            Some((sensor, index))
+        })
+    }
+
+    /// A hash of the layout of the data log.
+    ///
+    /// The fingerprint only covers what is needed to decode a log: the
+    /// endianess, the sensor IDs, and the kind and order of the values of each
+    /// sensor. Names, units, roles and descriptions can change without changing
+    /// the fingerprint, and so can the order of the sensors in the config.
+    ///
+    /// This is stable between versions of this library, so it can be logged by
+    /// the flight computer and compared with a config when reading the log.
+    pub fn fingerprint(&self) -> u64 {
+        let mut sensors: Vec<&SensorConfig> = self.sensors.iter().collect();
+        sensors.sort_by_key(|sensor| sensor.id);
+
+        let mut layout = vec![u8::from(self.endianess.is_big())];
+        for sensor in sensors {
+            layout.push(sensor.id);
+            layout.extend_from_slice(&(sensor.values.len() as u32).to_le_bytes());
+            layout.extend(sensor.values.iter().map(|value| value.data_type.code()));
+        }
+
+        fnv1a(&layout)
+    }
+
     /// Get the sensor configuration based on an ID, if it exists.
     pub fn get_sensor_by_id(&self, id: u8) -> Option<&SensorConfig> {
         // TODO: A way to keep this sorted would be handy for performance.
         self.sensors.iter().find(|&sensor| sensor.id == id)
     }
 }
+
+/// The 64 bit FNV-1a hash of some bytes.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
@@ -0,0 +1,183 @@
+use std::fmt::Display;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+
+use super::RocketConfig;
+
+/// A file format that rocket configs can be written in.
+///
+/// JSON is always supported. YAML and TOML need the `yaml` and `toml`
+/// features, and give an error when used without them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl ConfigFormat {
+    /// The format of a config file from its extension, if it is known.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+
+        match extension.as_str() {
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "toml" => Some(Self::Toml),
+            _ => None,
+        }
+    }
+
+    /// Guess the format of a config from its content.
+    ///
+    /// JSON configs start with `{`. Otherwise, the first line that isn't empty
+    /// or a comment is used: TOML if it is a table header or a `key = value`
+    /// pair, and YAML for anything else.
+    pub fn detect(content: &str) -> Self {
+        let content = content.trim_start_matches('\u{feff}').trim_start();
+        if content.starts_with('{') {
+            return Self::Json;
+        }
+
+        let first_line = content
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'));
+
+        let Some(line) = first_line else {
+            return Self::Yaml;
+        };
+
+        let is_table = line.starts_with('[');
+        let is_pair = match (line.find('='), line.find(':')) {
+            (Some(equals), Some(colon)) => equals < colon,
+            (Some(_), None) => true,
+            _ => false,
+        };
+
+        if is_table || is_pair {
+            Self::Toml
+        } else {
+            Self::Yaml
+        }
+    }
+
+    /// Parse a config written in this format.
+    pub fn parse(&self, content: &str) -> Result<RocketConfig, String> {
+        self.deserialize(content)
+    }
+
+    /// Parse any type that can be read from configs written in this format.
+    pub fn deserialize<T: DeserializeOwned>(&self, content: &str) -> Result<T, String> {
+        match self {
+            Self::Json => serde_json::from_str(content).map_err(|e| e.to_string()),
+            #[cfg(feature = "yaml")]
+            Self::Yaml => serde_yaml::from_str(content).map_err(|e| e.to_string()),
+            #[cfg(feature = "toml")]
+            Self::Toml => toml::from_str(content).map_err(|e| e.to_string()),
+            #[allow(unreachable_patterns)]
+            _ => Err(format!("Support for {self} configs is not enabled")),
+        }
+    }
+
+    /// Write a config in this format.
+    pub fn write(&self, config: &RocketConfig) -> Result<String, String> {
+        match self {
+            Self::Json => serde_json::to_string_pretty(config).map_err(|e| e.to_string()),
+            #[cfg(feature = "yaml")]
+            Self::Yaml => serde_yaml::to_string(config).map_err(|e| e.to_string()),
+            #[cfg(feature = "toml")]
+            Self::Toml => toml::to_string_pretty(config).map_err(|e| e.to_string()),
+            #[allow(unreachable_patterns)]
+            _ => Err(format!("Support for {self} configs is not enabled")),
+        }
+    }
+}
+
+impl Display for ConfigFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Json => "JSON",
+            Self::Yaml => "YAML",
+            Self::Toml => "TOML",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RAW_CONFIG: &str = include_str!("../../example_config.json");
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(
+            ConfigFormat::from_path("rocket.json"),
+            Some(ConfigFormat::Json)
+        );
+        assert_eq!(
+            ConfigFormat::from_path("a/rocket.YML"),
+            Some(ConfigFormat::Yaml)
+        );
+        assert_eq!(
+            ConfigFormat::from_path("rocket.toml"),
+            Some(ConfigFormat::Toml)
+        );
+        assert_eq!(ConfigFormat::from_path("rocket"), None);
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(ConfigFormat::detect(RAW_CONFIG), ConfigFormat::Json);
+        assert_eq!(
+            ConfigFormat::detect("# Wiring notes\nname: Xenia-2\n"),
+            ConfigFormat::Yaml
+        );
+        assert_eq!(
+            ConfigFormat::detect("# Wiring notes\nname = \"Xenia-2\"\n"),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::detect("[[sensors]]\nid = 1\n"),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::detect("description: \"a = b\"\n"),
+            ConfigFormat::Yaml
+        );
+    }
+
+    #[cfg(all(feature = "yaml", feature = "toml"))]
+    #[test]
+    fn test_round_trip() {
+        let config = ConfigFormat::Json.parse(RAW_CONFIG).unwrap();
+
+        for format in [ConfigFormat::Json, ConfigFormat::Yaml, ConfigFormat::Toml] {
+            let written = format.write(&config).unwrap();
+            assert_eq!(ConfigFormat::detect(&written), format);
+
+            let parsed = format.parse(&written).unwrap();
+            assert_eq!(parsed.name, config.name);
+            assert_eq!(parsed.fingerprint(), config.fingerprint());
+        }
+    }
+
+    #[cfg(all(feature = "yaml", feature = "toml"))]
+    #[test]
+    fn test_invalid_kind() {
+        let yaml = "name: test\nsensors:\n  - name: a\n    id: 1\n    values:\n      - name: x\n        data_type: float_16\n";
+        let toml = "name = \"test\"\n[[sensors]]\nname = \"a\"\nid = 1\nvalues = [{ name = \"x\", data_type = \"float_16\" }]\n";
+
+        assert!(ConfigFormat::Yaml
+            .parse(yaml)
+            .unwrap_err()
+            .contains("float_16"));
+        assert!(ConfigFormat::Toml
+            .parse(toml)
+            .unwrap_err()
+            .contains("float_16"));
+    }
+}
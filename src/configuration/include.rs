@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::format::ConfigFormat;
+use super::SensorConfig;
+
+/// A file of shared sensor definitions included in a config.
+///
+/// The sensors of the file are added to the config before its own sensors.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Include {
+    /// The path of the file, relative to the file that includes it.
+    pub path: String,
+    /// Changes to the included sensors, by the name of the sensor in the file.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub overrides: BTreeMap<String, SensorOverride>,
+}
+
+/// Changes made to a sensor from an included file.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SensorOverride {
+    /// The ID the sensor has on this rocket.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<u8>,
+    /// The name the sensor has on this rocket.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// A file of sensor definitions that can be included in configs.
+///
+/// Libraries can include other libraries.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SensorLibrary {
+    /// Other libraries whose sensors are added before the sensors of this one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<Include>,
+    /// The sensors defined in this library.
+    #[serde(default)]
+    pub sensors: Vec<SensorConfig>,
+}
+
+/// A sensor and the file it was defined in.
+pub(super) struct SourcedSensor {
+    pub sensor: SensorConfig,
+    pub source: PathBuf,
+}
+
+/// Load the sensors of the included files, including the files they include.
+///
+/// # Params
+///
+/// * `includes` - The files to include.
+/// * `base` - The path of the file that includes them.
+/// * `stack` - The files that are being included, to detect cycles.
+pub(super) fn resolve(
+    includes: &[Include],
+    base: &Path,
+    stack: &mut Vec<PathBuf>,
+) -> Result<Vec<SourcedSensor>, String> {
+    let directory = base.parent().unwrap_or(Path::new(""));
+    let mut result = vec![];
+
+    for include in includes {
+        let path = directory.join(&include.path);
+        let path = path
+            .canonicalize()
+            .map_err(|e| format!("Could not include {}: {e}", path.display()))?;
+
+        if stack.contains(&path) {
+            let cycle: Vec<String> = stack
+                .iter()
+                .chain(std::iter::once(&path))
+                .map(|p| p.display().to_string())
+                .collect();
+            return Err(format!("Include cycle: {}", cycle.join(" -> ")));
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Could not include {}: {e}", path.display()))?;
+        let format =
+            ConfigFormat::from_path(&path).unwrap_or_else(|| ConfigFormat::detect(&content));
+        let library: SensorLibrary = format
+            .deserialize(&content)
+            .map_err(|e| format!("Could not include {}: {e}", path.display()))?;
+
+        stack.push(path.clone());
+        let mut sensors = resolve(&library.include, &path, stack)?;
+        stack.pop();
+
+        sensors.extend(library.sensors.into_iter().map(|sensor| SourcedSensor {
+            sensor,
+            source: path.clone(),
+        }));
+
+        for (name, sensor_override) in include.overrides.iter() {
+            let Some(sourced) = sensors.iter_mut().find(|s| &s.sensor.name == name) else {
+                return Err(format!(
+                    "Could not override {name}: {} has no sensor with that name",
+                    path.display()
+                ));
+            };
+
+            if let Some(id) = sensor_override.id {
+                sourced.sensor.id = id;
+            }
+            if let Some(new_name) = &sensor_override.name {
+                sourced.sensor.name = new_name.clone();
+            }
+        }
+
+        result.extend(sensors);
+    }
+
+    Ok(result)
+}
+
+/// Check that no two sensors have the same ID or name.
+pub(super) fn check_conflicts(sensors: &[SourcedSensor]) -> Result<(), String> {
+    let mut ids: HashMap<u8, &SourcedSensor> = HashMap::new();
+    let mut names: HashMap<&str, &SourcedSensor> = HashMap::new();
+
+    for sourced in sensors {
+        if let Some(other) = ids.insert(sourced.sensor.id, sourced) {
+            return Err(format!(
+                "Sensor ID {} is used by both {} from {} and {} from {}",
+                sourced.sensor.id,
+                other.sensor.name,
+                other.source.display(),
+                sourced.sensor.name,
+                sourced.source.display()
+            ));
+        }
+
+        if let Some(other) = names.insert(&sourced.sensor.name, sourced) {
+            return Err(format!(
+                "Sensor name {} is used by both {} and {}",
+                sourced.sensor.name,
+                other.source.display(),
+                sourced.source.display()
+            ));
+        }
+    }
+
+    Ok(())
+}
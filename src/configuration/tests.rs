@@ -7,8 +7,11 @@ fn test_validate_with_conflicting_ids() {
     let config = RocketConfig {
         name: "test".to_string(),
         endianess: Endianess::default(),
+        nose_axis: Axis::default(),
+        include: vec![],
         display_name: None,
         description: None,
+        kalman: None,
         sensors: vec![
             SensorConfig {
                 name: "sensor_a".to_string(),
@@ -31,8 +34,11 @@ fn test_validate_normal() {
     let config = RocketConfig {
         name: "test".to_string(),
         endianess: Endianess::default(),
+        nose_axis: Axis::default(),
+        include: vec![],
         display_name: None,
         description: None,
+        kalman: None,
         sensors: vec![
             SensorConfig {
                 name: "sensor_a".to_string(),
@@ -50,6 +56,29 @@ fn test_validate_normal() {
     assert!(config.validate().is_ok());
 }
 
+#[test]
+fn test_validate_kalman_noise() {
+    let mut config = RocketConfig {
+        name: "test".to_string(),
+        endianess: Endianess::default(),
+        nose_axis: Axis::default(),
+        include: vec![],
+        display_name: None,
+        description: None,
+        kalman: Some(KalmanConfig::default()),
+        sensors: vec![],
+    };
+    assert!(config.validate().is_ok());
+
+    for noise in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        config.kalman = Some(KalmanConfig {
+            acceleration_noise: noise,
+            ..Default::default()
+        });
+        assert!(config.validate().is_err(), "{noise}");
+    }
+}
+
 #[test]
 fn test_sensor_by_id() {
     let config: RocketConfig = serde_json::from_value(json!({
@@ -84,3 +113,266 @@ fn test_load_from_json() {
     assert_eq!(config.sensors.len(), 3);
     assert_eq!(config.get_sensor_by_id(1).unwrap().name, "LSM");
 }
+
+#[test]
+fn test_fingerprint() {
+    let config: RocketConfig = serde_json::from_value(json!({
+        "name": "test",
+        "endianess": "Little",
+        "sensors": [
+            {
+                "name": "a",
+                "id": 1,
+                "values": [
+                    { "name": "x", "data_type": "float_32" },
+                    { "name": "y", "data_type": "int_8" }
+                ]
+            },
+            {
+                "name": "b",
+                "id": 2,
+                "values": [{ "name": "z", "data_type": "uint_16" }]
+            }
+        ]
+    }))
+    .unwrap();
+    let fingerprint = config.fingerprint();
+
+    // The fingerprint is logged by firmware, so it must never change.
+    assert_eq!(fingerprint, 0xe180_5f61_f9ac_1206);
+
+    let mut renamed = config.clone();
+    renamed.name = "other".to_string();
+    renamed.sensors[0].values[0].name = "w".to_string();
+    renamed.sensors[0].values[0].unit = Some("m".to_string());
+    renamed.sensors.reverse();
+    assert_eq!(renamed.fingerprint(), fingerprint);
+
+    let mut reordered = config.clone();
+    reordered.sensors[0].values.reverse();
+    assert_ne!(reordered.fingerprint(), fingerprint);
+
+    let mut retyped = config.clone();
+    retyped.sensors[1].values[0].data_type = ValueKind::Int16;
+    assert_ne!(retyped.fingerprint(), fingerprint);
+
+    let mut big = config.clone();
+    big.endianess = Endianess::Big;
+    assert_ne!(big.fingerprint(), fingerprint);
+}
+
+#[test]
+fn test_fnv1a() {
+    assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+}
+
+#[cfg(feature = "schema")]
+#[test]
+fn test_json_schema() {
+    let schema = json_schema();
+
+    let required = schema["required"].as_array().unwrap();
+    assert!(required.contains(&json!("name")));
+    assert!(required.contains(&json!("sensors")));
+    assert!(!required.contains(&json!("endianess")));
+
+    let schema = schema.to_string();
+    for name in [
+        "float_32",
+        "uint_64",
+        "Little",
+        "-z",
+        "fix_quality",
+        "data_type",
+    ] {
+        assert!(schema.contains(&format!("\"{name}\"")), "{name} missing");
+    }
+}
+
+/// Write files to a new directory for a test, returning the directory.
+fn write_files(test: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+    let directory = std::env::temp_dir().join(format!("flight_data_reader_{test}"));
+    let _ = std::fs::remove_dir_all(&directory);
+
+    for (name, content) in files {
+        let path = directory.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    directory
+}
+
+#[test]
+fn test_include_with_overrides() {
+    let directory = write_files(
+        "include_with_overrides",
+        &[
+            (
+                "boards/mpu.json",
+                r#"{ "sensors": [{ "name": "MPU", "id": 2, "values": [{ "name": "x", "data_type": "int_16" }] }] }"#,
+            ),
+            (
+                "boards/avionics.json",
+                r#"{ "include": [{ "path": "mpu.json" }], "sensors": [{ "name": "BMP", "id": 1, "values": [] }] }"#,
+            ),
+            (
+                "rocket.json",
+                r#"{
+                    "name": "test",
+                    "include": [{ "path": "boards/avionics.json", "overrides": { "MPU": { "id": 5, "name": "IMU" } } }],
+                    "sensors": [{ "name": "GPS", "id": 2, "values": [] }]
+                }"#,
+            ),
+        ],
+    );
+
+    let config = crate::load_config(directory.join("rocket.json")).unwrap();
+
+    let sensors: Vec<(&str, u8)> = config
+        .sensors
+        .iter()
+        .map(|s| (s.name.as_str(), s.id))
+        .collect();
+    assert_eq!(sensors, vec![("IMU", 5), ("BMP", 1), ("GPS", 2)]);
+    assert_eq!(config.sensors[0].values[0].data_type, ValueKind::Int16);
+    assert!(config.include.is_empty());
+}
+
+#[test]
+fn test_include_conflict() {
+    let directory = write_files(
+        "include_conflict",
+        &[
+            (
+                "mpu.json",
+                r#"{ "sensors": [{ "name": "MPU", "id": 2, "values": [] }] }"#,
+            ),
+            (
+                "rocket.json",
+                r#"{ "name": "test", "include": [{ "path": "mpu.json" }], "sensors": [{ "name": "GPS", "id": 2, "values": [] }] }"#,
+            ),
+        ],
+    );
+
+    let error = crate::load_config(directory.join("rocket.json")).unwrap_err();
+
+    assert!(
+        error.starts_with("Sensor ID 2 is used by both MPU from"),
+        "{error}"
+    );
+    assert!(error.contains("mpu.json and GPS from"), "{error}");
+}
+
+#[test]
+fn test_include_cycle() {
+    let directory = write_files(
+        "include_cycle",
+        &[
+            ("a.json", r#"{ "include": [{ "path": "b.json" }] }"#),
+            ("b.json", r#"{ "include": [{ "path": "a.json" }] }"#),
+            (
+                "rocket.json",
+                r#"{ "name": "test", "include": [{ "path": "a.json" }], "sensors": [] }"#,
+            ),
+        ],
+    );
+
+    let error = crate::load_config(directory.join("rocket.json")).unwrap_err();
+
+    assert!(error.starts_with("Include cycle: "), "{error}");
+    assert!(error.contains("b.json -> "), "{error}");
+    assert!(error.ends_with("a.json"), "{error}");
+}
+
+#[test]
+fn test_include_unknown_override() {
+    let directory = write_files(
+        "include_unknown_override",
+        &[
+            (
+                "mpu.json",
+                r#"{ "sensors": [{ "name": "MPU", "id": 2, "values": [] }] }"#,
+            ),
+            (
+                "rocket.json",
+                r#"{ "name": "test", "include": [{ "path": "mpu.json", "overrides": { "BMP": { "id": 3 } } }], "sensors": [] }"#,
+            ),
+        ],
+    );
+
+    let error = crate::load_config(directory.join("rocket.json")).unwrap_err();
+
+    assert!(error.starts_with("Could not override BMP: "), "{error}");
+}
+
+#[test]
+fn test_include_from_string() {
+    let config = r#"{ "name": "test", "include": [{ "path": "mpu.json" }], "sensors": [] }"#;
+
+    assert!(crate::load_config_str(config).is_err());
+}
+
+#[test]
+fn test_filter_from_str() {
+    assert_eq!(
+        "low_pass,cutoff=5,sample_rate=100".parse::<FilterConfig>(),
+        Ok(FilterConfig::LowPass {
+            cutoff: 5.0,
+            sample_rate: 100.0
+        })
+    );
+    assert_eq!(
+        "despike, window=5".parse::<FilterConfig>(),
+        Ok(FilterConfig::Despike {
+            window: 5,
+            threshold: 3.0
+        })
+    );
+
+    assert!("low_pass,cutoff=60,sample_rate=100"
+        .parse::<FilterConfig>()
+        .is_err());
+    assert!("median,window=0".parse::<FilterConfig>().is_err());
+    assert!("median,size=3".parse::<FilterConfig>().is_err());
+    assert!("bandpass".parse::<FilterConfig>().is_err());
+}
+
+#[test]
+fn test_load_filters() {
+    let value: ValueConfig = serde_json::from_value(json!({
+        "name": "pressure",
+        "data_type": "float_32",
+        "filters": [
+            {"type": "median", "window": 5},
+            {"type": "savitzky_golay", "window": 7, "order": 2, "derivative": 1}
+        ]
+    }))
+    .unwrap();
+
+    assert_eq!(value.filters.len(), 2);
+    assert_eq!(
+        value.filters[1],
+        FilterConfig::SavitzkyGolay {
+            window: 7,
+            order: 2,
+            derivative: 1,
+            sample_rate: 1.0
+        }
+    );
+}
+
+#[test]
+fn test_load_invalid_filters() {
+    let config = r#"{ "name": "test", "sensors": [{ "id": 0, "name": "BMP", "values": [
+        { "name": "pressure", "data_type": "float_32", "filters": [{ "type": "moving_average", "window": 0 }] }
+    ] }] }"#;
+
+    let error = crate::load_config_str(config).unwrap_err();
+
+    assert!(
+        error.starts_with("Invalid filter for BMP_pressure: "),
+        "{error}"
+    );
+}
@@ -1,39 +1,201 @@
 use std::error::Error;
+use std::fmt::Display;
 use std::io::Write;
 
-use std::fmt::Write as FmtWrite;
+use crate::configuration::{RocketConfig, ValueKind};
+use crate::data::{PacketError, TypedValue};
 
-use crate::configuration::RocketConfig;
-use crate::data::PacketError;
+use crate::result_table::{RowSource, SourceIterator, TableGenerator};
 
-use crate::result_table::{SourceIterator, TableGenerator};
+/// When fields of a CSV row are wrapped in quotes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuotePolicy {
+    /// Only quote fields that contain the delimiter, a quote or a line break.
+    ///
+    /// This is the default value.
+    #[default]
+    Minimal,
+    /// Quote every field, including empty cells.
+    Always,
+    /// Quote every field that is not a finite number, which in practice is
+    /// the header, NaN and infinite values and any custom empty cell text.
+    NonNumeric,
+    /// Never quote fields, even if that produces an ambiguous file.
+    Never,
+}
+
+/// How floating point values are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatFormat {
+    /// A fixed number of decimal places.
+    Fixed(usize),
+    /// The shortest representation that reads back as the same value.
+    Shortest,
+}
+
+impl Default for FloatFormat {
+    #[inline]
+    fn default() -> Self {
+        Self::Fixed(8)
+    }
+}
+
+/// The dialect used when writing CSV files.
+///
+/// The default options produce the same output as previous versions of this
+/// library: comma separated, minimal quoting, `\n` line endings, floats with 8
+/// decimal places and a header row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// The character placed between fields.
+    pub delimiter: char,
+    /// When fields are quoted.
+    pub quoting: QuotePolicy,
+    /// The text written after each row by [`CsvGenerator::write_csv`].
+    pub line_terminator: String,
+    /// How floating point values are written.
+    pub float_format: FloatFormat,
+    /// The text written for a floating point NaN.
+    ///
+    /// When this is `None` the float format is used, which writes `NaN`.
+    pub nan_value: Option<String>,
+    /// The text written for a cell that has no value in a row.
+    pub empty_value: String,
+    /// Whether the first row is the header of column names.
+    pub header: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            quoting: QuotePolicy::default(),
+            line_terminator: "\n".to_string(),
+            float_format: FloatFormat::default(),
+            nan_value: None,
+            empty_value: String::new(),
+            header: true,
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Format a single value according to the float format and NaN options.
+    pub fn format_value(&self, value: &TypedValue) -> String {
+        let raw = value.value();
+
+        // The safety is assumed by the constructor of the typed value.
+        let (float, is_nan) = unsafe {
+            match value.kind() {
+                ValueKind::Float32 => {
+                    (Some(self.format_float(raw.float_32)), raw.float_32.is_nan())
+                }
+                ValueKind::Float64 => {
+                    (Some(self.format_float(raw.float_64)), raw.float_64.is_nan())
+                }
+                _ => (None, false),
+            }
+        };
+
+        match (float, &self.nan_value) {
+            (Some(_), Some(nan)) if is_nan => nan.clone(),
+            (Some(float), _) => float,
+            (None, _) => value.to_string(),
+        }
+    }
+
+    fn format_float<F: Display>(&self, value: F) -> String {
+        match self.float_format {
+            FloatFormat::Fixed(precision) => format!("{value:.precision$}"),
+            FloatFormat::Shortest => format!("{value}"),
+        }
+    }
+
+    /// Quote a field if required by the quoting policy.
+    ///
+    /// `numeric` is whether the field is a formatted number, which is only
+    /// relevant to [`QuotePolicy::NonNumeric`].
+    pub fn quote(&self, field: &str, numeric: bool) -> String {
+        let needs_quotes = match self.quoting {
+            QuotePolicy::Minimal => {
+                field.contains(self.delimiter) || field.contains(['"', '\r', '\n'])
+            }
+            QuotePolicy::Always => true,
+            QuotePolicy::NonNumeric => !numeric,
+            QuotePolicy::Never => false,
+        };
+
+        if needs_quotes {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }
+
+    fn join(&self, fields: impl Iterator<Item = String>) -> String {
+        let mut result = String::new();
+
+        for (i, field) in fields.enumerate() {
+            if i > 0 {
+                result.push(self.delimiter);
+            }
+            result.push_str(&field);
+        }
+
+        result
+    }
+}
 
 /// Iterator that generates CSV rows from data provided.
 ///
-/// This uses the TableGenerator to generate the rows of a table and then
-/// converts them to CSV rows. This iterator will also return the table header
-/// as the first row. Each row will be a string containing the values separated
-/// by commas, or the header if it's the first row.
-pub struct CsvGenerator<I: SourceIterator> {
+/// This uses a [`RowSource`], by default the [`TableGenerator`], to generate
+/// the rows of a table and then converts them to CSV rows. This iterator will
+/// also return the table header as the first row unless disabled in the
+/// [`CsvOptions`]. Each row will be a string containing the values separated
+/// by the delimiter, without the line terminator.
+pub struct CsvGenerator<R: RowSource> {
     is_first: bool,
-    iter: TableGenerator<I>,
+    iter: R,
+    options: CsvOptions,
 }
 
-impl<I: SourceIterator> CsvGenerator<I> {
+impl<I: SourceIterator> CsvGenerator<TableGenerator<I>> {
     /// Create a new CSV generator given a Packet iterator and a rocket
     /// configuration.
     ///
     /// This constructs the table generator so it can be used during the rest
     /// of the lifetime of the CSV generator.
     pub fn new(iter: I, config: RocketConfig) -> Self {
+        Self::with_options(iter, config, CsvOptions::default())
+    }
+
+    /// Create a new CSV generator that writes with a custom dialect.
+    pub fn with_options(iter: I, config: RocketConfig, options: CsvOptions) -> Self {
+        Self::from_rows(TableGenerator::new(iter, config), options)
+    }
+}
+
+impl<R: RowSource> CsvGenerator<R> {
+    /// Create a new CSV generator from any source of rows, such as a table
+    /// generator with derived columns added or a
+    /// [`Resampler`](crate::result_table::resample::Resampler).
+    pub fn from_rows(rows: R, options: CsvOptions) -> Self {
         Self {
-            iter: TableGenerator::new(iter, config),
-            is_first: true,
+            iter: rows,
+            is_first: options.header,
+            options,
         }
     }
 
+    /// The dialect used to write rows.
+    pub fn options(&self) -> &CsvOptions {
+        &self.options
+    }
+
     /// Consume the iterator and write the CSV to the given writer.
     ///
+    /// Every row is followed by the configured line terminator.
+    ///
     /// # Params
     ///
     /// * `writer` - Writer to write the CSV to.
@@ -47,23 +209,30 @@ impl<I: SourceIterator> CsvGenerator<I> {
     /// The two cases for error are either an IO error if the writer fails to
     /// write or a packet error if the iterator fails to yield a packet.
     pub fn write_csv<W: Write>(self, writer: &mut W) -> Result<(), Box<dyn Error>> {
+        let line_terminator = self.options.line_terminator.clone();
+
         for row in self {
             let row = row?;
-            writeln!(writer, "{}", row)?;
+            write!(writer, "{row}{line_terminator}")?;
         }
 
         Ok(())
     }
 }
 
-impl<I: SourceIterator> Iterator for CsvGenerator<I> {
+impl<R: RowSource> Iterator for CsvGenerator<R> {
     type Item = Result<String, PacketError>;
 
     fn next(&mut self) -> Option<Self::Item> {
         // Create the header row if this is the first row.
         if self.is_first {
             self.is_first = false;
-            return Some(Ok(self.iter.column_names().join(",")));
+            let header = self
+                .iter
+                .column_names()
+                .into_iter()
+                .map(|name| self.options.quote(&name, false));
+            return Some(Ok(self.options.join(header)));
         }
 
         // Get the next row from the table generator.
@@ -77,17 +246,17 @@ impl<I: SourceIterator> Iterator for CsvGenerator<I> {
             return None;
         }
 
-        // TODO: This might be better as iter to string and join.
-        let mut result = String::new();
-
-        for value in row {
-            match value {
-                Some(value) => write!(result, "{},", value).unwrap(),
-                None => write!(result, ",").unwrap(),
+        let options = &self.options;
+        let fields = row.iter().map(|value| match value {
+            Some(value) => {
+                let field = options.format_value(value);
+                let numeric = value.to_f64().is_finite();
+                options.quote(&field, numeric)
             }
-        }
+            None => options.quote(&options.empty_value, options.empty_value.is_empty()),
+        });
 
-        Some(Ok(result[0..result.len() - 1].to_string()))
+        Some(Ok(options.join(fields)))
     }
 }
 
@@ -98,13 +267,65 @@ mod tests {
 
     use super::*;
 
+    fn test_config(sensor_name: &str) -> RocketConfig {
+        RocketConfig {
+            name: "test".to_string(),
+            endianess: crate::configuration::Endianess::default(),
+            nose_axis: crate::configuration::Axis::default(),
+            include: vec![],
+            display_name: None,
+            description: None,
+            kalman: None,
+            sensors: vec![SensorConfig {
+                id: 0,
+                name: sensor_name.to_string(),
+                values: vec![
+                    ValueConfig {
+                        name: "value".to_string(),
+                        data_type: ValueKind::Float32,
+                        unit: None,
+                        role: None,
+                        filters: vec![],
+                    },
+                    ValueConfig {
+                        name: "value2".to_string(),
+                        data_type: ValueKind::Int32,
+                        unit: None,
+                        role: None,
+                        filters: vec![],
+                    },
+                ],
+            }],
+        }
+    }
+
+    fn test_packets() -> Vec<Packet> {
+        vec![
+            Packet {
+                id: 0,
+                values: vec![Value { float_32: 1.0 }, Value { int_32: 1 }],
+            },
+            Packet {
+                id: 0,
+                values: vec![Value { float_32: 2.0 }, Value { int_32: 2 }],
+            },
+            Packet {
+                id: 0,
+                values: vec![Value { float_32: 3.0 }, Value { int_32: 3 }],
+            },
+        ]
+    }
+
     #[test]
     fn test_csv_generator() {
         let config = RocketConfig {
             name: "test".to_string(),
             endianess: crate::configuration::Endianess::default(),
+            nose_axis: crate::configuration::Axis::default(),
+            include: vec![],
             display_name: None,
             description: None,
+            kalman: None,
             sensors: vec![SensorConfig {
                 id: 0,
                 name: "test".to_string(),
@@ -112,10 +333,16 @@ mod tests {
                     ValueConfig {
                         name: "value".to_string(),
                         data_type: ValueKind::Float32,
+                        unit: None,
+                        role: None,
+                        filters: vec![],
                     },
                     ValueConfig {
                         name: "value2".to_string(),
                         data_type: ValueKind::Int32,
+                        unit: None,
+                        role: None,
+                        filters: vec![],
                     },
                 ],
             }],
@@ -144,4 +371,107 @@ mod tests {
         assert_eq!(csv.next().unwrap().unwrap(), "3.00000000,3");
         assert!(csv.next().is_none());
     }
+
+    #[test]
+    fn test_csv_minimal_quoting() {
+        let options = CsvOptions::default();
+
+        assert_eq!(options.quote("plain", false), "plain");
+        assert_eq!(options.quote("a,b", false), "\"a,b\"");
+        assert_eq!(options.quote("say \"hi\"", false), "\"say \"\"hi\"\"\"");
+        assert_eq!(options.quote("two\nlines", false), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn test_csv_header_with_delimiter_is_quoted() {
+        let mut csv = CsvGenerator::new(test_packets().into_iter().map(Ok), test_config("a,b"));
+
+        assert_eq!(csv.next().unwrap().unwrap(), "\"a,b_value\",\"a,b_value2\"");
+    }
+
+    #[test]
+    fn test_csv_custom_dialect() {
+        let options = CsvOptions {
+            delimiter: ';',
+            quoting: QuotePolicy::NonNumeric,
+            line_terminator: "\r\n".to_string(),
+            float_format: FloatFormat::Shortest,
+            header: false,
+            ..Default::default()
+        };
+        let csv = CsvGenerator::with_options(
+            test_packets().into_iter().map(Ok),
+            test_config("test"),
+            options,
+        );
+
+        let mut result = vec![];
+        csv.write_csv(&mut result).unwrap();
+
+        assert_eq!(String::from_utf8(result).unwrap(), "1;1\r\n2;2\r\n3;3\r\n");
+    }
+
+    #[test]
+    fn test_csv_non_numeric_quotes_non_finite() {
+        let packets = vec![
+            Packet {
+                id: 0,
+                values: vec![Value { float_32: f32::NAN }, Value { int_32: 1 }],
+            },
+            Packet {
+                id: 0,
+                values: vec![
+                    Value {
+                        float_32: f32::INFINITY,
+                    },
+                    Value { int_32: 2 },
+                ],
+            },
+        ];
+        let options = CsvOptions {
+            quoting: QuotePolicy::NonNumeric,
+            float_format: FloatFormat::Shortest,
+            header: false,
+            ..Default::default()
+        };
+
+        let mut csv =
+            CsvGenerator::with_options(packets.into_iter().map(Ok), test_config("test"), options);
+
+        assert_eq!(csv.next().unwrap().unwrap(), "\"NaN\",1");
+        assert_eq!(csv.next().unwrap().unwrap(), "\"inf\",2");
+        assert!(csv.next().is_none());
+    }
+
+    #[test]
+    fn test_csv_nan_and_empty_values() {
+        let mut config = test_config("test");
+        config.sensors.push(SensorConfig {
+            id: 1,
+            name: "other".to_string(),
+            values: vec![ValueConfig {
+                name: "value".to_string(),
+                data_type: ValueKind::Float64,
+                unit: None,
+                role: None,
+                filters: vec![],
+            }],
+        });
+        let packets = vec![Packet {
+            id: 0,
+            values: vec![Value { float_32: f32::NAN }, Value { int_32: 1 }],
+        }];
+        let options = CsvOptions {
+            float_format: FloatFormat::Fixed(2),
+            nan_value: Some("nan".to_string()),
+            empty_value: "NA".to_string(),
+            header: false,
+            ..Default::default()
+        };
+
+        let mut csv = CsvGenerator::with_options(packets.into_iter().map(Ok), config, options);
+
+        assert_eq!(csv.next().unwrap().unwrap(), "nan,1,NA");
+        assert!(csv.next().is_none());
+    }
 }
@@ -4,7 +4,8 @@ use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::Path;
 
-use crate::configuration::{RocketConfig, ValueConfig, ValueKind};
+use crate::configuration::{Endianess, RocketConfig, ValueConfig, ValueKind};
+use crate::header::{AfterHeader, LogHeader};
 
 #[cfg(test)]
 mod tests;
@@ -47,6 +48,42 @@ impl TypedValue {
             value_kind: *value_kind,
         }
     }
+
+    /// The kind of the wrapped value.
+    #[inline]
+    pub fn kind(&self) -> ValueKind {
+        self.value_kind
+    }
+
+    /// Convert the value to a 64 bit float.
+    ///
+    /// This is lossy for 64 bit integers with a large magnitude.
+    pub fn to_f64(&self) -> f64 {
+        // The safety is assumed by the constructor.
+        unsafe {
+            match self.value_kind {
+                ValueKind::Int8 => self.value.int_8 as f64,
+                ValueKind::Int16 => self.value.int_16 as f64,
+                ValueKind::Int32 => self.value.int_32 as f64,
+                ValueKind::Int64 => self.value.int_64 as f64,
+                ValueKind::UInt8 => self.value.uint_8 as f64,
+                ValueKind::UInt16 => self.value.uint_16 as f64,
+                ValueKind::UInt32 => self.value.uint_32 as f64,
+                ValueKind::UInt64 => self.value.uint_64 as f64,
+                ValueKind::Float32 => self.value.float_32 as f64,
+                ValueKind::Float64 => self.value.float_64,
+            }
+        }
+    }
+
+    /// The raw wrapped value.
+    ///
+    /// The value is only safe to read as the field matching
+    /// [`TypedValue::kind`].
+    #[inline]
+    pub fn value(&self) -> Value {
+        self.value
+    }
 }
 
 impl Display for TypedValue {
@@ -156,10 +193,7 @@ impl TypedValue {
 
 impl PartialEq for TypedValue {
     fn eq(&self, other: &Self) -> bool {
-        match self.partial_cmp(other) {
-            Some(std::cmp::Ordering::Equal) => true,
-            _ => false,
-        }
+        matches!(self.partial_cmp(other), Some(std::cmp::Ordering::Equal))
     }
 }
 
@@ -226,12 +260,59 @@ impl Value {
             ValueKind::Float64 => format!("{:.8}", self.float_64),
         }
     }
+
+    /// Converts the value to the bytes it is encoded as in a data log.
+    ///
+    /// This is the inverse of what the [`PacketParser`] reads for a single
+    /// value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flight_data_reader::data::Value;
+    /// use flight_data_reader::configuration::{Endianess, ValueKind};
+    ///
+    /// let value = Value { int_16: 258 };
+    ///
+    /// assert_eq!(unsafe { value.to_bytes(&ValueKind::Int16, Endianess::Big) }, vec![1, 2]);
+    /// assert_eq!(unsafe { value.to_bytes(&ValueKind::Int16, Endianess::Little) }, vec![2, 1]);
+    /// ```
+    ///
+    /// # Safety
+    ///
+    /// This method is unsafe for the same reason as [`Value::to_string`], the
+    /// value kind must match the field of the union that was set.
+    pub unsafe fn to_bytes(&self, value_kind: &ValueKind, endianess: Endianess) -> Vec<u8> {
+        macro_rules! bytes {
+            ($field:ident) => {
+                if endianess.is_big() {
+                    self.$field.to_be_bytes().to_vec()
+                } else {
+                    self.$field.to_le_bytes().to_vec()
+                }
+            };
+        }
+
+        match value_kind {
+            ValueKind::Int8 => bytes!(int_8),
+            ValueKind::Int16 => bytes!(int_16),
+            ValueKind::Int32 => bytes!(int_32),
+            ValueKind::Int64 => bytes!(int_64),
+            ValueKind::UInt8 => bytes!(uint_8),
+            ValueKind::UInt16 => bytes!(uint_16),
+            ValueKind::UInt32 => bytes!(uint_32),
+            ValueKind::UInt64 => bytes!(uint_64),
+            ValueKind::Float32 => bytes!(float_32),
+            ValueKind::Float64 => bytes!(float_64),
+        }
+    }
 }
 
 /// A single reading of a sensor.
 ///
 /// Each packet consists of a single byte indicating the sensor ID that is in
 /// the config file, followed by the values for that sensor.
+#[derive(Clone)]
 pub struct Packet {
     /// The ID of the sensor that is read.
     pub id: u8,
@@ -247,12 +328,22 @@ pub struct Packet {
 /// This struct takes a reader that can be used as a byte stream of data, and
 /// presents an iterator interface that can be used to read packets
 /// individually.
+///
+/// If the stream starts with a [`LogHeader`], it is read and skipped before
+/// the first packet.
 pub struct PacketParser<R: Read> {
-    /// The input reader.
-    reader: BufReader<R>,
+    /// The input reader, after the header.
+    reader: BufReader<AfterHeader<R>>,
     /// The configuration used to know how many values and what kind of values
     /// to read.
     config: RocketConfig,
+    /// The header at the start of the stream, if it has one.
+    header: Option<LogHeader>,
+    /// Why the header at the start of the stream couldn't be read, which is
+    /// returned instead of the first packet.
+    header_error: Option<String>,
+    /// Whether the stream can't be read any further.
+    stopped: bool,
 }
 
 macro_rules! from_le_or_be_bytes {
@@ -267,13 +358,85 @@ macro_rules! from_le_or_be_bytes {
 
 impl<R: Read> PacketParser<R> {
     /// Create a new packet parser from a reader and a config.
+    ///
+    /// A header at the start of the stream is skipped, but the config is not
+    /// checked against it. Use [`PacketParser::open`] to check the config.
+    ///
+    /// If the header is damaged, the iterator returns the error instead of
+    /// the first packet and then ends, since the packets can't be found.
     pub fn new(reader: R, config: RocketConfig) -> Self {
+        let (reader, header) = LogHeader::read(reader);
+        let (header, header_error) = match header {
+            Ok(header) => (header, None),
+            Err(e) => (None, Some(e)),
+        };
+
         Self {
             reader: BufReader::new(reader),
             config,
+            header,
+            header_error,
+            stopped: false,
         }
     }
 
+    /// Create a new packet parser from a reader, reading the header of the
+    /// stream.
+    ///
+    /// If no config is given, the config embedded in the header is used. A
+    /// given config is used even if it doesn't match the one the log was
+    /// written with, since it may only have been renamed or patched. Check
+    /// [`PacketParser::config_matches`] to warn about or reject a mismatch.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the header is damaged, or if no config is given
+    /// and the stream doesn't have an embedded config.
+    pub fn open(reader: R, config: Option<RocketConfig>) -> Result<Self, String> {
+        let (reader, header) = LogHeader::read(reader);
+        let header = header?;
+
+        let config = match (config, &header) {
+            (Some(config), _) => config,
+            (
+                None,
+                Some(LogHeader {
+                    config: Some(config),
+                    ..
+                }),
+            ) => config.clone(),
+            (None, _) => return Err("The log does not contain a config".to_string()),
+        };
+
+        Ok(Self {
+            reader: BufReader::new(reader),
+            config,
+            header,
+            header_error: None,
+            stopped: false,
+        })
+    }
+
+    /// The config used to read the packets.
+    pub fn config(&self) -> &RocketConfig {
+        &self.config
+    }
+
+    /// The header at the start of the stream, if it has one.
+    pub fn header(&self) -> Option<&LogHeader> {
+        self.header.as_ref()
+    }
+
+    /// Whether the config has the same layout as the one the log was written
+    /// with, compared by [`RocketConfig::fingerprint`].
+    ///
+    /// Returns `None` if the stream doesn't have a header to compare with.
+    pub fn config_matches(&self) -> Option<bool> {
+        self.header
+            .as_ref()
+            .map(|header| header.matches(&self.config))
+    }
+
     /// Create a new packet parser from a path and a config.
     ///
     /// The file is opened and a packet parser is created from the file.
@@ -367,8 +530,13 @@ pub enum PacketError {
     /// config.
     ///
     /// This is likely only to happen if the packets are loaded with a different
-    /// config than the one used to later process the packets.
+    /// config than the one used to later process the packets. Comparing the
+    /// [`RocketConfig::fingerprint`] of both configs will detect this.
     InvalidValueCount { actual: usize, expected: usize },
+    /// A packet could not be decoded, for example from a damaged archive.
+    ///
+    /// The contained value describes the problem.
+    Malformed(String),
 }
 
 impl std::fmt::Display for PacketError {
@@ -380,6 +548,7 @@ impl std::fmt::Display for PacketError {
                 "Invalid value count: expected {}, got {}",
                 expected, actual
             ),
+            PacketError::Malformed(reason) => write!(f, "Malformed packet: {}", reason),
         }
     }
 }
@@ -390,6 +559,14 @@ impl<R: Read> Iterator for PacketParser<R> {
     type Item = Result<Packet, PacketError>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.stopped {
+            return None;
+        }
+        if let Some(error) = self.header_error.take() {
+            self.stopped = true;
+            return Some(Err(PacketError::Malformed(error)));
+        }
+
         let mut id = [0u8; 1];
 
         if self.reader.read_exact(&mut id).is_err() {
@@ -32,3 +32,50 @@ fn test_packet_parser() {
         assert_eq!(unsafe { value.float_32 }, 1.0);
     }
 }
+
+#[test]
+fn test_packet_parser_with_header() {
+    let config: RocketConfig = serde_json::from_str(raw_config).unwrap();
+    let mut bin = vec![];
+    LogHeader::new(&config, true).write(&mut bin).unwrap();
+    bin.extend_from_slice(&[0x02, 0x3f, 0x80, 0x00, 0x00, 0x3f, 0x80, 0x00, 0x00]);
+
+    let packet_parser = PacketParser::new(bin.as_slice(), config.clone());
+    assert!(packet_parser.header().is_some());
+    assert_eq!(packet_parser.count(), 1);
+
+    let mut packet_parser = PacketParser::open(bin.as_slice(), None).unwrap();
+    assert_eq!(packet_parser.config().name, config.name);
+    assert_eq!(packet_parser.config_matches(), Some(true));
+    let packet = packet_parser.next().unwrap().unwrap();
+    assert_eq!(packet.id, 2);
+    assert!(packet_parser.next().is_none());
+
+    let mut other = config.clone();
+    other.sensors.pop();
+    let packet_parser = PacketParser::open(bin.as_slice(), Some(other)).unwrap();
+    assert_eq!(packet_parser.config_matches(), Some(false));
+}
+
+#[test]
+fn test_packet_parser_open_without_config() {
+    let bin: Vec<u8> = vec![0x02, 0x3f, 0x80, 0x00, 0x00, 0x3f, 0x80, 0x00, 0x00];
+
+    assert!(PacketParser::open(bin.as_slice(), None).is_err());
+}
+
+#[test]
+fn test_packet_parser_damaged_header() {
+    let config: RocketConfig = serde_json::from_str(raw_config).unwrap();
+    let mut bin = vec![];
+    LogHeader::new(&config, true).write(&mut bin).unwrap();
+    bin.truncate(20);
+
+    let mut packet_parser = PacketParser::new(bin.as_slice(), config);
+    assert!(packet_parser.header().is_none());
+    assert!(matches!(
+        packet_parser.next(),
+        Some(Err(PacketError::Malformed(_)))
+    ));
+    assert!(packet_parser.next().is_none());
+}
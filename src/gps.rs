@@ -0,0 +1,519 @@
+use std::error::Error;
+use std::io::Write;
+
+use crate::configuration::{RocketConfig, ValueRole};
+use crate::data::{Packet, PacketError, TypedValue};
+use crate::result_table::SourceIterator;
+use crate::time::PacketClock;
+
+/// A single position in a GPS track.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackPoint {
+    /// The time of the fix in seconds, if the config has a time value.
+    pub time: Option<f64>,
+    /// The latitude in degrees.
+    pub latitude: f64,
+    /// The longitude in degrees.
+    pub longitude: f64,
+    /// The altitude above mean sea level in metres, if the config has an
+    /// altitude value and a finite altitude has been read.
+    pub altitude: Option<f64>,
+}
+
+/// Options for generating a GPS track.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackOptions {
+    /// The lowest fix quality that is included in the track.
+    ///
+    /// This is only used if the config has a value with the
+    /// [`ValueRole::FixQuality`] role. The default of 1 drops invalid NMEA
+    /// fixes.
+    pub min_fix_quality: f64,
+    /// The time of the start of the log, in nanoseconds since the Unix epoch.
+    ///
+    /// Track points only have a timestamp in the GPX output if this is set,
+    /// as packet times are normally relative to the flight computer starting
+    /// up.
+    pub start_time: Option<i64>,
+}
+
+impl Default for TrackOptions {
+    fn default() -> Self {
+        Self {
+            min_fix_quality: 1.0,
+            start_time: None,
+        }
+    }
+}
+
+/// The location of a value in a packet as the sensor ID and value index.
+type ValueLocation = (u8, usize);
+
+/// Iterator that generates the GPS track of a flight from data provided.
+///
+/// The latitude, longitude, altitude and fix quality are found using the
+/// roles of values in the rocket configuration. A track point is generated
+/// every time a packet containing the latitude is read, using the most recent
+/// value of the others.
+pub struct TrackGenerator<I: SourceIterator> {
+    iter: I,
+    config: RocketConfig,
+    clock: PacketClock,
+    options: TrackOptions,
+    latitude: ValueLocation,
+    longitude: ValueLocation,
+    /// The location of the altitude and the number of metres per unit.
+    altitude: Option<(ValueLocation, f64)>,
+    fix_quality: Option<ValueLocation>,
+    /// The most recent values read of the latitude, longitude, altitude and
+    /// fix quality.
+    current: [Option<f64>; 4],
+}
+
+impl<I: SourceIterator> TrackGenerator<I> {
+    /// Create a new track generator given a Packet iterator and a rocket
+    /// configuration.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the config doesn't have both a latitude and a
+    /// longitude value.
+    pub fn new(iter: I, config: RocketConfig) -> Result<Self, String> {
+        Self::with_options(iter, config, TrackOptions::default())
+    }
+
+    /// Create a new track generator with custom options.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the config doesn't have both a latitude and a
+    /// longitude value.
+    pub fn with_options(
+        iter: I,
+        config: RocketConfig,
+        options: TrackOptions,
+    ) -> Result<Self, String> {
+        let locate = |role| {
+            config
+                .find_role(role)
+                .map(|(sensor, index)| ((sensor.id, index), &sensor.values[index]))
+        };
+
+        let Some((latitude, _)) = locate(ValueRole::Latitude) else {
+            return Err("No value has the latitude role".to_string());
+        };
+        let Some((longitude, _)) = locate(ValueRole::Longitude) else {
+            return Err("No value has the longitude role".to_string());
+        };
+        let altitude = locate(ValueRole::Altitude)
+            .map(|(location, value)| (location, value.metres_per_unit().unwrap_or(1.0)));
+        let fix_quality = locate(ValueRole::FixQuality).map(|(location, _)| location);
+
+        Ok(Self {
+            iter,
+            clock: PacketClock::new(&config),
+            config,
+            options,
+            latitude,
+            longitude,
+            altitude,
+            fix_quality,
+            current: [None; 4],
+        })
+    }
+
+    /// Consume the iterator and write the track as a GPX file.
+    ///
+    /// # Errors
+    ///
+    /// The two cases for error are either an IO error if the writer fails to
+    /// write or a packet error if the iterator fails to yield a packet.
+    pub fn write_gpx<W: Write>(self, writer: &mut W) -> Result<(), Box<dyn Error>> {
+        let name = escape_xml(self.config.display_name());
+        let start_time = self.options.start_time;
+
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<gpx version="1.1" creator="flight_data_reader" xmlns="http://www.topografix.com/GPX/1/1">"#
+        )?;
+        writeln!(writer, "  <trk>")?;
+        writeln!(writer, "    <name>{name}</name>")?;
+        writeln!(writer, "    <trkseg>")?;
+
+        for point in self {
+            let point = point?;
+
+            write!(
+                writer,
+                r#"      <trkpt lat="{}" lon="{}">"#,
+                point.latitude, point.longitude
+            )?;
+            if let Some(altitude) = point.altitude {
+                write!(writer, "<ele>{altitude}</ele>")?;
+            }
+            if let (Some(time), Some(start_time)) = (point.time, start_time) {
+                let nanoseconds = (time * 1e9).round() as i64 + start_time;
+                write!(writer, "<time>{}</time>", iso_8601(nanoseconds))?;
+            }
+            writeln!(writer, "</trkpt>")?;
+        }
+
+        writeln!(writer, "    </trkseg>")?;
+        writeln!(writer, "  </trk>")?;
+        writeln!(writer, "</gpx>")?;
+
+        Ok(())
+    }
+
+    /// Consume the iterator and write the track as a KML file.
+    ///
+    /// If the config has an altitude value, the path is drawn at its absolute
+    /// altitude and extruded down to the ground, and points without an
+    /// altitude are left out. Otherwise it is drawn on the ground.
+    ///
+    /// # Errors
+    ///
+    /// The two cases for error are either an IO error if the writer fails to
+    /// write or a packet error if the iterator fails to yield a packet.
+    pub fn write_kml<W: Write>(self, writer: &mut W) -> Result<(), Box<dyn Error>> {
+        let name = escape_xml(self.config.display_name());
+        let has_altitude = self.altitude.is_some();
+
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(writer, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#)?;
+        writeln!(writer, "  <Document>")?;
+        writeln!(writer, "    <name>{name}</name>")?;
+        writeln!(writer, "    <Placemark>")?;
+        writeln!(writer, "      <name>Flight path</name>")?;
+        writeln!(writer, "      <LineString>")?;
+        if has_altitude {
+            writeln!(writer, "        <extrude>1</extrude>")?;
+            writeln!(writer, "        <altitudeMode>absolute</altitudeMode>")?;
+        } else {
+            writeln!(writer, "        <tessellate>1</tessellate>")?;
+            writeln!(writer, "        <altitudeMode>clampToGround</altitudeMode>")?;
+        }
+        writeln!(writer, "        <coordinates>")?;
+
+        for point in self {
+            let point = point?;
+
+            match point.altitude {
+                Some(altitude) if has_altitude => writeln!(
+                    writer,
+                    "          {},{},{}",
+                    point.longitude, point.latitude, altitude
+                )?,
+                // An absolute path would take the point as at sea level.
+                None if has_altitude => {}
+                _ => writeln!(writer, "          {},{}", point.longitude, point.latitude)?,
+            }
+        }
+
+        writeln!(writer, "        </coordinates>")?;
+        writeln!(writer, "      </LineString>")?;
+        writeln!(writer, "    </Placemark>")?;
+        writeln!(writer, "  </Document>")?;
+        writeln!(writer, "</kml>")?;
+
+        Ok(())
+    }
+
+    /// Update the most recent values with any GPS values in the packet.
+    fn update(&mut self, packet: &Packet) -> Result<(), PacketError> {
+        let Some(sensor) = self.config.get_sensor_by_id(packet.id) else {
+            return Err(PacketError::InvalidId(packet.id));
+        };
+
+        if packet.values.len() != sensor.values.len() {
+            return Err(PacketError::InvalidValueCount {
+                expected: sensor.values.len(),
+                actual: packet.values.len(),
+            });
+        }
+
+        let locations = [
+            Some((self.latitude, 1.0)),
+            Some((self.longitude, 1.0)),
+            self.altitude,
+            self.fix_quality.map(|location| (location, 1.0)),
+        ];
+
+        for (current, location) in self.current.iter_mut().zip(locations) {
+            let Some(((id, index), scale)) = location else {
+                continue;
+            };
+            if id != packet.id {
+                continue;
+            }
+
+            let spec = &sensor.values[index];
+            let value = unsafe { TypedValue::new(packet.values[index], &spec.data_type) };
+            *current = Some(value.to_f64() * scale);
+        }
+
+        Ok(())
+    }
+}
+
+impl<I: SourceIterator> Iterator for TrackGenerator<I> {
+    type Item = Result<TrackPoint, PacketError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let packet = match self.iter.next()? {
+                Ok(packet) => packet,
+                Err(e) => return Some(Err(e)),
+            };
+
+            if let Err(e) = self.update(&packet) {
+                return Some(Err(e));
+            }
+            let time = self.clock.update(&packet);
+
+            if packet.id != self.latitude.0 {
+                continue;
+            }
+
+            let [Some(latitude), Some(longitude), altitude, fix_quality] = self.current else {
+                continue;
+            };
+
+            if !latitude.is_finite() || !longitude.is_finite() {
+                continue;
+            }
+
+            if self.fix_quality.is_some()
+                && !fix_quality.is_some_and(|quality| quality >= self.options.min_fix_quality)
+            {
+                continue;
+            }
+
+            return Some(Ok(TrackPoint {
+                time,
+                latitude,
+                longitude,
+                altitude: altitude.filter(|altitude| altitude.is_finite()),
+            }));
+        }
+    }
+}
+
+/// Escape text for use in XML.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Format a time in nanoseconds since the Unix epoch as an ISO 8601 UTC time
+/// with millisecond precision.
+fn iso_8601(nanoseconds: i64) -> String {
+    let milliseconds = nanoseconds.div_euclid(1_000_000);
+    let seconds = milliseconds.div_euclid(1000);
+    let days = seconds.div_euclid(86_400);
+    let second_of_day = seconds.rem_euclid(86_400);
+
+    // Convert days since the epoch to a civil date, from Howard Hinnant's
+    // `civil_from_days` algorithm.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        second_of_day / 3600,
+        second_of_day % 3600 / 60,
+        second_of_day % 60,
+        milliseconds.rem_euclid(1000)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::{Axis, Endianess, SensorConfig, ValueConfig, ValueKind};
+    use crate::data::Value;
+
+    use super::*;
+
+    fn value(name: &str, data_type: ValueKind, role: ValueRole) -> ValueConfig {
+        ValueConfig {
+            name: name.to_string(),
+            data_type,
+            unit: None,
+            role: Some(role),
+            filters: vec![],
+        }
+    }
+
+    fn test_config() -> RocketConfig {
+        RocketConfig {
+            name: "test".to_string(),
+            endianess: Endianess::default(),
+            nose_axis: Axis::default(),
+            include: vec![],
+            display_name: Some("Test & Rocket".to_string()),
+            description: None,
+            kalman: None,
+            sensors: vec![
+                SensorConfig {
+                    id: 0,
+                    name: "clock".to_string(),
+                    values: vec![value("time", ValueKind::UInt32, ValueRole::Time)],
+                },
+                SensorConfig {
+                    id: 1,
+                    name: "GPS".to_string(),
+                    values: vec![
+                        value("lat", ValueKind::Float64, ValueRole::Latitude),
+                        value("lon", ValueKind::Float64, ValueRole::Longitude),
+                        value("alt", ValueKind::Float32, ValueRole::Altitude),
+                        value("fix", ValueKind::UInt8, ValueRole::FixQuality),
+                    ],
+                },
+            ],
+        }
+    }
+
+    fn fix(latitude: f64, longitude: f64, altitude: f32, fix_quality: u8) -> Packet {
+        Packet {
+            id: 1,
+            values: vec![
+                Value { float_64: latitude },
+                Value {
+                    float_64: longitude,
+                },
+                Value { float_32: altitude },
+                Value {
+                    uint_8: fix_quality,
+                },
+            ],
+        }
+    }
+
+    fn test_packets() -> Vec<Packet> {
+        vec![
+            fix(0.0, 0.0, 0.0, 0),
+            Packet {
+                id: 0,
+                values: vec![Value { uint_32: 1000 }],
+            },
+            fix(48.5, -123.25, 100.0, 1),
+            fix(48.5, -123.5, 1500.0, 2),
+        ]
+    }
+
+    #[test]
+    fn test_track_points_are_gated() {
+        let points = TrackGenerator::new(test_packets().into_iter().map(Ok), test_config())
+            .unwrap()
+            .collect::<Result<Vec<TrackPoint>, PacketError>>()
+            .unwrap();
+
+        assert_eq!(
+            points,
+            vec![
+                TrackPoint {
+                    time: Some(1.0),
+                    latitude: 48.5,
+                    longitude: -123.25,
+                    altitude: Some(100.0),
+                },
+                TrackPoint {
+                    time: Some(1.0),
+                    latitude: 48.5,
+                    longitude: -123.5,
+                    altitude: Some(1500.0),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_track_requires_roles() {
+        let mut config = test_config();
+        config.sensors[1].values[0].role = None;
+
+        let result = TrackGenerator::new(test_packets().into_iter().map(Ok), config);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_write_gpx() {
+        let options = TrackOptions {
+            min_fix_quality: 2.0,
+            start_time: Some(1_690_000_000_000_000_000),
+        };
+        let generator = TrackGenerator::with_options(
+            test_packets().into_iter().map(Ok),
+            test_config(),
+            options,
+        )
+        .unwrap();
+
+        let mut result = vec![];
+        generator.write_gpx(&mut result).unwrap();
+        let result = String::from_utf8(result).unwrap();
+
+        assert!(result.contains("<name>Test &amp; Rocket</name>"));
+        assert!(result.contains(
+            r#"<trkpt lat="48.5" lon="-123.5"><ele>1500</ele><time>2023-07-22T04:26:41.000Z</time></trkpt>"#
+        ));
+        assert!(!result.contains("-123.25"));
+    }
+
+    #[test]
+    fn test_write_kml() {
+        let generator =
+            TrackGenerator::new(test_packets().into_iter().map(Ok), test_config()).unwrap();
+
+        let mut result = vec![];
+        generator.write_kml(&mut result).unwrap();
+        let result = String::from_utf8(result).unwrap();
+
+        assert!(result.contains("<extrude>1</extrude>"));
+        assert!(result.contains("<altitudeMode>absolute</altitudeMode>"));
+        assert!(result.contains("          -123.25,48.5,100\n          -123.5,48.5,1500\n"));
+    }
+
+    #[test]
+    fn test_non_finite_altitude() {
+        let mut packets = test_packets();
+        packets.insert(2, fix(48.0, -123.0, f32::NAN, 1));
+
+        let generator = TrackGenerator::new(packets.into_iter().map(Ok), test_config()).unwrap();
+        let mut gpx = vec![];
+        generator.write_gpx(&mut gpx).unwrap();
+        let gpx = String::from_utf8(gpx).unwrap();
+        assert!(gpx.contains(r#"<trkpt lat="48" lon="-123"></trkpt>"#));
+
+        let mut packets = test_packets();
+        packets.insert(2, fix(48.0, -123.0, f32::INFINITY, 1));
+
+        let generator = TrackGenerator::new(packets.into_iter().map(Ok), test_config()).unwrap();
+        let mut kml = vec![];
+        generator.write_kml(&mut kml).unwrap();
+        let kml = String::from_utf8(kml).unwrap();
+        assert!(kml.contains("<coordinates>\n          -123.25,48.5,100\n"));
+    }
+
+    #[test]
+    fn test_iso_8601() {
+        assert_eq!(iso_8601(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(
+            iso_8601(951_782_400_500_000_000),
+            "2000-02-29T00:00:00.500Z"
+        );
+        assert_eq!(iso_8601(-1_000_000), "1969-12-31T23:59:59.999Z");
+    }
+}
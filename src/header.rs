@@ -0,0 +1,244 @@
+use std::io::{Chain, Cursor, Read, Write};
+
+use crate::configuration::RocketConfig;
+
+/// The bytes every log file with a header starts with.
+pub const LOG_MAGIC: [u8; 4] = *b"FDRL";
+
+/// The version of the header format that is written.
+pub const LOG_HEADER_VERSION: u8 = 1;
+
+/// The flag set when the header contains the config.
+const FLAG_EMBEDDED_CONFIG: u8 = 0b0000_0001;
+
+/// A reader continuing after the [`LogHeader`] of a stream, see
+/// [`LogHeader::read`].
+pub type AfterHeader<R> = Chain<Cursor<Vec<u8>>, R>;
+
+/// An optional header at the start of a log file that describes the data.
+///
+/// The header is always little endian, regardless of the config, and has the
+/// following layout:
+///
+/// | Bytes | Content                                      |
+/// |-------|----------------------------------------------|
+/// | 4     | [`LOG_MAGIC`]                                |
+/// | 1     | The header version                           |
+/// | 1     | Flags, bit 0 is set if the config is present |
+/// | 8     | [`RocketConfig::fingerprint`] of the config  |
+/// | 4     | Length of the config (only if present)       |
+/// | n     | The config as UTF-8 JSON (only if present)   |
+///
+/// The packets follow directly after the header. Files without a header are
+/// still read as before, a file is only considered to have a header if it
+/// starts with the magic bytes.
+#[derive(Debug, Clone)]
+pub struct LogHeader {
+    /// The version of the header format.
+    pub version: u8,
+    /// The fingerprint of the config used to write the log.
+    pub fingerprint: u64,
+    /// The config used to write the log, if it was embedded.
+    pub config: Option<RocketConfig>,
+}
+
+impl LogHeader {
+    /// Create a header for logs written with the given config.
+    ///
+    /// # Params
+    ///
+    /// * `config` - The config the log is written with.
+    /// * `embed_config` - Whether the full config is included in the header.
+    pub fn new(config: &RocketConfig, embed_config: bool) -> Self {
+        Self {
+            version: LOG_HEADER_VERSION,
+            fingerprint: config.fingerprint(),
+            config: embed_config.then(|| config.clone()),
+        }
+    }
+
+    /// Whether the given config has the same layout as the one the log was
+    /// written with.
+    pub fn matches(&self, config: &RocketConfig) -> bool {
+        self.fingerprint == config.fingerprint()
+    }
+
+    /// Write the header to the given writer.
+    pub fn write<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&LOG_MAGIC)?;
+        writer.write_all(&[self.version])?;
+
+        match &self.config {
+            Some(config) => {
+                let config = serde_json::to_vec(config)?;
+                let length = u32::try_from(config.len())
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+                writer.write_all(&[FLAG_EMBEDDED_CONFIG])?;
+                writer.write_all(&self.fingerprint.to_le_bytes())?;
+                writer.write_all(&length.to_le_bytes())?;
+                writer.write_all(&config)?;
+            }
+            None => {
+                writer.write_all(&[0])?;
+                writer.write_all(&self.fingerprint.to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read a header from the start of a reader, if it has one.
+    ///
+    /// The first bytes of the reader are read to look for [`LOG_MAGIC`]. If
+    /// they don't match, they are given back by the returned reader, so it
+    /// always continues at the first packet.
+    ///
+    /// # Returns
+    ///
+    /// The reader continuing after the header, and the header, `None` if the
+    /// reader doesn't start with a header, or an error if the header is
+    /// damaged or of an unknown version.
+    pub fn read<R: Read>(mut reader: R) -> (AfterHeader<R>, Result<Option<Self>, String>) {
+        let mut start = Vec::with_capacity(LOG_MAGIC.len());
+        if let Err(e) = (&mut reader)
+            .take(LOG_MAGIC.len() as u64)
+            .read_to_end(&mut start)
+        {
+            return (Cursor::new(start).chain(reader), Err(e.to_string()));
+        }
+
+        if start != LOG_MAGIC {
+            return (Cursor::new(start).chain(reader), Ok(None));
+        }
+
+        let header = Self::read_after_magic(&mut reader);
+        (Cursor::new(vec![]).chain(reader), header.map(Some))
+    }
+
+    /// Read the rest of a header after the magic bytes.
+    fn read_after_magic<R: Read>(reader: &mut R) -> Result<Self, String> {
+        let mut fixed = [0u8; 10];
+        reader
+            .read_exact(&mut fixed)
+            .map_err(|e| format!("Invalid log header: {e}"))?;
+
+        let version = fixed[0];
+        if version > LOG_HEADER_VERSION {
+            return Err(format!("Unsupported log header version: {version}"));
+        }
+
+        let flags = fixed[1];
+        let fingerprint = u64::from_le_bytes(fixed[2..].try_into().unwrap());
+
+        let config = if flags & FLAG_EMBEDDED_CONFIG != 0 {
+            let mut length = [0u8; 4];
+            reader
+                .read_exact(&mut length)
+                .map_err(|e| format!("Invalid log header: {e}"))?;
+            let length = u32::from_le_bytes(length) as usize;
+
+            // The length is not trusted to allocate the config, so a damaged
+            // length can't use more memory than the file holds.
+            let mut config = vec![];
+            reader
+                .take(length as u64)
+                .read_to_end(&mut config)
+                .map_err(|e| format!("Invalid log header: {e}"))?;
+            if config.len() != length {
+                return Err(format!(
+                    "Invalid log header: the config is {length} bytes but the log ends after {}",
+                    config.len()
+                ));
+            }
+
+            let config = serde_json::from_slice(&config)
+                .map_err(|e| format!("Invalid config in log header: {e}"))?;
+            Some(config)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            version,
+            fingerprint,
+            config,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A reader that returns at most one byte from every read.
+    struct OneByteReader<'a>(&'a [u8]);
+
+    impl Read for OneByteReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let length = buf.len().min(self.0.len()).min(1);
+            buf[..length].copy_from_slice(&self.0[..length]);
+            self.0 = &self.0[length..];
+            Ok(length)
+        }
+    }
+
+    const RAW_CONFIG: &str = include_str!("../example_config.json");
+
+    #[test]
+    fn test_header_round_trip() {
+        let config: RocketConfig = serde_json::from_str(RAW_CONFIG).unwrap();
+
+        for embed_config in [false, true] {
+            let mut bytes = vec![];
+            LogHeader::new(&config, embed_config)
+                .write(&mut bytes)
+                .unwrap();
+            bytes.push(0x01);
+
+            let (mut reader, header) = LogHeader::read(OneByteReader(&bytes));
+            let header = header.unwrap().unwrap();
+
+            assert_eq!(header.version, LOG_HEADER_VERSION);
+            assert!(header.matches(&config));
+            assert_eq!(header.config.is_some(), embed_config);
+
+            // The packets after the header are left in the reader.
+            let mut rest = vec![];
+            reader.read_to_end(&mut rest).unwrap();
+            assert_eq!(rest, vec![0x01]);
+        }
+    }
+
+    #[test]
+    fn test_read_without_header() {
+        let bytes = [0x01, 0x3f, 0x80, 0x00, 0x00];
+        let (mut reader, header) = LogHeader::read(bytes.as_slice());
+
+        assert!(header.unwrap().is_none());
+        let mut rest = vec![];
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, bytes);
+    }
+
+    #[test]
+    fn test_read_damaged_config_length() {
+        let config: RocketConfig = serde_json::from_str(RAW_CONFIG).unwrap();
+        let mut bytes = vec![];
+        LogHeader::new(&config, true).write(&mut bytes).unwrap();
+        bytes[14..18].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let (_, header) = LogHeader::read(bytes.as_slice());
+
+        assert!(header.unwrap_err().contains("log ends after"));
+    }
+
+    #[test]
+    fn test_header_mismatch() {
+        let config: RocketConfig = serde_json::from_str(RAW_CONFIG).unwrap();
+        let mut other = config.clone();
+        other.sensors.pop();
+
+        assert!(!LogHeader::new(&config, false).matches(&other));
+    }
+}
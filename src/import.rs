@@ -0,0 +1,480 @@
+use std::collections::HashMap;
+
+use crate::configuration::{Axis, Endianess, RocketConfig, SensorConfig, ValueConfig, ValueKind};
+
+/// Create a rocket config from a C header that defines the packets.
+///
+/// Only a restricted form of C is understood, which is what firmware usually
+/// uses to define packets:
+///
+/// * Every struct is a sensor, named after the struct without a `_packet_t`,
+///   `_packet` or `_t` suffix. Structs must only have fields with a fixed width
+///   integer type from `stdint.h`, `float` or `double`. Array fields are split
+///   into one value per element, named `<field>_<index>`.
+/// * The ID of each sensor comes from a `#define` named `<NAME>_ID`,
+///   `SENSOR_<NAME>_ID` or `ID_<NAME>`, where `<NAME>` is the name of the
+///   sensor. The case of the names doesn't matter. Lines continued with a
+///   backslash are joined first, as the preprocessor does.
+///
+/// Other declarations, such as functions and variables, are ignored. Headers
+/// written by [`crate::codegen::write_c_header`] can be read again.
+///
+/// # Params
+///
+/// * `source` - The content of the header.
+/// * `name` - The name of the rocket.
+/// * `endianess` - The endianess the firmware writes values in, which can't be
+///   known from the header.
+///
+/// # Errors
+///
+/// An error is returned if the header can't be parsed, a field has a type that
+/// isn't allowed, a struct has no ID, or the config isn't valid, for example
+/// because two sensors have the same ID.
+pub fn config_from_c_header(
+    source: &str,
+    name: &str,
+    endianess: Endianess,
+) -> Result<RocketConfig, String> {
+    let mut defines: HashMap<String, String> = HashMap::new();
+    let mut code = String::with_capacity(source.len());
+
+    // Continued lines are joined before anything else, so a continued define
+    // isn't read as code.
+    let source = source.replace("\\\r\n", "").replace("\\\n", "");
+
+    for line in strip_comments(&source).lines() {
+        let line = line.trim();
+        if let Some(directive) = line.strip_prefix('#') {
+            let mut parts = directive.split_whitespace();
+            if parts.next() == Some("define") {
+                if let (Some(key), Some(value)) = (parts.next(), parts.next()) {
+                    defines.insert(key.to_uppercase(), value.to_string());
+                }
+            }
+        } else {
+            code.push_str(line);
+            code.push('\n');
+        }
+    }
+
+    let mut sensors = vec![];
+    let mut parser = Parser::new(&code);
+
+    while let Some(token) = parser.peek() {
+        if token == "struct" || (token == "typedef" && parser.peek_nth(1) == Some("struct")) {
+            let Some((struct_name, values)) = parser.parse_struct()? else {
+                continue;
+            };
+            let name = sensor_name(&struct_name);
+            let Some(id) = find_id(&defines, &name)? else {
+                return Err(format!("No ID define for struct {struct_name}"));
+            };
+            sensors.push(SensorConfig { name, id, values });
+        } else {
+            parser.skip_declaration();
+        }
+    }
+
+    let config = RocketConfig {
+        name: name.to_string(),
+        sensors,
+        endianess,
+        nose_axis: Axis::default(),
+        include: vec![],
+        display_name: None,
+        description: None,
+        kalman: None,
+    };
+    config.validate()?;
+
+    Ok(config)
+}
+
+/// Remove `/* */` and `//` comments from C source, keeping the line breaks.
+fn strip_comments(source: &str) -> String {
+    let mut result = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        result.push('\n');
+                    }
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                result.push(' ');
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        result.push('\n');
+                        break;
+                    }
+                }
+            }
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+/// A parser over the tokens of C code without preprocessor lines.
+struct Parser {
+    tokens: Vec<String>,
+    position: usize,
+}
+
+impl Parser {
+    fn new(code: &str) -> Self {
+        let mut tokens = vec![];
+        let mut current = String::new();
+
+        for c in code.chars() {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                current.push(c);
+                continue;
+            }
+
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+            if !c.is_whitespace() {
+                tokens.push(c.to_string());
+            }
+        }
+        if !current.is_empty() {
+            tokens.push(current);
+        }
+
+        Self {
+            tokens,
+            position: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.peek_nth(0)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<&str> {
+        self.tokens.get(self.position + n).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("Expected `{expected}`, found `{token}`")),
+            None => Err(format!(
+                "Expected `{expected}`, found the end of the header"
+            )),
+        }
+    }
+
+    /// Skip a declaration that isn't a struct, such as a variable or function.
+    fn skip_declaration(&mut self) {
+        while let Some(token) = self.next() {
+            match token.as_str() {
+                ";" => return,
+                "{" => {
+                    self.skip_block();
+                    if self.peek() == Some(";") {
+                        self.next();
+                    }
+                    return;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Skip to the end of a `{}` block, after the opening brace.
+    fn skip_block(&mut self) {
+        let mut depth = 1;
+        while let Some(token) = self.next() {
+            match token.as_str() {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                return;
+            }
+        }
+    }
+
+    /// Skip any `__attribute__((...))` specifiers.
+    fn skip_attributes(&mut self) {
+        while self.peek() == Some("__attribute__") {
+            self.next();
+            let mut depth = 0;
+            while let Some(token) = self.next() {
+                match token.as_str() {
+                    "(" => depth += 1,
+                    ")" => depth -= 1,
+                    _ => {}
+                }
+                if depth == 0 {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Parse a struct definition, with or without a typedef.
+    ///
+    /// # Returns
+    ///
+    /// The name of the struct and the values of its fields, or `None` if this
+    /// is a declaration that doesn't define the fields.
+    fn parse_struct(&mut self) -> Result<Option<(String, Vec<ValueConfig>)>, String> {
+        let is_typedef = self.peek() == Some("typedef");
+        if is_typedef {
+            self.next();
+        }
+        self.expect("struct")?;
+        self.skip_attributes();
+
+        let mut name = match self.peek() {
+            Some("{") => None,
+            _ => self.next(),
+        };
+
+        if self.peek() != Some("{") {
+            // A forward declaration, or a declaration of a struct variable.
+            self.skip_declaration();
+            return Ok(None);
+        }
+        self.expect("{")?;
+
+        let mut values = vec![];
+        while self.peek() != Some("}") {
+            values.extend(self.parse_field()?);
+        }
+        self.expect("}")?;
+        self.skip_attributes();
+
+        if is_typedef {
+            name = self.next();
+        }
+        self.expect(";")?;
+
+        let name = name.ok_or("A struct has no name")?;
+        Ok(Some((name, values)))
+    }
+
+    /// Parse a field of a struct, which may be an array of values.
+    fn parse_field(&mut self) -> Result<Vec<ValueConfig>, String> {
+        let mut type_name = self.next().ok_or("Unexpected end of a struct")?;
+        while matches!(type_name.as_str(), "const" | "volatile") {
+            type_name = self.next().ok_or("Unexpected end of a struct")?;
+        }
+
+        let Some(data_type) = value_kind(&type_name) else {
+            return Err(format!("Unsupported field type: {type_name}"));
+        };
+
+        let name = self.next().ok_or("Unexpected end of a struct")?;
+
+        let length = if self.peek() == Some("[") {
+            self.next();
+            let length = self.next().ok_or("Unexpected end of a struct")?;
+            self.expect("]")?;
+            Some(
+                parse_integer(&length)
+                    .ok_or(format!("Unsupported array length: {name}[{length}]"))?,
+            )
+        } else {
+            None
+        };
+
+        self.skip_attributes();
+        self.expect(";")?;
+
+        let value = |name: String| ValueConfig {
+            name,
+            data_type,
+            unit: None,
+            role: None,
+            filters: vec![],
+        };
+
+        Ok(match length {
+            Some(length) => (0..length).map(|i| value(format!("{name}_{i}"))).collect(),
+            None => vec![value(name)],
+        })
+    }
+}
+
+/// The kind of a value from its C type.
+fn value_kind(type_name: &str) -> Option<ValueKind> {
+    match type_name {
+        "int8_t" => Some(ValueKind::Int8),
+        "int16_t" => Some(ValueKind::Int16),
+        "int32_t" => Some(ValueKind::Int32),
+        "int64_t" => Some(ValueKind::Int64),
+        "uint8_t" => Some(ValueKind::UInt8),
+        "uint16_t" => Some(ValueKind::UInt16),
+        "uint32_t" => Some(ValueKind::UInt32),
+        "uint64_t" => Some(ValueKind::UInt64),
+        "float" => Some(ValueKind::Float32),
+        "double" => Some(ValueKind::Float64),
+        _ => None,
+    }
+}
+
+/// The name of the sensor for a struct, without the common suffixes.
+fn sensor_name(struct_name: &str) -> String {
+    ["_packet_t", "_packet", "_t"]
+        .iter()
+        .find_map(|suffix| struct_name.strip_suffix(suffix))
+        .unwrap_or(struct_name)
+        .to_string()
+}
+
+/// Find the ID of a sensor from the defines.
+fn find_id(defines: &HashMap<String, String>, sensor_name: &str) -> Result<Option<u8>, String> {
+    let name = sensor_name.to_uppercase();
+    let candidates = [
+        format!("{name}_ID"),
+        format!("SENSOR_{name}_ID"),
+        format!("ID_{name}"),
+    ];
+
+    let Some(value) = candidates.iter().find_map(|key| defines.get(key)) else {
+        return Ok(None);
+    };
+
+    parse_integer(value)
+        .and_then(|id| u8::try_from(id).ok())
+        .map(Some)
+        .ok_or(format!("Invalid ID for sensor {sensor_name}: {value}"))
+}
+
+/// Parse an integer literal, allowing hexadecimal, parentheses and suffixes.
+fn parse_integer(literal: &str) -> Option<usize> {
+    let literal = literal
+        .trim_matches(|c| c == '(' || c == ')')
+        .trim_end_matches(['u', 'U', 'l', 'L']);
+
+    match literal
+        .strip_prefix("0x")
+        .or_else(|| literal.strip_prefix("0X"))
+    {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => literal.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codegen::write_c_header;
+
+    use super::*;
+
+    const LEGACY_HEADER: &str = r#"
+        #include <stdint.h>
+
+        #define BMP_ID 0x02
+        #define SENSOR_IMU_ID (5u) // The IMU.
+
+        typedef struct imu_packet imu_packet_t;
+
+        /* Barometer, read at 50 Hz. */
+        typedef struct __attribute__((packed)) {
+            float pressure;
+            const float temperature; /* Celsius */
+        } bmp_t;
+
+        struct __attribute__((packed)) imu_packet {
+            uint32_t time;
+            int16_t accel[3];
+        };
+
+        void log_packet(const uint8_t *data, size_t length);
+    "#;
+
+    #[test]
+    fn test_import_legacy_header() {
+        let config = config_from_c_header(LEGACY_HEADER, "legacy", Endianess::Little).unwrap();
+
+        assert_eq!(config.name, "legacy");
+        assert!(!config.endianess.is_big());
+        assert_eq!(config.sensors.len(), 2);
+
+        let bmp = &config.sensors[0];
+        assert_eq!((bmp.name.as_str(), bmp.id), ("bmp", 2));
+        assert_eq!(bmp.values.len(), 2);
+        assert_eq!(bmp.values[1].name, "temperature");
+        assert_eq!(bmp.values[1].data_type, ValueKind::Float32);
+
+        let imu = &config.sensors[1];
+        assert_eq!((imu.name.as_str(), imu.id), ("imu", 5));
+        let names: Vec<&str> = imu.values.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["time", "accel_0", "accel_1", "accel_2"]);
+        assert_eq!(imu.values[3].data_type, ValueKind::Int16);
+    }
+
+    #[test]
+    fn test_import_generated_header() {
+        let original: RocketConfig =
+            serde_json::from_str(include_str!("../example_config.json")).unwrap();
+
+        let mut header = vec![];
+        write_c_header(&original, &mut header).unwrap();
+        let header = String::from_utf8(header).unwrap();
+
+        let config = config_from_c_header(&header, "Xenia-2", Endianess::Big).unwrap();
+
+        assert_eq!(config.fingerprint(), original.fingerprint());
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let missing_id = "struct gps { float latitude; };";
+        assert_eq!(
+            config_from_c_header(missing_id, "test", Endianess::Big).unwrap_err(),
+            "No ID define for struct gps"
+        );
+
+        let bad_type = "#define GPS_ID 1\nstruct gps { int latitude; };";
+        assert_eq!(
+            config_from_c_header(bad_type, "test", Endianess::Big).unwrap_err(),
+            "Unsupported field type: int"
+        );
+
+        let duplicate_id = "#define GPS_ID 1\n#define BMP_ID 1\n\
+            struct gps { float latitude; };\n\
+            struct bmp { float pressure; };";
+        assert_eq!(
+            config_from_c_header(duplicate_id, "test", Endianess::Big).unwrap_err(),
+            "Multiple sensors with ID: 1"
+        );
+    }
+
+    #[test]
+    fn test_import_continued_define() {
+        let header = "#define GPS_ID \\\n    3\n\
+            #define GPS_MASK (0x01 | \\\r\n    0x02)\n\
+            struct gps { float latitude; };";
+
+        let config = config_from_c_header(header, "test", Endianess::Big).unwrap();
+
+        assert_eq!(config.sensors.len(), 1);
+        assert_eq!(config.sensors[0].id, 3);
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::configuration::{RocketConfig, ValueKind};
+use crate::data::{PacketError, TypedValue};
+use crate::result_table::SourceIterator;
+use crate::time::PacketClock;
+
+/// Options for writing InfluxDB line protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfluxOptions {
+    /// The value of the `flight` tag, which is left out if not set.
+    pub flight_id: Option<String>,
+    /// The time of the start of the log, in nanoseconds since the Unix epoch.
+    ///
+    /// This is added to the time of every packet, which is normally relative
+    /// to the flight computer starting up. The default is the time the options
+    /// are created, since the log doesn't record when it was written.
+    pub start_time: i64,
+}
+
+impl Default for InfluxOptions {
+    fn default() -> Self {
+        let start_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as i64);
+
+        Self {
+            flight_id: None,
+            start_time,
+        }
+    }
+}
+
+/// Iterator that generates InfluxDB line protocol from data provided.
+///
+/// Every packet becomes one line. The measurement is the sensor name, the tags
+/// are the rocket name and flight ID, and the fields are the values of the
+/// packet. The timestamp is the start time plus the time of the packet from
+/// the [`PacketClock`]. Packets read before any time, or every packet if no
+/// value has the time role, are instead stamped with the start time plus their
+/// index in the log in nanoseconds, so they stay in order and don't overwrite
+/// each other in the database. For the same reason, packets of a sensor read
+/// before the clock moves on, such as from a sensor without its own time
+/// value, are each a nanosecond after the one before.
+///
+/// Floating point values that are NaN or infinite can't be stored by InfluxDB
+/// and are left out.
+pub struct InfluxGenerator<I: SourceIterator> {
+    iter: I,
+    config: RocketConfig,
+    clock: PacketClock,
+    /// The index of the next packet in the log.
+    ordinal: i64,
+    /// The last timestamp of each sensor before it was made unique, by sensor
+    /// ID, with how many later packets were given the same timestamp.
+    last_stamps: HashMap<u8, (i64, i64)>,
+    /// The escaped tag set, including the leading comma.
+    tags: String,
+    options: InfluxOptions,
+}
+
+impl<I: SourceIterator> InfluxGenerator<I> {
+    /// Create a new line protocol generator given a Packet iterator and a
+    /// rocket configuration.
+    pub fn new(iter: I, config: RocketConfig) -> Self {
+        Self::with_options(iter, config, InfluxOptions::default())
+    }
+
+    /// Create a new line protocol generator with custom options.
+    pub fn with_options(iter: I, config: RocketConfig, options: InfluxOptions) -> Self {
+        // Tags are sorted by key, as recommended for write performance.
+        let mut tags = String::new();
+        if let Some(flight_id) = &options.flight_id {
+            tags.push_str(&format!(",flight={}", escape_key(flight_id)));
+        }
+        tags.push_str(&format!(",rocket={}", escape_key(&config.name)));
+
+        Self {
+            iter,
+            clock: PacketClock::new(&config),
+            ordinal: 0,
+            last_stamps: HashMap::new(),
+            config,
+            tags,
+            options,
+        }
+    }
+
+    /// Consume the iterator and write the lines to the given writer.
+    ///
+    /// # Errors
+    ///
+    /// The two cases for error are either an IO error if the writer fails to
+    /// write or a packet error if the iterator fails to yield a packet.
+    pub fn write_influx<W: Write>(self, writer: &mut W) -> Result<(), Box<dyn Error>> {
+        for line in self {
+            let line = line?;
+            writeln!(writer, "{line}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<I: SourceIterator> Iterator for InfluxGenerator<I> {
+    type Item = Result<String, PacketError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let packet = match self.iter.next()? {
+                Ok(packet) => packet,
+                Err(e) => return Some(Err(e)),
+            };
+
+            let Some(sensor) = self.config.get_sensor_by_id(packet.id) else {
+                return Some(Err(PacketError::InvalidId(packet.id)));
+            };
+
+            if packet.values.len() != sensor.values.len() {
+                return Some(Err(PacketError::InvalidValueCount {
+                    expected: sensor.values.len(),
+                    actual: packet.values.len(),
+                }));
+            }
+
+            let time = self.clock.update(&packet);
+            let ordinal = self.ordinal;
+            self.ordinal += 1;
+
+            let fields = sensor
+                .values
+                .iter()
+                .zip(packet.values.iter())
+                .filter_map(|(spec, value)| {
+                    let value = unsafe { TypedValue::new(*value, &spec.data_type) };
+                    Some(format!(
+                        "{}={}",
+                        escape_key(&spec.name),
+                        field_value(&value)?
+                    ))
+                })
+                .collect::<Vec<String>>();
+
+            // A line must have at least one field.
+            if fields.is_empty() {
+                continue;
+            }
+
+            let offset = match time {
+                Some(time) => (time * 1e9).round() as i64,
+                None => ordinal,
+            };
+            let timestamp = self.options.start_time + offset;
+
+            // InfluxDB keeps only the last point of a series at a timestamp.
+            let (last, repeats) = self.last_stamps.entry(packet.id).or_insert((timestamp, -1));
+            if *last == timestamp {
+                *repeats += 1;
+            } else {
+                (*last, *repeats) = (timestamp, 0);
+            }
+            let timestamp = timestamp + *repeats;
+
+            return Some(Ok(format!(
+                "{}{} {} {}",
+                escape_measurement(&sensor.name),
+                self.tags,
+                fields.join(","),
+                timestamp
+            )));
+        }
+    }
+}
+
+/// Format a field value with the type suffix InfluxDB expects.
+///
+/// Returns `None` for floats that can't be stored.
+fn field_value(value: &TypedValue) -> Option<String> {
+    let raw = value.value();
+
+    // The safety is assumed by the constructor of the typed value.
+    unsafe {
+        match value.kind() {
+            ValueKind::Int8 | ValueKind::Int16 | ValueKind::Int32 | ValueKind::Int64 => {
+                Some(format!("{value}i"))
+            }
+            ValueKind::UInt8 | ValueKind::UInt16 | ValueKind::UInt32 | ValueKind::UInt64 => {
+                Some(format!("{value}u"))
+            }
+            ValueKind::Float32 if raw.float_32.is_finite() => Some(format!("{}", raw.float_32)),
+            ValueKind::Float64 if raw.float_64.is_finite() => Some(format!("{}", raw.float_64)),
+            ValueKind::Float32 | ValueKind::Float64 => None,
+        }
+    }
+}
+
+/// Escape a measurement name.
+fn escape_measurement(name: &str) -> String {
+    name.replace(',', "\\,").replace(' ', "\\ ")
+}
+
+/// Escape a tag key, tag value or field key.
+fn escape_key(name: &str) -> String {
+    escape_measurement(name).replace('=', "\\=")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::{Axis, Endianess, SensorConfig, ValueConfig, ValueRole};
+    use crate::data::{Packet, Value};
+
+    use super::*;
+
+    fn test_config() -> RocketConfig {
+        RocketConfig {
+            name: "Xenia 2".to_string(),
+            endianess: Endianess::default(),
+            nose_axis: Axis::default(),
+            include: vec![],
+            display_name: None,
+            description: None,
+            kalman: None,
+            sensors: vec![
+                SensorConfig {
+                    id: 0,
+                    name: "clock".to_string(),
+                    values: vec![ValueConfig {
+                        name: "time".to_string(),
+                        data_type: ValueKind::UInt32,
+                        unit: Some("ms".to_string()),
+                        role: Some(ValueRole::Time),
+                        filters: vec![],
+                    }],
+                },
+                SensorConfig {
+                    id: 1,
+                    name: "BMP".to_string(),
+                    values: vec![
+                        ValueConfig {
+                            name: "temp".to_string(),
+                            data_type: ValueKind::Float32,
+                            unit: None,
+                            role: None,
+                            filters: vec![],
+                        },
+                        ValueConfig {
+                            name: "raw=count".to_string(),
+                            data_type: ValueKind::Int16,
+                            unit: None,
+                            role: None,
+                            filters: vec![],
+                        },
+                    ],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_influx_lines() {
+        let packets = vec![
+            Packet {
+                id: 1,
+                values: vec![Value { float_32: 21.5 }, Value { int_16: -3 }],
+            },
+            Packet {
+                id: 0,
+                values: vec![Value { uint_32: 1500 }],
+            },
+            Packet {
+                id: 1,
+                values: vec![Value { float_32: 22.0 }, Value { int_16: 4 }],
+            },
+        ];
+        let options = InfluxOptions {
+            flight_id: Some("launch-1".to_string()),
+            start_time: 1_000,
+        };
+
+        let lines =
+            InfluxGenerator::with_options(packets.into_iter().map(Ok), test_config(), options)
+                .collect::<Result<Vec<String>, PacketError>>()
+                .unwrap();
+
+        assert_eq!(
+            lines,
+            vec![
+                r"BMP,flight=launch-1,rocket=Xenia\ 2 temp=21.5,raw\=count=-3i 1000",
+                r"clock,flight=launch-1,rocket=Xenia\ 2 time=1500u 1500001000",
+                r"BMP,flight=launch-1,rocket=Xenia\ 2 temp=22,raw\=count=4i 1500001000",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_influx_unique_timestamps() {
+        let bmp = |temp| Packet {
+            id: 1,
+            values: vec![Value { float_32: temp }, Value { int_16: 0 }],
+        };
+        let packets = vec![
+            Packet {
+                id: 0,
+                values: vec![Value { uint_32: 1 }],
+            },
+            bmp(1.0),
+            bmp(2.0),
+            bmp(3.0),
+            Packet {
+                id: 0,
+                values: vec![Value { uint_32: 2 }],
+            },
+            bmp(4.0),
+        ];
+        let options = InfluxOptions {
+            flight_id: None,
+            start_time: 0,
+        };
+
+        let stamps: Vec<String> =
+            InfluxGenerator::with_options(packets.into_iter().map(Ok), test_config(), options)
+                .map(|line| line.unwrap().rsplit(' ').next().unwrap().to_string())
+                .collect();
+
+        assert_eq!(
+            stamps,
+            ["1000000", "1000000", "1000001", "1000002", "2000000", "2000000"]
+        );
+    }
+
+    #[test]
+    fn test_influx_skips_non_finite_floats() {
+        let packets = vec![Packet {
+            id: 1,
+            values: vec![Value { float_32: f32::NAN }, Value { int_16: 1 }],
+        }];
+
+        let options = InfluxOptions {
+            flight_id: None,
+            start_time: 0,
+        };
+
+        let mut influx =
+            InfluxGenerator::with_options(packets.into_iter().map(Ok), test_config(), options);
+
+        assert_eq!(
+            influx.next().unwrap().unwrap(),
+            r"BMP,rocket=Xenia\ 2 raw\=count=1i 0"
+        );
+        assert!(influx.next().is_none());
+    }
+
+    #[test]
+    fn test_influx_without_time_uses_ordinal() {
+        let mut config = test_config();
+        config.sensors.remove(0);
+        let packets = vec![
+            Packet {
+                id: 1,
+                values: vec![Value { float_32: 1.0 }, Value { int_16: 1 }],
+            },
+            Packet {
+                id: 1,
+                values: vec![Value { float_32: 2.0 }, Value { int_16: 2 }],
+            },
+        ];
+        let options = InfluxOptions {
+            flight_id: None,
+            start_time: 5_000,
+        };
+
+        let lines = InfluxGenerator::with_options(packets.into_iter().map(Ok), config, options)
+            .collect::<Result<Vec<String>, PacketError>>()
+            .unwrap();
+
+        assert_eq!(
+            lines,
+            vec![
+                r"BMP,rocket=Xenia\ 2 temp=1,raw\=count=1i 5000",
+                r"BMP,rocket=Xenia\ 2 temp=2,raw\=count=2i 5001",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_influx_default_start_time_is_now() {
+        let start_time = InfluxOptions::default().start_time;
+
+        // Later than the start of 2020.
+        assert!(start_time > 1_577_836_800_000_000_000);
+    }
+}
@@ -1,21 +1,56 @@
-use std::io::BufReader;
+use std::path::Path;
 
+use crate::configuration::format::ConfigFormat;
 use crate::configuration::RocketConfig;
 
+pub mod analysis;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+pub mod codegen;
 pub mod configuration;
 pub mod csv;
 pub mod data;
+pub mod gps;
+pub mod header;
+pub mod import;
+pub mod influx;
+pub mod matlab;
+pub mod openrocket;
 #[cfg(feature = "report")]
 pub mod report;
 pub mod result_table;
+pub mod time;
+#[cfg(feature = "xlsx")]
+pub mod xlsx;
 
-pub fn load_config(path: &str) -> Result<RocketConfig, String> {
-    let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
-    let file_reader = BufReader::new(file);
+/// Load a config file, including any shared sensor files it includes.
+///
+/// The format is chosen from the extension of the file, or from the content if
+/// the extension isn't known. See [`ConfigFormat`]. The filters of the config
+/// are checked with [`RocketConfig::validate_filters`].
+pub fn load_config<P: AsRef<Path>>(path: P) -> Result<RocketConfig, String> {
+    let content = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let format = ConfigFormat::from_path(&path).unwrap_or_else(|| ConfigFormat::detect(&content));
 
-    serde_json::from_reader(file_reader).map_err(|e| e.to_string())
+    let mut config = format.parse(&content)?;
+    config.resolve_includes(path)?;
+    config.validate_filters()?;
+
+    Ok(config)
 }
 
+/// Load a config from a string, detecting the format from the content.
+///
+/// Configs that include other files can't be loaded from a string, because
+/// the paths are relative to the config file. The filters are checked as in
+/// [`load_config`].
 pub fn load_config_str(config: &str) -> Result<RocketConfig, String> {
-    serde_json::from_str(config).map_err(|e| e.to_string())
-}
\ No newline at end of file
+    let config = ConfigFormat::detect(config).parse(config)?;
+
+    if !config.include.is_empty() {
+        return Err("Includes can only be used in config files".to_string());
+    }
+    config.validate_filters()?;
+
+    Ok(config)
+}
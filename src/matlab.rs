@@ -0,0 +1,475 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::io::Write;
+
+use crate::configuration::{Endianess, RocketConfig, SensorConfig, ValueKind};
+use crate::data::{PacketError, Value};
+use crate::result_table::SourceIterator;
+
+/// The descriptive text at the start of every file.
+const HEADER_TEXT: &str = "MATLAB 5.0 MAT-file, written by flight_data_reader";
+
+/// The name of the variable holding the rocket configuration.
+pub const METADATA_NAME: &str = "metadata";
+
+/// The longest identifier MATLAB accepts for variable and field names.
+const MAX_NAME_LENGTH: usize = 63;
+
+// Data types of the elements in the file.
+const MI_INT8: u32 = 1;
+const MI_UINT8: u32 = 2;
+const MI_INT16: u32 = 3;
+const MI_UINT16: u32 = 4;
+const MI_INT32: u32 = 5;
+const MI_UINT32: u32 = 6;
+const MI_SINGLE: u32 = 7;
+const MI_DOUBLE: u32 = 9;
+const MI_INT64: u32 = 12;
+const MI_UINT64: u32 = 13;
+const MI_MATRIX: u32 = 14;
+
+// Classes of the arrays stored in matrix elements.
+const MX_STRUCT_CLASS: u32 = 2;
+const MX_CHAR_CLASS: u32 = 4;
+const MX_DOUBLE_CLASS: u32 = 6;
+const MX_SINGLE_CLASS: u32 = 7;
+const MX_INT8_CLASS: u32 = 8;
+const MX_UINT8_CLASS: u32 = 9;
+const MX_INT16_CLASS: u32 = 10;
+const MX_UINT16_CLASS: u32 = 11;
+const MX_INT32_CLASS: u32 = 12;
+const MX_UINT32_CLASS: u32 = 13;
+const MX_INT64_CLASS: u32 = 14;
+const MX_UINT64_CLASS: u32 = 15;
+
+/// Writer for MATLAB/Octave MAT-files (level 5).
+///
+/// Each sensor is stored as a struct variable named after the sensor, with one
+/// column vector per value. The vectors keep the precision of the configured
+/// [`ValueKind`], so an `int_16` value becomes an `int16` vector and a
+/// `float_32` value becomes a `single` vector. The rocket configuration is
+/// stored in a struct called [`METADATA_NAME`].
+///
+/// Names that are not valid MATLAB identifiers have every invalid character
+/// replaced with an underscore, and names that become the same as an earlier
+/// one have a number appended.
+pub struct MatGenerator<I: SourceIterator> {
+    iter: I,
+    config: RocketConfig,
+}
+
+impl<I: SourceIterator> MatGenerator<I> {
+    /// Create a new MAT-file generator given a Packet iterator and a rocket
+    /// configuration.
+    pub fn new(iter: I, config: RocketConfig) -> Self {
+        Self { iter, config }
+    }
+
+    /// Consume the iterator and write the MAT-file to the given writer.
+    ///
+    /// All packets are read into memory before anything is written, because
+    /// the size of every variable is stored before its data.
+    ///
+    /// # Errors
+    ///
+    /// The two cases for error are either an IO error if the writer fails to
+    /// write or a packet error if the iterator fails to yield a valid packet.
+    pub fn write_mat<W: Write>(self, writer: &mut W) -> Result<(), Box<dyn Error>> {
+        // The samples of every value, indexed by sensor ID and value index.
+        let mut samples: HashMap<u8, Vec<Vec<Value>>> = HashMap::new();
+
+        for packet in self.iter {
+            let packet = packet?;
+
+            let Some(sensor) = self.config.get_sensor_by_id(packet.id) else {
+                return Err(PacketError::InvalidId(packet.id).into());
+            };
+
+            if packet.values.len() != sensor.values.len() {
+                return Err(PacketError::InvalidValueCount {
+                    expected: sensor.values.len(),
+                    actual: packet.values.len(),
+                }
+                .into());
+            }
+
+            let columns = samples
+                .entry(sensor.id)
+                .or_insert_with(|| vec![vec![]; sensor.values.len()]);
+            for (column, value) in columns.iter_mut().zip(packet.values) {
+                column.push(value);
+            }
+        }
+
+        writer.write_all(&header())?;
+
+        let mut names = HashSet::from([METADATA_NAME.to_string()]);
+        for sensor in self.config.sensors.iter() {
+            let columns = samples.remove(&sensor.id).unwrap_or_default();
+            let name = unique_identifier(&sensor.name, &mut names);
+            writer.write_all(&sensor_matrix(&name, sensor, &columns))?;
+        }
+
+        writer.write_all(&metadata_matrix(&self.config))?;
+
+        Ok(())
+    }
+}
+
+/// Make a name usable as a MATLAB variable or field name.
+///
+/// Invalid characters are replaced with underscores, names that don't start
+/// with a letter are prefixed with `x` and the result is truncated to the
+/// longest name MATLAB accepts.
+pub fn matlab_identifier(name: &str) -> String {
+    let mut result: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    if !result.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        result.insert(0, 'x');
+    }
+
+    result.truncate(MAX_NAME_LENGTH);
+    result
+}
+
+/// Make a name a MATLAB identifier that isn't already used.
+///
+/// Names that are already used, after being made valid identifiers, have a
+/// number appended.
+fn unique_identifier(name: &str, used: &mut HashSet<String>) -> String {
+    let base = matlab_identifier(name);
+
+    let mut candidate = base.clone();
+    let mut suffix = 2;
+
+    while used.contains(&candidate) {
+        let suffix_text = format!("_{suffix}");
+        let prefix: String = base
+            .chars()
+            .take(MAX_NAME_LENGTH - suffix_text.len())
+            .collect();
+        candidate = format!("{prefix}{suffix_text}");
+        suffix += 1;
+    }
+
+    used.insert(candidate.clone());
+    candidate
+}
+
+/// The 128 byte file header.
+fn header() -> Vec<u8> {
+    let mut result = format!("{HEADER_TEXT:<116}").into_bytes();
+    // Subsystem data offset, unused.
+    result.extend_from_slice(&[0; 8]);
+    // Version.
+    result.extend_from_slice(&0x0100u16.to_le_bytes());
+    // Endian indicator, which reads as "MI" when the bytes are in order.
+    result.extend_from_slice(b"IM");
+    result
+}
+
+/// A data element with its tag and padding to a multiple of 8 bytes.
+fn element(data_type: u32, data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len() + 16);
+    result.extend_from_slice(&data_type.to_le_bytes());
+    result.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    result.extend_from_slice(data);
+    result.resize(result.len() + (8 - data.len() % 8) % 8, 0);
+    result
+}
+
+/// A matrix element with the array flags, dimensions and name sub-elements
+/// followed by the given contents.
+fn matrix(class: u32, dimensions: &[i32], name: &str, contents: &[u8]) -> Vec<u8> {
+    let mut data = vec![];
+
+    let mut flags = class.to_le_bytes().to_vec();
+    flags.extend_from_slice(&[0; 4]);
+    data.extend(element(MI_UINT32, &flags));
+
+    let dimensions: Vec<u8> = dimensions.iter().flat_map(|d| d.to_le_bytes()).collect();
+    data.extend(element(MI_INT32, &dimensions));
+
+    data.extend(element(MI_INT8, name.as_bytes()));
+    data.extend_from_slice(contents);
+
+    element(MI_MATRIX, &data)
+}
+
+/// A 1x1 struct with the given named fields.
+///
+/// Each field must be a matrix element, which is written without a name.
+fn struct_matrix(name: &str, fields: Vec<(String, Vec<u8>)>) -> Vec<u8> {
+    let mut used = HashSet::new();
+    let names: Vec<String> = fields
+        .iter()
+        .map(|(n, _)| unique_identifier(n, &mut used))
+        .collect();
+    let name_length = names.iter().map(String::len).max().unwrap_or(0) + 1;
+
+    let mut contents = element(MI_INT32, &(name_length as i32).to_le_bytes());
+
+    let mut name_bytes = vec![0; name_length * names.len()];
+    for (i, name) in names.iter().enumerate() {
+        name_bytes[i * name_length..i * name_length + name.len()].copy_from_slice(name.as_bytes());
+    }
+    contents.extend(element(MI_INT8, &name_bytes));
+
+    for (_, field) in fields {
+        contents.extend(field);
+    }
+
+    matrix(MX_STRUCT_CLASS, &[1, 1], name, &contents)
+}
+
+/// A character row vector.
+fn char_matrix(name: &str, value: &str) -> Vec<u8> {
+    let utf16: Vec<u16> = value.encode_utf16().collect();
+    let data: Vec<u8> = utf16.iter().flat_map(|c| c.to_le_bytes()).collect();
+
+    matrix(
+        MX_CHAR_CLASS,
+        &[1, utf16.len() as i32],
+        name,
+        &element(MI_UINT16, &data),
+    )
+}
+
+/// A numeric column vector of the given kind.
+fn numeric_matrix(name: &str, kind: &ValueKind, values: &[Value]) -> Vec<u8> {
+    let (class, data_type) = match kind {
+        ValueKind::Int8 => (MX_INT8_CLASS, MI_INT8),
+        ValueKind::Int16 => (MX_INT16_CLASS, MI_INT16),
+        ValueKind::Int32 => (MX_INT32_CLASS, MI_INT32),
+        ValueKind::Int64 => (MX_INT64_CLASS, MI_INT64),
+        ValueKind::UInt8 => (MX_UINT8_CLASS, MI_UINT8),
+        ValueKind::UInt16 => (MX_UINT16_CLASS, MI_UINT16),
+        ValueKind::UInt32 => (MX_UINT32_CLASS, MI_UINT32),
+        ValueKind::UInt64 => (MX_UINT64_CLASS, MI_UINT64),
+        ValueKind::Float32 => (MX_SINGLE_CLASS, MI_SINGLE),
+        ValueKind::Float64 => (MX_DOUBLE_CLASS, MI_DOUBLE),
+    };
+
+    // The safety is assumed by the packets matching the configuration.
+    let data: Vec<u8> = values
+        .iter()
+        .flat_map(|value| unsafe { value.to_bytes(kind, Endianess::Little) })
+        .collect();
+
+    matrix(
+        class,
+        &[values.len() as i32, 1],
+        name,
+        &element(data_type, &data),
+    )
+}
+
+/// The struct variable for a single sensor, with the given variable name.
+fn sensor_matrix(name: &str, sensor: &SensorConfig, columns: &[Vec<Value>]) -> Vec<u8> {
+    let fields = sensor
+        .values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let samples = columns.get(i).map(Vec::as_slice).unwrap_or_default();
+            let field = numeric_matrix("", &value.data_type, samples);
+            (value.name.clone(), field)
+        })
+        .collect();
+
+    struct_matrix(name, fields)
+}
+
+/// The struct variable describing the rocket configuration.
+///
+/// Every sensor is a field of the `sensors` struct, holding its `id` and the
+/// data type of every value in `types`.
+fn metadata_matrix(config: &RocketConfig) -> Vec<u8> {
+    let sensors = config
+        .sensors
+        .iter()
+        .map(|sensor| {
+            let types = sensor
+                .values
+                .iter()
+                .map(|v| (v.name.clone(), char_matrix("", &v.data_type.to_string())))
+                .collect();
+            let id = numeric_matrix("", &ValueKind::UInt8, &[Value { uint_8: sensor.id }]);
+            let fields = vec![
+                ("id".to_string(), id),
+                ("types".to_string(), struct_matrix("", types)),
+            ];
+
+            (sensor.name.clone(), struct_matrix("", fields))
+        })
+        .collect();
+
+    let endianess = match config.endianess {
+        Endianess::Little => "Little",
+        Endianess::Big => "Big",
+    };
+
+    let fields = vec![
+        ("name".to_string(), char_matrix("", &config.name)),
+        (
+            "display_name".to_string(),
+            char_matrix("", config.display_name()),
+        ),
+        (
+            "description".to_string(),
+            char_matrix("", config.description.as_deref().unwrap_or_default()),
+        ),
+        ("endianess".to_string(), char_matrix("", endianess)),
+        ("sensors".to_string(), struct_matrix("", sensors)),
+    ];
+
+    struct_matrix(METADATA_NAME, fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::{Axis, SensorConfig, ValueConfig};
+    use crate::data::Packet;
+
+    use super::*;
+
+    fn test_config() -> RocketConfig {
+        RocketConfig {
+            name: "test".to_string(),
+            endianess: Endianess::default(),
+            nose_axis: Axis::default(),
+            include: vec![],
+            display_name: None,
+            description: None,
+            kalman: None,
+            sensors: vec![SensorConfig {
+                id: 0,
+                name: "BMP".to_string(),
+                values: vec![ValueConfig {
+                    name: "temp".to_string(),
+                    data_type: ValueKind::Int16,
+                    unit: None,
+                    role: None,
+                    filters: vec![],
+                }],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_matlab_identifier() {
+        assert_eq!(matlab_identifier("LSM"), "LSM");
+        assert_eq!(matlab_identifier("accel x"), "accel_x");
+        assert_eq!(matlab_identifier("9dof"), "x9dof");
+        assert_eq!(matlab_identifier(&"a".repeat(100)).len(), 63);
+    }
+
+    #[test]
+    fn test_unique_identifier() {
+        let mut used = HashSet::from([METADATA_NAME.to_string()]);
+
+        assert_eq!(unique_identifier("accel x", &mut used), "accel_x");
+        assert_eq!(unique_identifier("accel_x", &mut used), "accel_x_2");
+        assert_eq!(unique_identifier("accel-x", &mut used), "accel_x_3");
+        assert_eq!(unique_identifier("metadata", &mut used), "metadata_2");
+        assert_eq!(unique_identifier("Accel_x", &mut used), "Accel_x");
+        assert_eq!(unique_identifier(&"a".repeat(100), &mut used).len(), 63);
+        assert_eq!(unique_identifier(&"a".repeat(100), &mut used).len(), 63);
+    }
+
+    #[test]
+    fn test_struct_field_names_are_unique() {
+        let field = || numeric_matrix("", &ValueKind::UInt8, &[Value { uint_8: 1 }]);
+        let result = struct_matrix(
+            "s",
+            vec![
+                ("accel x".to_string(), field()),
+                ("accel_x".to_string(), field()),
+            ],
+        );
+
+        assert!(result.windows(8).any(|w| w == b"accel_x\0"));
+        assert!(result.windows(9).any(|w| w == b"accel_x_2"));
+    }
+
+    #[test]
+    fn test_element_padding() {
+        assert_eq!(
+            element(MI_INT8, b"abc"),
+            [1, 0, 0, 0, 3, 0, 0, 0, b'a', b'b', b'c', 0, 0, 0, 0, 0]
+        );
+        assert_eq!(element(MI_INT8, b""), [1, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_numeric_matrix_keeps_precision() {
+        let result = numeric_matrix(
+            "v",
+            &ValueKind::Int16,
+            &[Value { int_16: 1 }, Value { int_16: -2 }],
+        );
+
+        #[rustfmt::skip]
+        let expected: Vec<u8> = vec![
+            14, 0, 0, 0, 64, 0, 0, 0,
+            // Array flags.
+            6, 0, 0, 0, 8, 0, 0, 0, 10, 0, 0, 0, 0, 0, 0, 0,
+            // Dimensions.
+            5, 0, 0, 0, 8, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0,
+            // Name.
+            1, 0, 0, 0, 1, 0, 0, 0, b'v', 0, 0, 0, 0, 0, 0, 0,
+            // Data.
+            3, 0, 0, 0, 4, 0, 0, 0, 1, 0, 0xfe, 0xff, 0, 0, 0, 0,
+        ];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_write_mat() {
+        let packets = vec![
+            Packet {
+                id: 0,
+                values: vec![Value { int_16: 20 }],
+            },
+            Packet {
+                id: 0,
+                values: vec![Value { int_16: 21 }],
+            },
+        ];
+
+        let mut result = vec![];
+        MatGenerator::new(packets.into_iter().map(Ok), test_config())
+            .write_mat(&mut result)
+            .unwrap();
+
+        assert!(result.starts_with(b"MATLAB 5.0 MAT-file"));
+        assert_eq!(&result[124..128], &[0x00, 0x01, b'I', b'M']);
+        assert_eq!(result.len() % 8, 0);
+
+        // The first variable is the sensor struct.
+        assert_eq!(&result[128..132], &MI_MATRIX.to_le_bytes());
+        assert_eq!(result[144], MX_STRUCT_CLASS as u8);
+        assert!(result.windows(4).any(|w| w == [20, 0, 21, 0]));
+        assert!(result.windows(8).any(|w| w == b"metadata"));
+    }
+
+    #[test]
+    fn test_write_mat_invalid_id() {
+        let packets = vec![Packet {
+            id: 9,
+            values: vec![],
+        }];
+
+        let mut result = vec![];
+        let error = MatGenerator::new(packets.into_iter().map(Ok), test_config())
+            .write_mat(&mut result)
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), "Invalid packet id: 9");
+        assert!(result.is_empty());
+    }
+}
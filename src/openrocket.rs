@@ -0,0 +1,314 @@
+use std::error::Error;
+use std::io::Write;
+
+use crate::analysis::atmosphere::pressure_altitude;
+use crate::analysis::Channel;
+use crate::configuration::{RocketConfig, ValueRole, STANDARD_GRAVITY};
+use crate::data::PacketError;
+use crate::result_table::SourceIterator;
+use crate::time::PacketClock;
+
+/// The column header of the file, in the same form OpenRocket exports.
+const COLUMN_HEADER: &str =
+    "# Time (s),Altitude (m),Vertical velocity (m/s),Vertical acceleration (m/s²)";
+
+/// Options for generating OpenRocket flight data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenRocketOptions {
+    /// Whether standard gravity is subtracted from the axial acceleration.
+    ///
+    /// An accelerometer reads 1 g while the rocket is standing still, so this
+    /// is needed to get the vertical acceleration OpenRocket simulates. It
+    /// should only be disabled if the accelerometer already removes gravity.
+    pub subtract_gravity: bool,
+}
+
+impl Default for OpenRocketOptions {
+    fn default() -> Self {
+        Self {
+            subtract_gravity: true,
+        }
+    }
+}
+
+/// A single row of OpenRocket flight data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OpenRocketRow {
+    /// The time of the row in seconds.
+    pub time: f64,
+    /// The altitude above the ground in metres.
+    pub altitude: f64,
+    /// The vertical velocity in metres per second.
+    pub vertical_velocity: f64,
+    /// The vertical acceleration in metres per second squared, if the config
+    /// has an acceleration value along the nose axis and it has been read.
+    pub vertical_acceleration: Option<f64>,
+}
+
+/// Iterator that generates flight data in the layout of OpenRocket's CSV
+/// files from data provided.
+///
+/// A row is generated for every barometer reading, found with the
+/// [`ValueRole::Pressure`] role. The altitude is calculated using the standard
+/// atmosphere with the first reading as the ground, and the vertical velocity
+/// is the change in altitude since the previous reading. The acceleration is
+/// the most recent reading along the [`RocketConfig::nose_axis`], which
+/// assumes the rocket is flying vertically.
+pub struct OpenRocketGenerator<I: SourceIterator> {
+    iter: I,
+    config: RocketConfig,
+    clock: PacketClock,
+    options: OpenRocketOptions,
+    pressure: Channel,
+    acceleration: Option<Channel>,
+    ground_pressure: Option<f64>,
+    /// The time and altitude of the previous row.
+    previous: Option<(f64, f64)>,
+    vertical_velocity: f64,
+    vertical_acceleration: Option<f64>,
+}
+
+impl<I: SourceIterator> OpenRocketGenerator<I> {
+    /// Create a new OpenRocket generator given a Packet iterator and a rocket
+    /// configuration.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the config doesn't have both a pressure and a
+    /// time value.
+    pub fn new(iter: I, config: RocketConfig) -> Result<Self, String> {
+        Self::with_options(iter, config, OpenRocketOptions::default())
+    }
+
+    /// Create a new OpenRocket generator with custom options.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the config doesn't have both a pressure and a
+    /// time value.
+    pub fn with_options(
+        iter: I,
+        config: RocketConfig,
+        options: OpenRocketOptions,
+    ) -> Result<Self, String> {
+        let Some(pressure) = Channel::from_role(&config, ValueRole::Pressure) else {
+            return Err("No value has the pressure role".to_string());
+        };
+
+        let clock = PacketClock::new(&config);
+        if !clock.has_source() {
+            return Err("No value has the time role".to_string());
+        }
+
+        Ok(Self {
+            iter,
+            acceleration: Channel::axial_acceleration(&config),
+            config,
+            clock,
+            options,
+            pressure,
+            ground_pressure: None,
+            previous: None,
+            vertical_velocity: 0.0,
+            vertical_acceleration: None,
+        })
+    }
+
+    /// Consume the iterator and write the flight data to the given writer.
+    ///
+    /// # Errors
+    ///
+    /// The two cases for error are either an IO error if the writer fails to
+    /// write or a packet error if the iterator fails to yield a packet.
+    pub fn write_openrocket<W: Write>(self, writer: &mut W) -> Result<(), Box<dyn Error>> {
+        writeln!(writer, "# {}", self.config.display_name())?;
+        writeln!(writer, "{COLUMN_HEADER}")?;
+
+        for row in self {
+            let row = row?;
+
+            write!(
+                writer,
+                "{:.3},{:.3},{:.3},",
+                row.time, row.altitude, row.vertical_velocity
+            )?;
+            if let Some(vertical_acceleration) = row.vertical_acceleration {
+                write!(writer, "{vertical_acceleration:.3}")?;
+            }
+            writeln!(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<I: SourceIterator> Iterator for OpenRocketGenerator<I> {
+    type Item = Result<OpenRocketRow, PacketError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let packet = match self.iter.next()? {
+                Ok(packet) => packet,
+                Err(e) => return Some(Err(e)),
+            };
+
+            let Some(sensor) = self.config.get_sensor_by_id(packet.id) else {
+                return Some(Err(PacketError::InvalidId(packet.id)));
+            };
+
+            if packet.values.len() != sensor.values.len() {
+                return Some(Err(PacketError::InvalidValueCount {
+                    expected: sensor.values.len(),
+                    actual: packet.values.len(),
+                }));
+            }
+
+            let time = self.clock.update(&packet);
+
+            if let Some(acceleration) = self.acceleration.and_then(|c| c.read(&packet)) {
+                let gravity = if self.options.subtract_gravity {
+                    STANDARD_GRAVITY
+                } else {
+                    0.0
+                };
+                self.vertical_acceleration = Some(acceleration - gravity);
+            }
+
+            let Some(pressure) = self.pressure.read(&packet) else {
+                continue;
+            };
+            let Some(time) = time else {
+                continue;
+            };
+
+            let ground_pressure = *self.ground_pressure.get_or_insert(pressure);
+            let altitude = pressure_altitude(pressure, ground_pressure);
+
+            if let Some((previous_time, previous_altitude)) = self.previous {
+                let elapsed = time - previous_time;
+                if elapsed > 0.0 {
+                    self.vertical_velocity = (altitude - previous_altitude) / elapsed;
+                }
+            }
+            self.previous = Some((time, altitude));
+
+            return Some(Ok(OpenRocketRow {
+                time,
+                altitude,
+                vertical_velocity: self.vertical_velocity,
+                vertical_acceleration: self.vertical_acceleration,
+            }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::{Axis, Endianess, SensorConfig, ValueConfig, ValueKind};
+    use crate::data::{Packet, Value};
+
+    use super::*;
+
+    fn value(name: &str, unit: &str, role: ValueRole) -> ValueConfig {
+        ValueConfig {
+            name: name.to_string(),
+            data_type: ValueKind::Float32,
+            unit: Some(unit.to_string()),
+            role: Some(role),
+            filters: vec![],
+        }
+    }
+
+    fn test_config() -> RocketConfig {
+        RocketConfig {
+            name: "test".to_string(),
+            endianess: Endianess::default(),
+            nose_axis: Axis::X,
+            include: vec![],
+            display_name: None,
+            description: None,
+            kalman: None,
+            sensors: vec![
+                SensorConfig {
+                    id: 0,
+                    name: "clock".to_string(),
+                    values: vec![value("time", "s", ValueRole::Time)],
+                },
+                SensorConfig {
+                    id: 1,
+                    name: "BMP".to_string(),
+                    values: vec![value("pressure", "hPa", ValueRole::Pressure)],
+                },
+                SensorConfig {
+                    id: 2,
+                    name: "IMU".to_string(),
+                    values: vec![value("x", "g", ValueRole::AccelerationX)],
+                },
+            ],
+        }
+    }
+
+    fn packet(id: u8, value: f32) -> Packet {
+        Packet {
+            id,
+            values: vec![Value { float_32: value }],
+        }
+    }
+
+    #[test]
+    fn test_openrocket_rows() {
+        let packets = vec![
+            packet(1, 1013.25),
+            packet(0, 0.0),
+            packet(2, 1.0),
+            packet(1, 1013.25),
+            packet(0, 2.0),
+            packet(2, 3.0),
+            packet(1, 898.746),
+        ];
+
+        let rows = OpenRocketGenerator::new(packets.into_iter().map(Ok), test_config())
+            .unwrap()
+            .collect::<Result<Vec<OpenRocketRow>, PacketError>>()
+            .unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].time, 0.0);
+        assert_eq!(rows[0].altitude, 0.0);
+        assert_eq!(rows[0].vertical_velocity, 0.0);
+        assert!(rows[0].vertical_acceleration.unwrap().abs() < 1e-6);
+
+        assert_eq!(rows[1].time, 2.0);
+        assert!((rows[1].altitude - 1000.0).abs() < 0.5);
+        assert!((rows[1].vertical_velocity - 500.0).abs() < 0.5);
+        assert!((rows[1].vertical_acceleration.unwrap() - 2.0 * STANDARD_GRAVITY).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_openrocket_requires_pressure() {
+        let mut config = test_config();
+        config.sensors.remove(1);
+
+        let packets: Vec<Packet> = vec![];
+
+        assert!(OpenRocketGenerator::new(packets.into_iter().map(Ok), config).is_err());
+    }
+
+    #[test]
+    fn test_write_openrocket() {
+        let mut config = test_config();
+        config.sensors.remove(2);
+        let packets = vec![packet(0, 1.5), packet(1, 1013.25)];
+
+        let mut result = vec![];
+        OpenRocketGenerator::new(packets.into_iter().map(Ok), config)
+            .unwrap()
+            .write_openrocket(&mut result)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(result).unwrap(),
+            format!("# test\n{COLUMN_HEADER}\n1.500,0.000,0.000,\n")
+        );
+    }
+}
@@ -1,30 +1,213 @@
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::Write;
 
-use crate::configuration::RocketConfig;
+use crate::analysis::altitude::{AltitudeEstimator, AltitudeOptions};
+use crate::analysis::events::{EventDetector, EventKind, EventOptions, FlightEvent};
+use crate::analysis::orientation::{OrientationEstimator, OrientationOptions};
+use crate::analysis::rate::{Gap, RateAnalysis, RateOptions, SensorRate, TimeBase};
+use crate::analysis::stats::{P2Quantile, RunningStats};
+use crate::configuration::{RocketConfig, SensorConfig};
 use crate::data::{PacketParser, TypedValue};
-use crate::report::latex::LatexElement;
-use crate::result_table::TableGenerator;
+use crate::report::latex::{escape, LatexElement};
+use crate::result_table::{SourceIterator, TableGenerator};
 
 mod latex;
 
+/// The most dropouts of a sensor that are listed in the report.
+const MAX_LISTED_GAPS: usize = 10;
+
 const HEADER_CONTENT: &str = r#"\documentclass{article}
 
 "#;
 
+/// Options for generating a report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportOptions {
+    /// The percentiles estimated for every value, between 0 and 100.
+    pub percentiles: Vec<f64>,
+    /// Options for the data rate of every sensor.
+    pub rates: RateOptions,
+    /// Options for detecting the events of the flight.
+    pub events: EventOptions,
+    /// Options for the altitude from the barometer.
+    pub altitude: AltitudeOptions,
+    /// Options for the orientation from the gyroscope.
+    pub orientation: OrientationOptions,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        Self {
+            percentiles: vec![1.0, 5.0, 95.0, 99.0],
+            rates: RateOptions::default(),
+            events: EventOptions::default(),
+            altitude: AltitudeOptions::default(),
+            orientation: OrientationOptions::default(),
+        }
+    }
+}
+
 /// Statistics on a value.
 ///
 /// This is used during the construction of a report to calculate statistics on
-/// the data.
+/// the data. The statistics are calculated as the samples are recorded, so the
+/// memory used doesn't grow with the length of the log.
+///
+/// NaN and infinite samples are counted, but are not included in any of the
+/// other statistics except the first and last value.
 #[derive(Debug, Clone)]
 pub struct ValueStats {
     /// The minimum value recorded.
-    min: TypedValue,
+    min: Option<TypedValue>,
     /// The maximum value recorded.
-    max: TypedValue,
+    max: Option<TypedValue>,
     /// The number of samples recorded.
     count: u64,
+    /// The number of NaN samples recorded.
+    nan_count: u64,
+    /// The number of infinite samples recorded.
+    infinite_count: u64,
+    /// The first sample recorded.
+    first: TypedValue,
+    /// The last sample recorded.
+    last: TypedValue,
+    /// The mean and variance of the finite samples.
+    finite: RunningStats,
+    /// The estimate of the median.
+    median: P2Quantile,
+    /// The estimates of the percentiles, with the percentile they estimate.
+    percentiles: Vec<(f64, P2Quantile)>,
+}
+
+impl ValueStats {
+    /// Start the statistics of a value from its first sample.
+    fn new(first: TypedValue, percentiles: &[f64]) -> Self {
+        let mut stats = Self {
+            min: None,
+            max: None,
+            count: 0,
+            nan_count: 0,
+            infinite_count: 0,
+            first,
+            last: first,
+            finite: RunningStats::default(),
+            median: P2Quantile::new(0.5),
+            percentiles: percentiles
+                .iter()
+                .map(|&p| (p, P2Quantile::new(p / 100.0)))
+                .collect(),
+        };
+        stats.record(first);
+        stats
+    }
+
+    /// Add a sample to the statistics.
+    fn record(&mut self, value: TypedValue) {
+        self.count += 1;
+        self.last = value;
+
+        let number = value.to_f64();
+        if number.is_nan() {
+            self.nan_count += 1;
+            return;
+        }
+        if number.is_infinite() {
+            self.infinite_count += 1;
+            return;
+        }
+
+        // Neither of these can fail, as the value isn't NaN and every sample
+        // of a value has the same kind.
+        self.min = Some(
+            self.min
+                .map_or(value, |min| min.partial_min(&value).unwrap()),
+        );
+        self.max = Some(
+            self.max
+                .map_or(value, |max| max.partial_max(&value).unwrap()),
+        );
+
+        self.finite.add(number);
+        self.median.add(number);
+        for (_, quantile) in self.percentiles.iter_mut() {
+            quantile.add(number);
+        }
+    }
+
+    /// The minimum value recorded, or `None` if every sample was NaN or
+    /// infinite.
+    pub fn min(&self) -> Option<TypedValue> {
+        self.min
+    }
+
+    /// The maximum value recorded, or `None` if every sample was NaN or
+    /// infinite.
+    pub fn max(&self) -> Option<TypedValue> {
+        self.max
+    }
+
+    /// The number of samples recorded.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The number of NaN samples recorded.
+    pub fn nan_count(&self) -> u64 {
+        self.nan_count
+    }
+
+    /// The number of infinite samples recorded.
+    pub fn infinite_count(&self) -> u64 {
+        self.infinite_count
+    }
+
+    /// The first sample recorded.
+    pub fn first(&self) -> TypedValue {
+        self.first
+    }
+
+    /// The last sample recorded.
+    pub fn last(&self) -> TypedValue {
+        self.last
+    }
+
+    /// The mean of the finite samples.
+    pub fn mean(&self) -> Option<f64> {
+        self.finite.mean()
+    }
+
+    /// The sample standard deviation of the finite samples, or `None` if
+    /// there are fewer than two.
+    pub fn std_dev(&self) -> Option<f64> {
+        self.finite.std_dev()
+    }
+
+    /// The estimated median of the finite samples.
+    pub fn median(&self) -> Option<f64> {
+        self.median.estimate()
+    }
+
+    /// The estimated percentile of the finite samples.
+    ///
+    /// # Returns
+    ///
+    /// The estimate, or `None` if the percentile was not in the
+    /// [`ReportOptions`] or there were no finite samples.
+    pub fn percentile(&self, percentile: f64) -> Option<f64> {
+        self.percentiles
+            .iter()
+            .find(|(p, _)| *p == percentile)
+            .and_then(|(_, quantile)| quantile.estimate())
+    }
+
+    /// The estimates of every percentile in the [`ReportOptions`], with the
+    /// percentile they estimate.
+    pub fn percentiles(&self) -> impl Iterator<Item = (f64, Option<f64>)> + '_ {
+        self.percentiles
+            .iter()
+            .map(|(p, quantile)| (*p, quantile.estimate()))
+    }
 }
 
 /// Reported data about a sensor.
@@ -32,21 +215,67 @@ pub struct SensorReport {
     value_stats: HashMap<String, ValueStats>,
 }
 
+impl SensorReport {
+    /// Get the statistics of a value by its name, if it was recorded.
+    pub fn value_stats(&self, name: &str) -> Option<&ValueStats> {
+        self.value_stats.get(name)
+    }
+}
+
 // TODO: Calculation reports with a function based on variable names.
 pub struct Report {
     config: RocketConfig,
+    options: ReportOptions,
     sensor_reports: HashMap<u8, SensorReport>,
+    rates: RateAnalysis,
+    /// The events of the flight, or why they could not be detected.
+    events: Result<Vec<FlightEvent>, String>,
+    /// The altitude from the barometer, or why it could not be found.
+    altitude: Result<AltitudeEstimator, String>,
+    /// The orientation from the gyroscope, or why it could not be found.
+    orientation: Result<OrientationEstimator, String>,
 }
 
 impl Report {
-    pub fn new<R: Read>(config: RocketConfig, packet_parser: PacketParser<R>) -> Report {
-        let mut table_generator = TableGenerator::new(packet_parser, config.clone());
+    /// Create a report from the packets of a log.
+    ///
+    /// The statistics of values with filters in the config are of the
+    /// filtered values, the same as in CSV output.
+    pub fn new<I: SourceIterator>(config: RocketConfig, packets: I) -> Report {
+        Self::with_options(config, packets, ReportOptions::default())
+    }
+
+    /// Create a report with custom options.
+    pub fn with_options<I: SourceIterator>(
+        config: RocketConfig,
+        packets: I,
+        options: ReportOptions,
+    ) -> Report {
+        let mut rates = RateAnalysis::new(&config, options.rates.clone());
+        let mut event_detector = EventDetector::new(&config, options.events.clone());
+        let mut altitude = AltitudeEstimator::new(&config, options.altitude.clone());
+        let mut orientation = OrientationEstimator::new(&config, options.orientation.clone());
+        let packets = packets.inspect(|packet| {
+            if let Ok(packet) = packet {
+                rates.update(packet);
+                if let Ok(event_detector) = event_detector.as_mut() {
+                    event_detector.update(packet);
+                }
+                if let Ok(altitude) = altitude.as_mut() {
+                    altitude.update(packet);
+                }
+                if let Ok(orientation) = orientation.as_mut() {
+                    orientation.update(packet);
+                }
+            }
+        });
+        let table_generator = TableGenerator::new(packets, config.clone());
         let mut sensor_reports = HashMap::new();
 
         let column_names = table_generator.column_names();
-        let mut column_stats = HashMap::new();
+        let mut column_stats: HashMap<String, ValueStats> = HashMap::new();
 
-        while let Some(row) = table_generator.next() {
+        for row in table_generator {
             // TODO: Deal with errors.
             let row = row.unwrap();
 
@@ -55,18 +284,13 @@ impl Report {
                     continue;
                 };
 
-                let stats = column_stats
-                    .entry(column_name.clone())
-                    .or_insert_with(|| ValueStats {
-                        min: value.clone(),
-                        max: value.clone(),
-                        count: 0,
-                    });
-
-                // If any of these fails, it means that the data is invalid.
-                stats.min = stats.min.partial_min(value).unwrap();
-                stats.max = stats.max.partial_max(value).unwrap();
-                stats.count += 1;
+                match column_stats.get_mut(column_name) {
+                    Some(stats) => stats.record(*value),
+                    None => {
+                        let stats = ValueStats::new(*value, &options.percentiles);
+                        column_stats.insert(column_name.clone(), stats);
+                    }
+                }
             }
         }
 
@@ -86,57 +310,373 @@ impl Report {
 
         Report {
             config,
+            options,
             sensor_reports,
+            rates,
+            events: event_detector.map(|event_detector| event_detector.events()),
+            altitude,
+            orientation,
+        }
+    }
+
+    /// The events detected in the flight, or why they could not be detected.
+    pub fn events(&self) -> Result<&[FlightEvent], &str> {
+        match &self.events {
+            Ok(events) => Ok(events),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The altitude from the barometer, or why it could not be found.
+    pub fn altitude(&self) -> Result<&AltitudeEstimator, &str> {
+        match &self.altitude {
+            Ok(altitude) => Ok(altitude),
+            Err(e) => Err(e),
         }
     }
 
+    /// The orientation from the gyroscope, or why it could not be found.
+    pub fn orientation(&self) -> Result<&OrientationEstimator, &str> {
+        match &self.orientation {
+            Ok(orientation) => Ok(orientation),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The data rate of every sensor.
+    pub fn rates(&self) -> &RateAnalysis {
+        &self.rates
+    }
+
+    /// Get the report of a sensor by its ID.
+    pub fn sensor_report(&self, id: u8) -> Option<&SensorReport> {
+        self.sensor_reports.get(&id)
+    }
+
     pub fn write<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
         write!(writer, "{HEADER_CONTENT}")?;
 
-        let mut elements = vec![
+        let mut elements = vec![LatexElement::Section("Flight Timeline".to_string())];
+        elements.extend(self.timeline());
+        elements.extend([
+            LatexElement::Subsection("Altitude".to_string()),
+            LatexElement::Raw(self.altitude_description()),
+            LatexElement::Subsection("Orientation".to_string()),
+            LatexElement::Raw(self.orientation_description()),
+        ]);
+        elements.extend([
             LatexElement::Section("Sensor Data".to_string()),
             LatexElement::Raw(self.sensor_introduction()),
-        ];
+        ]);
 
         for sensor in self.config.sensors.iter() {
-            elements.push(LatexElement::Subsection(sensor.name.clone()));
+            elements.push(LatexElement::Subsection(escape(&sensor.name)));
             let value_list = sensor
                 .values
                 .iter()
-                .map(|v| v.name.as_str())
-                .collect::<Vec<&str>>()
+                .map(|v| escape(&v.name))
+                .collect::<Vec<String>>()
                 .join(", ");
             elements.push(LatexElement::raw(format!(
                 "The {} sensor has {} values: {}. ",
-                sensor.name,
+                escape(&sensor.name),
                 sensor.values.len(),
                 value_list
             )));
+            if let Some(rate) = self.rates.sensor(sensor.id) {
+                elements.push(LatexElement::raw(self.rate_description(rate)));
+            }
 
-            let Some(sensor_report) = self.sensor_reports.get(&sensor.id) else {
+            let sensor_report = self
+                .sensor_reports
+                .get(&sensor.id)
+                .filter(|sensor_report| !sensor_report.value_stats.is_empty());
+            let Some(sensor_report) = sensor_report else {
                 elements.push(LatexElement::raw("No data was recorded for this sensor. "));
                 continue;
             };
 
-            for (name, stats) in sensor_report.value_stats.iter() {
-                elements.push(LatexElement::raw(format!(
-                    "The {} value has {} samples. ",
-                    name, stats.count
-                )));
-                elements.push(LatexElement::raw(format!(
-                    "The minimum value is {}. ",
-                    stats.min
-                )));
-                elements.push(LatexElement::raw(format!(
-                    "The maximum value is {}. ",
-                    stats.max
-                )));
-            }
+            elements.push(self.stats_table(sensor, sensor_report));
         }
 
         LatexElement::environment("document", elements).write(writer)
     }
 
+    /// A table of the statistics of every value of a sensor, with a row for
+    /// each statistic and a column for each value.
+    fn stats_table(&self, sensor: &SensorConfig, sensor_report: &SensorReport) -> LatexElement {
+        let stats: Vec<Option<&ValueStats>> = sensor
+            .values
+            .iter()
+            .map(|value| sensor_report.value_stats(&value.name))
+            .collect();
+
+        let row = |label: &str, cell: &dyn Fn(&ValueStats) -> String| {
+            let cells: Vec<String> = stats
+                .iter()
+                .map(|stats| stats.map_or_else(|| "--".to_string(), cell))
+                .collect();
+            LatexElement::raw(format!("{label} & {} \\\\", cells.join(" & ")))
+        };
+
+        let header: Vec<String> = sensor
+            .values
+            .iter()
+            .map(|value| match &value.unit {
+                Some(unit) => format!("{} ({})", escape(&value.name), escape(unit)),
+                None => escape(&value.name),
+            })
+            .collect();
+
+        let mut rows = vec![
+            LatexElement::directive("hline", vec![], vec![]),
+            LatexElement::raw(format!("Statistic & {} \\\\", header.join(" & "))),
+            LatexElement::directive("hline", vec![], vec![]),
+            row("Samples", &|s| s.count().to_string()),
+            row("NaN", &|s| s.nan_count().to_string()),
+            row("Infinite", &|s| s.infinite_count().to_string()),
+            row("Mean", &|s| format_number(s.mean())),
+            row("Std. dev.", &|s| format_number(s.std_dev())),
+            row("Minimum", &|s| format_value(s.min())),
+        ];
+
+        // The 50th percentile is the median, which always has a row.
+        let (lower, upper): (Vec<f64>, Vec<f64>) = self
+            .options
+            .percentiles
+            .iter()
+            .filter(|&&p| p != 50.0)
+            .partition(|&&p| p < 50.0);
+        let percentile_row = |p: f64| {
+            row(&format!("P{p}"), &|s: &ValueStats| {
+                format_number(s.percentile(p))
+            })
+        };
+
+        rows.extend(lower.into_iter().map(percentile_row));
+        rows.push(row("Median", &|s| format_number(s.median())));
+        rows.extend(upper.into_iter().map(percentile_row));
+        rows.extend([
+            row("Maximum", &|s| format_value(s.max())),
+            row("First", &|s| format_value(Some(s.first()))),
+            row("Last", &|s| format_value(Some(s.last()))),
+            LatexElement::directive("hline", vec![], vec![]),
+        ]);
+
+        let columns = format!("l{}", "r".repeat(sensor.values.len()));
+        LatexElement::environment(
+            "table",
+            vec![
+                LatexElement::directive("centering", vec![], vec![]),
+                LatexElement::environment_with_args("tabular", vec![columns], rows),
+                LatexElement::directive(
+                    "caption",
+                    vec![],
+                    vec![format!(
+                        "Statistics of the {} sensor.",
+                        escape(&sensor.name)
+                    )],
+                ),
+            ],
+        )
+    }
+
+    /// The events of the flight as a table, or why there are none.
+    fn timeline(&self) -> Vec<LatexElement> {
+        let events = match &self.events {
+            Ok(events) if events.is_empty() => {
+                return vec![LatexElement::raw("No flight events were detected. ")];
+            }
+            Ok(events) => events,
+            Err(e) => {
+                let reason = escape(e);
+                return vec![LatexElement::raw(format!(
+                    "Flight events could not be detected: {reason}. "
+                ))];
+            }
+        };
+
+        let mut rows = vec![
+            LatexElement::directive("hline", vec![], vec![]),
+            LatexElement::raw("Event & Time (s) & Confidence \\\\"),
+            LatexElement::directive("hline", vec![], vec![]),
+        ];
+        rows.extend(events.iter().map(|event| {
+            LatexElement::raw(format!(
+                "{} & {:.3} & {:.0}\\% \\\\",
+                event.kind,
+                event.time,
+                event.confidence * 100.0
+            ))
+        }));
+        rows.push(LatexElement::directive("hline", vec![], vec![]));
+
+        vec![
+            LatexElement::raw(
+                "The events of the flight were detected from the acceleration and pressure. \
+                 The confidence is the share of the sensors able to see an event that saw it. ",
+            ),
+            LatexElement::environment(
+                "table",
+                vec![
+                    LatexElement::directive("centering", vec![], vec![]),
+                    LatexElement::environment_with_args("tabular", vec!["lrr".to_string()], rows),
+                    LatexElement::directive(
+                        "caption",
+                        vec![],
+                        vec!["Events of the flight.".to_string()],
+                    ),
+                ],
+            ),
+        ]
+    }
+
+    /// Sentences describing the apogee and fastest climb from the barometer.
+    fn altitude_description(&self) -> String {
+        let altitude = match &self.altitude {
+            Ok(altitude) => altitude,
+            Err(e) => {
+                let reason = escape(e);
+                return format!("The altitude could not be found: {reason}. ");
+            }
+        };
+
+        let mut description = String::new();
+        if let Some(pressure) = altitude.ground_pressure() {
+            description.push_str(&format!(
+                "The altitude is measured from a ground pressure of {:.2} hPa",
+                pressure / 100.0
+            ));
+            if let Some(temperature) = altitude.ground_temperature() {
+                description.push_str(&format!(" at {:.1} C", temperature - 273.15));
+            }
+            description.push_str(". ");
+        }
+
+        let (Some(apogee), Some(max_velocity)) = (altitude.apogee(), altitude.max_velocity())
+        else {
+            description.push_str("The barometer did not show a launch. ");
+            return description;
+        };
+
+        description.push_str(&format!(
+            "The apogee was {:.1} m above the ground at {:.3} s. \
+             The highest vertical velocity was {:.1} m/s at {:.3} s. ",
+            apogee.altitude, apogee.time, max_velocity.vertical_velocity, max_velocity.time
+        ));
+        description
+    }
+
+    /// Sentences describing the largest tilt from vertical and any tumble.
+    fn orientation_description(&self) -> String {
+        let orientation = match &self.orientation {
+            Ok(orientation) => orientation,
+            Err(e) => {
+                let reason = escape(e);
+                return format!("The orientation could not be estimated: {reason}. ");
+            }
+        };
+
+        let Some(max_tilt) = orientation.max_tilt() else {
+            return "No orientation was estimated, as there was no gyroscope reading after an \
+                    acceleration reading of about 1 g. "
+                .to_string();
+        };
+
+        let mut description = format!(
+            "The orientation was estimated from the gyroscope and accelerometer. \
+             The largest tilt from vertical was {:.1}$^\\circ$ at {:.3} s. ",
+            max_tilt.tilt, max_tilt.time
+        );
+
+        let tumble_tilt = orientation.options().tumble_tilt;
+        match orientation.tumble() {
+            Some(tumble) => {
+                description.push_str(&format!(
+                    "The rocket tumbled, passing {tumble_tilt:.0}$^\\circ$ from vertical at \
+                     {:.3} s",
+                    tumble.time
+                ));
+                let apogee = self
+                    .events()
+                    .ok()
+                    .and_then(|events| events.iter().find(|event| event.kind == EventKind::Apogee));
+                if apogee.is_some_and(|apogee| tumble.time < apogee.time) {
+                    description.push_str(", before apogee");
+                }
+                description.push_str(". ");
+            }
+            None => description.push_str(&format!(
+                "The tilt stayed below {tumble_tilt:.0}$^\\circ$, so the rocket did not tumble. "
+            )),
+        }
+
+        description
+    }
+
+    /// Sentences describing the data rate of a sensor and its dropouts.
+    fn rate_description(&self, rate: &SensorRate) -> String {
+        let time_base = self.rates.time_base();
+        let (period_unit, rate_unit) = (time_base.period_unit(), time_base.rate_unit());
+
+        let mut description = format!("It logged {} packets", rate.count());
+        match (rate.duration(), time_base) {
+            (Some(duration), TimeBase::Seconds) => {
+                description.push_str(&format!(" over {duration:.3} {period_unit}"));
+            }
+            (Some(duration), TimeBase::Packets) => {
+                description.push_str(&format!(" over {duration:.0} packet intervals"));
+            }
+            (None, _) => {}
+        }
+        let Some(average) = rate.average_rate() else {
+            description.push_str(". ");
+            return description;
+        };
+        description.push_str(&format!(", an average of {average:.3} {rate_unit}"));
+
+        if let (Some(min), Some(max)) = (rate.min_rate(), rate.max_rate()) {
+            description.push_str(&format!(" (between {min:.3} and {max:.3} {rate_unit})"));
+        }
+        description.push_str(". ");
+
+        if let (Some(period), Some(jitter)) = (rate.nominal_period(), rate.jitter()) {
+            description.push_str(&format!(
+                "The nominal period is {period:.4} {period_unit} with a jitter of {jitter:.4} {period_unit}. "
+            ));
+        }
+
+        let gaps: Vec<&Gap> = rate.gaps().collect();
+        let gap_factor = self.rates.options().gap_factor;
+        match gaps.len() {
+            0 => description.push_str(&format!(
+                "There were no dropouts longer than {gap_factor} times the nominal period. "
+            )),
+            count => {
+                let list = gaps
+                    .iter()
+                    .take(MAX_LISTED_GAPS)
+                    .map(|gap| {
+                        format!(
+                            "{:.3} {period_unit} at {:.3} {period_unit}",
+                            gap.duration, gap.start
+                        )
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                let more = match count > MAX_LISTED_GAPS {
+                    true => format!(" and {} more", count - MAX_LISTED_GAPS),
+                    false => String::new(),
+                };
+                description.push_str(&format!(
+                    "There were {count} dropouts longer than {gap_factor} times the nominal period: {list}{more}. "
+                ));
+            }
+        }
+
+        description
+    }
+
     fn sensor_introduction(&self) -> String {
         let rocket_name = self.config.display_name();
         let sensor_count = self.config.sensors.len();
@@ -144,10 +684,167 @@ impl Report {
             .config
             .sensors
             .iter()
-            .map(|s| s.name.as_str())
-            .collect::<Vec<&str>>()
+            .map(|s| escape(&s.name))
+            .collect::<Vec<String>>()
             .join(", ");
-        format!("The {rocket_name} rocket has {sensor_count} sensors: {sensor_list}.")
-        // TODO: Average data rate and things like that.
+        let rocket_name = escape(rocket_name);
+        let mut introduction =
+            format!("The {rocket_name} rocket has {sensor_count} sensors: {sensor_list}. ");
+
+        if self.rates.time_base() == TimeBase::Packets {
+            introduction.push_str(
+                "No value has the time role, so data rates are given per packet in the log. ",
+            );
+        }
+
+        introduction
+    }
+}
+
+/// Format a statistic for a table, with a dash if it is missing.
+fn format_number(number: Option<f64>) -> String {
+    match number {
+        Some(number) => format!("{number:.4}"),
+        None => "--".to_string(),
+    }
+}
+
+/// Format a recorded value for a table, with a dash if it is missing.
+fn format_value(value: Option<TypedValue>) -> String {
+    match value {
+        Some(value) => escape(&value.to_string()),
+        None => "--".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::{Axis, Endianess, ValueConfig, ValueKind};
+    use crate::data::{Packet, Value};
+
+    use super::*;
+
+    fn test_config() -> RocketConfig {
+        RocketConfig {
+            name: "test".to_string(),
+            endianess: Endianess::default(),
+            nose_axis: Axis::default(),
+            include: vec![],
+            display_name: None,
+            description: None,
+            kalman: None,
+            sensors: vec![SensorConfig {
+                id: 0,
+                name: "BMP_280".to_string(),
+                values: vec![ValueConfig {
+                    name: "pressure".to_string(),
+                    data_type: ValueKind::Float32,
+                    unit: Some("hPa".to_string()),
+                    role: None,
+                    filters: vec![],
+                }],
+            }],
+        }
+    }
+
+    fn test_report(values: &[f32], options: ReportOptions) -> Report {
+        let packets: Vec<Packet> = values
+            .iter()
+            .map(|&float_32| Packet {
+                id: 0,
+                values: vec![Value { float_32 }],
+            })
+            .collect();
+
+        Report::with_options(test_config(), packets.into_iter().map(Ok), options)
+    }
+
+    #[test]
+    fn test_value_stats() {
+        let report = test_report(
+            &[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0],
+            Default::default(),
+        );
+        let stats = report
+            .sensor_report(0)
+            .unwrap()
+            .value_stats("pressure")
+            .unwrap();
+
+        assert_eq!(stats.count(), 8);
+        assert_eq!(stats.min(), Some(2.0.into()));
+        assert_eq!(stats.max(), Some(9.0.into()));
+        assert_eq!(stats.first(), 2.0.into());
+        assert_eq!(stats.last(), 9.0.into());
+        assert_eq!(stats.mean(), Some(5.0));
+        assert!((stats.std_dev().unwrap() - (32.0f64 / 7.0).sqrt()).abs() < 1e-12);
+        assert!(stats.median().is_some());
+        assert_eq!(stats.percentiles().count(), 4);
+        assert!(stats.percentile(1.0).unwrap() <= stats.percentile(99.0).unwrap());
+        assert_eq!(stats.percentile(50.0), None);
+    }
+
+    #[test]
+    fn test_value_stats_non_finite() {
+        let options = ReportOptions {
+            percentiles: vec![50.0],
+            ..Default::default()
+        };
+        let report = test_report(&[f32::NAN, 1.0, f32::INFINITY, 3.0, f32::NAN], options);
+        let stats = report
+            .sensor_report(0)
+            .unwrap()
+            .value_stats("pressure")
+            .unwrap();
+
+        assert_eq!(stats.count(), 5);
+        assert_eq!(stats.nan_count(), 2);
+        assert_eq!(stats.infinite_count(), 1);
+        assert_eq!(stats.min(), Some(1.0.into()));
+        assert_eq!(stats.max(), Some(3.0.into()));
+        assert_eq!(stats.mean(), Some(2.0));
+        assert_eq!(stats.median(), Some(2.0));
+        assert_eq!(stats.percentile(50.0), Some(2.0));
+        assert!(stats.first().to_f64().is_nan());
+    }
+
+    #[test]
+    fn test_write_stats_table() {
+        let report = test_report(&[1.0, 2.0, 3.0], Default::default());
+
+        let mut result = vec![];
+        report.write(&mut result).unwrap();
+        let result = String::from_utf8(result).unwrap();
+
+        assert!(result.contains(r"\subsection{BMP\_280}"));
+        assert!(result.contains(r"\begin{tabular}{lr}"));
+        assert!(result.contains(r"Statistic & pressure (hPa) \\"));
+        assert!(result.contains(r"Mean & 2.0000 \\"));
+        assert!(result.contains(r"P5 & "));
+        assert!(result.contains(r"Median & 2.0000 \\"));
+        assert!(result.contains(r"\caption{Statistics of the BMP\_280 sensor.}"));
+        assert!(result.contains(
+            "It logged 3 packets over 2 packet intervals, an average of 1.000 per packet in the log"
+        ));
+        assert!(result.contains("no dropouts longer than 3 times"));
+        assert!(result.contains(r"\section{Flight Timeline}"));
+        assert!(result.contains("Flight events could not be detected: No value has the time role."));
+    }
+
+    #[test]
+    fn test_write_stats_table_median_percentile() {
+        let options = ReportOptions {
+            percentiles: vec![50.0, 90.0],
+            ..Default::default()
+        };
+        let report = test_report(&[1.0, 2.0, 3.0], options);
+
+        let mut result = vec![];
+        report.write(&mut result).unwrap();
+        let result = String::from_utf8(result).unwrap();
+
+        assert!(!result.contains("P50 & "));
+        assert_eq!(result.matches("Median & ").count(), 1);
+        assert!(result.contains("P90 & "));
     }
 }
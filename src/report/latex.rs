@@ -5,6 +5,7 @@ use std::str::FromStr;
 pub enum LatexElement {
     Environment {
         name: String,
+        args: Vec<String>,
         elements: Vec<LatexElement>,
     },
     Directive {
@@ -41,8 +42,16 @@ impl LatexElement {
                 }
                 write!(writer, " ")
             }
-            Self::Environment { name, elements } => {
-                write!(writer, "\\begin{{{name}}} ")?;
+            Self::Environment {
+                name,
+                args,
+                elements,
+            } => {
+                write!(writer, "\\begin{{{name}}}")?;
+                for arg in args {
+                    write!(writer, "{{{arg}}}")?;
+                }
+                write!(writer, " ")?;
                 for element in elements {
                     element.write(writer)?;
                 }
@@ -76,13 +85,43 @@ impl LatexElement {
 
     #[inline]
     pub fn environment<S: ToString>(name: S, contents: Vec<LatexElement>) -> Self {
+        Self::environment_with_args(name, vec![], contents)
+    }
+
+    #[inline]
+    pub fn environment_with_args<S: ToString>(
+        name: S,
+        args: Vec<String>,
+        contents: Vec<LatexElement>,
+    ) -> Self {
         Self::Environment {
             name: name.to_string(),
+            args,
             elements: contents,
         }
     }
 }
 
+/// Escape the characters in text that have a special meaning in LaTeX.
+pub fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                result.push('\\');
+                result.push(c);
+            }
+            '~' => result.push_str(r"\textasciitilde{}"),
+            '^' => result.push_str(r"\textasciicircum{}"),
+            '\\' => result.push_str(r"\textbackslash{}"),
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -141,4 +180,19 @@ mod tests {
         env.write(&mut result).unwrap();
         assert_eq!(result, br"\begin{center}  some text \end{center} ");
     }
+
+    #[test]
+    fn test_latex_environment_with_args() {
+        let mut result: Vec<u8> = vec![];
+        let env = LatexElement::environment_with_args("tabular", vec!["lr".to_string()], vec![]);
+        env.write(&mut result).unwrap();
+        assert_eq!(result, br"\begin{tabular}{lr} \end{tabular} ");
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("accel_x"), r"accel\_x");
+        assert_eq!(escape("50% & $5"), r"50\% \& \$5");
+        assert_eq!(escape(r"a\b^c"), r"a\textbackslash{}b\textasciicircum{}c");
+    }
 }
@@ -1,24 +1,109 @@
 use std::collections::HashMap;
 
-use crate::configuration::{RocketConfig, SensorConfig, ValueConfig};
-use crate::data::{Packet, PacketError, TypedValue};
+use self::assembly::{RepeatedSensor, RowAssembly};
+use crate::analysis::altitude::AltitudeEstimator;
+use crate::analysis::filter::{Filter, FilterChain};
+use crate::analysis::kalman::KalmanFilter;
+use crate::analysis::orientation::OrientationEstimator;
+use crate::configuration::{RocketConfig, SensorConfig, ValueConfig, ValueKind};
+use crate::data::{Packet, PacketError, TypedValue, Value};
+
+pub mod assembly;
+pub mod resample;
 
 pub trait SourceIterator: Iterator<Item = Result<Packet, PacketError>> {}
 impl<I: Iterator<Item = Result<Packet, PacketError>>> SourceIterator for I {}
 
+/// An iterator of table rows that always have the same columns.
+pub trait RowSource: Iterator<Item = Result<Vec<Option<TypedValue>>, PacketError>> {
+    /// The names of the columns, in the order of the values in each row.
+    fn column_names(&self) -> Vec<String>;
+}
+
 #[cfg(test)]
 mod tests;
 
+/// The name of the column of the altitude above the ground added by
+/// [`TableGenerator::add_altitude`].
+pub const ALTITUDE_COLUMN: &str = "altitude_agl";
+
+/// The name of the column of the vertical velocity added by
+/// [`TableGenerator::add_altitude`].
+pub const VERTICAL_VELOCITY_COLUMN: &str = "vertical_velocity";
+
+/// The names of the columns of the altitude, vertical velocity and vertical
+/// acceleration added by [`TableGenerator::add_kalman`].
+pub const KALMAN_COLUMNS: [&str; 3] = ["kalman_altitude", "kalman_velocity", "kalman_acceleration"];
+
+/// The names of the columns of the orientation quaternion, the roll, pitch
+/// and yaw in degrees, and the tilt from vertical in degrees added by
+/// [`TableGenerator::add_orientation`].
+pub const ORIENTATION_COLUMNS: [&str; 8] = [
+    "quaternion_w",
+    "quaternion_x",
+    "quaternion_y",
+    "quaternion_z",
+    "roll",
+    "pitch",
+    "yaw",
+    "tilt",
+];
+
+/// The suffix of the names of the columns added by
+/// [`TableGenerator::add_updated_columns`].
+pub const UPDATED_SUFFIX: &str = "_updated";
+
+/// What is written in a cell when its column was not sampled in a row.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FillPolicy {
+    /// The cell is empty.
+    #[default]
+    None,
+    /// The last value of the column is repeated.
+    ForwardFill,
+    /// The last value of the column is repeated if it was sampled at most
+    /// this many rows before, and the cell is empty otherwise.
+    ForwardFillMaxAge(u64),
+}
+
+impl FillPolicy {
+    /// Whether a value sampled this many rows before is repeated.
+    pub fn fills(&self, age: u64) -> bool {
+        match self {
+            Self::None => false,
+            Self::ForwardFill => true,
+            Self::ForwardFillMaxAge(max_age) => age <= *max_age,
+        }
+    }
+}
+
 /// Iterator that generates table rows from data provided.
 ///
 /// The resulting rows will always have the values in the same order and the
 /// order of column names can be retrieved from the
-/// [`TableGenerator::column_names`].
+/// [`TableGenerator::column_names`]. Values with
+/// [`filters`](crate::configuration::ValueConfig::filters) are filtered and
+/// given as 64 bit floats. Which packets are combined into a row is decided by
+/// a [`RowAssembly`], by default [`RepeatedSensor`]. Cells of columns that
+/// were not sampled in a row are empty unless a [`FillPolicy`] is set.
 pub struct TableGenerator<I: SourceIterator> {
     iter: I,
     config: RocketConfig,
     packet_buf: Vec<Packet>,
     columns: Vec<String>,
+    altitude: Option<AltitudeEstimator>,
+    kalman: Option<KalmanFilter>,
+    orientation: Option<OrientationEstimator>,
+    /// The filters of the values that have any, by column name.
+    filters: HashMap<String, FilterChain>,
+    assembly: Box<dyn RowAssembly>,
+    fill: FillPolicy,
+    updated_columns: bool,
+    /// The last value of each column and the row it was sampled in, when
+    /// values are filled.
+    last_values: HashMap<String, (TypedValue, u64)>,
+    /// The number of rows generated.
+    row: u64,
 }
 
 impl<I: SourceIterator> TableGenerator<I> {
@@ -29,22 +114,93 @@ impl<I: SourceIterator> TableGenerator<I> {
     ///
     /// * `iter` - Iterator that yields packets.
     /// * `config` - Rocket configuration, must be the same as the one used to
-    ///              generate the packets.
+    ///   generate the packets.
     ///
     /// # Returns
     ///
     /// A new table generator.
     pub fn new(iter: I, config: RocketConfig) -> Self {
         let columns = Self::columns(&config);
+        let filters = config
+            .sensors
+            .iter()
+            .flat_map(|sensor| sensor.values.iter().map(move |value| (sensor, value)))
+            .filter(|(_, value)| !value.filters.is_empty())
+            .map(|(sensor, value)| {
+                let chain = FilterChain::new(&value.filters);
+                (Self::column_name(sensor, value), chain)
+            })
+            .collect();
 
         Self {
             iter,
             config,
             columns,
             packet_buf: vec![],
+            altitude: None,
+            kalman: None,
+            orientation: None,
+            filters,
+            assembly: Box::new(RepeatedSensor::default()),
+            fill: FillPolicy::default(),
+            updated_columns: false,
+            last_values: HashMap::new(),
+            row: 0,
         }
     }
 
+    /// Set how packets are combined into rows.
+    pub fn set_assembly(&mut self, assembly: impl RowAssembly + 'static) {
+        self.assembly = Box::new(assembly);
+    }
+
+    /// Set what is written in the cells of columns that were not sampled in
+    /// a row.
+    pub fn set_fill_policy(&mut self, fill: FillPolicy) {
+        self.fill = fill;
+    }
+
+    /// Add a column after all the others for each column, named with the
+    /// [`UPDATED_SUFFIX`], that is 1 when the column was sampled in the row
+    /// and 0 when it is empty or filled.
+    pub fn add_updated_columns(&mut self) {
+        self.updated_columns = true;
+    }
+
+    /// Add columns with the altitude above the ground and the vertical
+    /// velocity derived from the barometer, named [`ALTITUDE_COLUMN`] and
+    /// [`VERTICAL_VELOCITY_COLUMN`].
+    ///
+    /// The columns have a value in every row with a pressure reading, once a
+    /// time has been read.
+    pub fn add_altitude(&mut self, estimator: AltitudeEstimator) {
+        self.columns.push(ALTITUDE_COLUMN.to_string());
+        self.columns.push(VERTICAL_VELOCITY_COLUMN.to_string());
+        self.altitude = Some(estimator);
+    }
+
+    /// Add columns with the estimates of a Kalman filter, named
+    /// [`KALMAN_COLUMNS`].
+    ///
+    /// The columns have a value in every row with a pressure or acceleration
+    /// reading, once a time has been read.
+    pub fn add_kalman(&mut self, filter: KalmanFilter) {
+        self.columns
+            .extend(KALMAN_COLUMNS.iter().map(|name| name.to_string()));
+        self.kalman = Some(filter);
+    }
+
+    /// Add columns with the orientation of the rocket, named
+    /// [`ORIENTATION_COLUMNS`].
+    ///
+    /// The columns have a value in every row with a gyroscope reading, once a
+    /// time and the starting orientation are known.
+    pub fn add_orientation(&mut self, estimator: OrientationEstimator) {
+        self.columns
+            .extend(ORIENTATION_COLUMNS.iter().map(|name| name.to_string()));
+        self.orientation = Some(estimator);
+    }
+
     /// Allow a custom subset of the columns to be generated.
     ///
     /// The resulting columns will be in the same order as the given columns,
@@ -97,8 +253,19 @@ impl<I: SourceIterator> TableGenerator<I> {
 
     /// An instance method of the [`TableGenerator::columns`] method to get the
     /// column names that are generated upon construction.
+    ///
+    /// This includes the columns added by
+    /// [`TableGenerator::add_updated_columns`].
     pub fn column_names(&self) -> Vec<String> {
-        self.columns.clone()
+        let mut names = self.columns.clone();
+        if self.updated_columns {
+            let updated = self
+                .columns
+                .iter()
+                .map(|name| format!("{name}{UPDATED_SUFFIX}"));
+            names.extend(updated);
+        }
+        names
     }
 
     /// Get the next packet from the internal buffer or the source iterator if
@@ -153,20 +320,48 @@ impl<I: SourceIterator> Iterator for TableGenerator<I> {
                 }));
             }
 
-            // If this packet has already been added to the current row, we
-            // push the packet into a buffer and then end the row.
-            for spec in sensor.values.iter() {
-                if current_row.contains_key(&Self::column_name(sensor, spec)) {
-                    self.packet_buf.push(packet);
-                    break 'packet_loop;
-                }
+            // If the row ends before this packet, we push the packet into a
+            // buffer so it starts the next row.
+            if self.assembly.ends_row(&packet, sensor, &current_row) {
+                self.packet_buf.push(packet);
+                break 'packet_loop;
             }
 
             // Push all the values into the hashmap with the column names.
+            // Values with filters are replaced with the filtered value.
             for (spec, value) in sensor.values.iter().zip(packet.values.iter()) {
                 let typed_value = unsafe { TypedValue::new(*value, &spec.data_type) };
+                let column_name = Self::column_name(sensor, spec);
 
-                current_row.insert(Self::column_name(sensor, spec), typed_value);
+                let typed_value = match self.filters.get_mut(&column_name) {
+                    Some(chain) => float_value(chain.apply(typed_value.to_f64())),
+                    None => typed_value,
+                };
+                current_row.insert(column_name, typed_value);
+            }
+
+            if let Some(sample) = self.altitude.as_mut().and_then(|a| a.update(&packet)) {
+                current_row.insert(ALTITUDE_COLUMN.to_string(), float_value(sample.altitude));
+                current_row.insert(
+                    VERTICAL_VELOCITY_COLUMN.to_string(),
+                    float_value(sample.vertical_velocity),
+                );
+            }
+
+            if let Some(state) = self.kalman.as_mut().and_then(|k| k.update(&packet)) {
+                let values = [state.altitude, state.velocity, state.acceleration];
+                for (name, value) in KALMAN_COLUMNS.iter().zip(values) {
+                    current_row.insert(name.to_string(), float_value(value));
+                }
+            }
+
+            if let Some(sample) = self.orientation.as_mut().and_then(|o| o.update(&packet)) {
+                let q = sample.quaternion;
+                let [roll, pitch, yaw] = sample.euler_angles;
+                let values = [q.w, q.x, q.y, q.z, roll, pitch, yaw, sample.tilt];
+                for (name, value) in ORIENTATION_COLUMNS.iter().zip(values) {
+                    current_row.insert(name.to_string(), float_value(value));
+                }
             }
         }
 
@@ -175,16 +370,56 @@ impl<I: SourceIterator> Iterator for TableGenerator<I> {
             return None;
         }
 
-        // Combine all the values into a vector instead of a hashmap.
+        let row = self.row;
+        self.row += 1;
+
+        // Combine all the values into a vector instead of a hashmap, filling
+        // the columns that were not sampled.
         let mut result = vec![];
+        let mut updated = vec![];
 
         for column in &self.columns {
             match current_row.get(column) {
-                Some(value) => result.push(Some(*value)),
-                None => result.push(None),
+                Some(value) => {
+                    if self.fill != FillPolicy::None {
+                        self.last_values.insert(column.clone(), (*value, row));
+                    }
+                    result.push(Some(*value));
+                    updated.push(1);
+                }
+                None => {
+                    let filled = self
+                        .last_values
+                        .get(column)
+                        .filter(|(_, sampled)| self.fill.fills(row - sampled))
+                        .map(|(value, _)| *value);
+                    result.push(filled);
+                    updated.push(0);
+                }
             }
         }
 
+        if self.updated_columns {
+            // The safety is assumed by the value being read as the kind it is
+            // written as.
+            let flags = updated.into_iter().map(|flag| {
+                Some(unsafe { TypedValue::new(Value { uint_8: flag }, &ValueKind::UInt8) })
+            });
+            result.extend(flags);
+        }
+
         Some(Ok(result))
     }
 }
+
+impl<I: SourceIterator> RowSource for TableGenerator<I> {
+    fn column_names(&self) -> Vec<String> {
+        TableGenerator::column_names(self)
+    }
+}
+
+/// A derived value, stored as a 64 bit float.
+pub(crate) fn float_value(number: f64) -> TypedValue {
+    // The value is read as the kind it is written as.
+    unsafe { TypedValue::new(Value { float_64: number }, &ValueKind::Float64) }
+}
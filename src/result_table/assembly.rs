@@ -0,0 +1,279 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::configuration::{RocketConfig, SensorConfig};
+use crate::data::{Packet, TypedValue};
+use crate::time::PacketClock;
+
+/// Decides which packets are combined into each row of a
+/// [`TableGenerator`](super::TableGenerator).
+///
+/// The generator asks before adding every packet to the current row. When the
+/// row ends, the packet is kept and given again as the first packet of the
+/// next row, with an empty row. A packet whose sensor is already in the row
+/// replaces its values.
+pub trait RowAssembly {
+    /// Whether the current row ends before the packet is added to it.
+    ///
+    /// # Params
+    ///
+    /// * `packet` - The next packet.
+    /// * `sensor` - The configuration of the sensor of the packet.
+    /// * `row` - The values in the current row, by column name.
+    fn ends_row(
+        &mut self,
+        packet: &Packet,
+        sensor: &SensorConfig,
+        row: &HashMap<String, TypedValue>,
+    ) -> bool;
+}
+
+/// Rows end when a sensor that is already in the row has another packet.
+///
+/// This is the default, and gives rows with the most recent packet of each
+/// sensor. A fast sensor ends rows often, so its rows are mostly empty.
+#[derive(Debug, Clone, Default)]
+pub struct RepeatedSensor {
+    /// The IDs of the sensors in the current row.
+    sensors: HashSet<u8>,
+}
+
+impl RowAssembly for RepeatedSensor {
+    fn ends_row(
+        &mut self,
+        packet: &Packet,
+        sensor: &SensorConfig,
+        row: &HashMap<String, TypedValue>,
+    ) -> bool {
+        if row.is_empty() {
+            self.sensors.clear();
+        }
+
+        // Sensors without values add nothing to the row.
+        !sensor.values.is_empty() && !self.sensors.insert(packet.id)
+    }
+}
+
+/// Every packet is a row of its own.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RowPerPacket;
+
+impl RowAssembly for RowPerPacket {
+    fn ends_row(
+        &mut self,
+        _: &Packet,
+        _: &SensorConfig,
+        row: &HashMap<String, TypedValue>,
+    ) -> bool {
+        !row.is_empty()
+    }
+}
+
+/// Rows end when a designated sensor has another packet.
+///
+/// Each row has one packet of the primary sensor and the most recent packets
+/// of the other sensors logged before the next one, so there is a row for
+/// every sample of the primary sensor.
+#[derive(Debug, Clone)]
+pub struct PrimarySensor {
+    id: u8,
+    /// Whether the primary sensor is in the current row.
+    seen: bool,
+}
+
+impl PrimarySensor {
+    /// Create an assembly with a row for each packet of the sensor with the
+    /// given ID.
+    pub fn new(id: u8) -> Self {
+        Self { id, seen: false }
+    }
+}
+
+impl RowAssembly for PrimarySensor {
+    fn ends_row(
+        &mut self,
+        packet: &Packet,
+        _: &SensorConfig,
+        row: &HashMap<String, TypedValue>,
+    ) -> bool {
+        if row.is_empty() {
+            self.seen = false;
+        }
+        if packet.id != self.id {
+            return false;
+        }
+
+        std::mem::replace(&mut self.seen, true)
+    }
+}
+
+/// Rows end when a packet is in a different window of time.
+///
+/// Each row has the most recent packet of each sensor within a window. The
+/// windows are aligned to multiples of their duration, and windows without
+/// packets have no row. Packets before the first time is read are in a row of
+/// their own.
+pub struct TimeWindow {
+    clock: PacketClock,
+    /// The duration of each window in seconds.
+    duration: f64,
+    /// The index of the window of the current row.
+    window: Option<i64>,
+}
+
+impl TimeWindow {
+    /// Create an assembly with windows of the given duration in seconds.
+    ///
+    /// # Errors
+    ///
+    /// If the config has no time values or the duration is not positive.
+    pub fn new(config: &RocketConfig, duration: f64) -> Result<Self, String> {
+        let clock = PacketClock::new(config);
+        if !clock.has_source() {
+            return Err("The config has no values with the time role".to_string());
+        }
+        if !duration.is_finite() || duration <= 0.0 {
+            return Err(format!("The window must be positive: {duration}"));
+        }
+
+        Ok(Self {
+            clock,
+            duration,
+            window: None,
+        })
+    }
+}
+
+impl RowAssembly for TimeWindow {
+    fn ends_row(
+        &mut self,
+        packet: &Packet,
+        _: &SensorConfig,
+        row: &HashMap<String, TypedValue>,
+    ) -> bool {
+        // A packet given again updates the clock to the same time.
+        let time = self.clock.update(packet);
+        let window = time.map(|time| (time / self.duration).floor() as i64);
+
+        if row.is_empty() {
+            self.window = window;
+            return false;
+        }
+
+        window != self.window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::{Axis, Endianess, ValueConfig, ValueKind, ValueRole};
+    use crate::data::Value;
+    use crate::result_table::TableGenerator;
+
+    use super::*;
+
+    /// A clock sensor and a fast sensor with three packets between ticks.
+    fn test_data() -> (RocketConfig, Vec<Packet>) {
+        let value = |name: &str, role: Option<ValueRole>| ValueConfig {
+            name: name.to_string(),
+            data_type: ValueKind::UInt32,
+            unit: role.map(|_| "ms".to_string()),
+            role,
+            filters: vec![],
+        };
+        let config = RocketConfig {
+            name: "test".to_string(),
+            endianess: Endianess::default(),
+            nose_axis: Axis::default(),
+            include: vec![],
+            display_name: None,
+            description: None,
+            kalman: None,
+            sensors: vec![
+                SensorConfig {
+                    id: 0,
+                    name: "clock".to_string(),
+                    values: vec![value("time", Some(ValueRole::Time))],
+                },
+                SensorConfig {
+                    id: 1,
+                    name: "fast".to_string(),
+                    values: vec![value("count", None)],
+                },
+            ],
+        };
+
+        let mut packets = vec![];
+        for tick in 0..3u32 {
+            packets.push(Packet {
+                id: 0,
+                values: vec![Value {
+                    uint_32: tick * 100,
+                }],
+            });
+            for i in 0..3 {
+                packets.push(Packet {
+                    id: 1,
+                    values: vec![Value {
+                        uint_32: tick * 3 + i,
+                    }],
+                });
+            }
+        }
+
+        (config, packets)
+    }
+
+    /// The rows of the table as the values of the clock and fast sensors.
+    fn rows(assembly: impl RowAssembly + 'static) -> Vec<[Option<u64>; 2]> {
+        let (config, packets) = test_data();
+        let mut table = TableGenerator::new(packets.into_iter().map(Ok), config);
+        table.set_assembly(assembly);
+
+        table
+            .map(|row| {
+                let row = row.unwrap();
+                [0, 1].map(|i| row[i].map(|value| value.to_f64() as u64))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_repeated_sensor() {
+        let rows = rows(RepeatedSensor::default());
+        assert_eq!(rows.len(), 9);
+        assert_eq!(
+            rows[..3],
+            [[Some(0), Some(0)], [None, Some(1)], [Some(100), Some(2)]]
+        );
+    }
+
+    #[test]
+    fn test_row_per_packet() {
+        let rows = rows(RowPerPacket);
+        assert_eq!(rows.len(), 12);
+        assert_eq!(rows[..2], [[Some(0), None], [None, Some(0)]]);
+    }
+
+    #[test]
+    fn test_primary_sensor() {
+        let rows = rows(PrimarySensor::new(0));
+        assert_eq!(
+            rows,
+            [
+                [Some(0), Some(2)],
+                [Some(100), Some(5)],
+                [Some(200), Some(8)]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_time_window() {
+        let (config, _) = test_data();
+        assert!(TimeWindow::new(&config, 0.0).is_err());
+
+        // Windows of 0.2 s have two ticks, except for the last.
+        let rows = rows(TimeWindow::new(&config, 0.2).unwrap());
+        assert_eq!(rows, [[Some(100), Some(5)], [Some(200), Some(8)]]);
+    }
+}
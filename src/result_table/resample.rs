@@ -0,0 +1,702 @@
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+
+use crate::analysis::filter::{Filter, FilterChain};
+use crate::configuration::{RocketConfig, ValueKind};
+use crate::data::{Packet, PacketError, TypedValue};
+use crate::time::PacketClock;
+
+use super::{float_value, RowSource, SourceIterator, TableGenerator};
+
+/// The name of the column of the time of each row, in seconds, which is the
+/// first column of a [`Resampler`].
+pub const TIME_COLUMN: &str = "time";
+
+/// How the value of a column is found at the time of a row.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResamplePolicy {
+    /// The most recent sample at or before the time of the row.
+    #[default]
+    Hold,
+    /// The samples either side of the time of the row, interpolated linearly.
+    Linear,
+    /// The sample closest to the time of the row.
+    Nearest,
+    /// The mean of the samples since the previous row, up to and including
+    /// the time of the row.
+    Mean,
+}
+
+impl FromStr for ResamplePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "hold" => Ok(Self::Hold),
+            "linear" => Ok(Self::Linear),
+            "nearest" => Ok(Self::Nearest),
+            "mean" => Ok(Self::Mean),
+            _ => Err(format!("Unknown resample policy: {s}")),
+        }
+    }
+}
+
+/// Options for resampling values to a fixed rate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResampleOptions {
+    /// The number of rows per second.
+    pub rate: f64,
+    /// The policy of columns without one in `column_policies`.
+    pub policy: ResamplePolicy,
+    /// The policies of specific columns, by column name.
+    pub column_policies: HashMap<String, ResamplePolicy>,
+    /// The longest time in seconds between a row and the samples used for it.
+    ///
+    /// Cells are empty when the sample used is further than this from the
+    /// time of the row, or for [`ResamplePolicy::Linear`], when the samples
+    /// either side of it are further apart than this. There is no limit if
+    /// this is `None`, which is only allowed without linear or nearest
+    /// columns, since their rows wait for the next sample and a sensor that
+    /// stops logging would hold every row until the end of the log.
+    pub max_staleness: Option<f64>,
+}
+
+impl Default for ResampleOptions {
+    fn default() -> Self {
+        Self {
+            rate: 100.0,
+            policy: ResamplePolicy::default(),
+            column_policies: HashMap::new(),
+            max_staleness: None,
+        }
+    }
+}
+
+impl ResampleOptions {
+    /// The policy used for a column.
+    pub fn policy_of(&self, column: &str) -> ResamplePolicy {
+        self.column_policies
+            .get(column)
+            .copied()
+            .unwrap_or(self.policy)
+    }
+
+    fn is_fresh(&self, distance: f64) -> bool {
+        self.max_staleness.is_none_or(|max| distance <= max)
+    }
+}
+
+/// A value of a column at a time.
+type Sample = (f64, TypedValue);
+
+/// The state of a value column.
+struct Column {
+    policy: ResamplePolicy,
+    filter: Option<FilterChain>,
+    /// The most recent sample.
+    last: Option<Sample>,
+    /// The sum and number of samples since the previous row, for
+    /// [`ResamplePolicy::Mean`].
+    sum: f64,
+    count: u32,
+    /// The number of pending rows at the end of the queue waiting for the
+    /// next sample.
+    waiting: usize,
+}
+
+enum Cell {
+    Done(Option<TypedValue>),
+    /// Waiting for the next sample of the column, with the sample before the
+    /// time of the row.
+    Waiting(Option<Sample>),
+}
+
+struct PendingRow {
+    time: f64,
+    cells: Vec<Cell>,
+}
+
+/// Iterator that generates table rows at a fixed rate from data provided.
+///
+/// Sensors log at different rates, so unlike the [`TableGenerator`], every
+/// row is at a time on a uniform grid and every column has a value found with
+/// its [`ResamplePolicy`]. The first column is the time of the row, named
+/// [`TIME_COLUMN`], followed by the value columns in the same order as the
+/// [`TableGenerator`]. Values are filtered before they are resampled, and
+/// interpolated values and means are given as 64 bit floats.
+///
+/// The grid is aligned to multiples of the period, starting from the first
+/// time read. Packets before the first time are ignored, as are packets read
+/// after the clock goes backwards until it passes the latest time again. Rows with linear or nearest columns are held
+/// until those columns have their next sample or the
+/// [`ResampleOptions::max_staleness`] has passed.
+pub struct Resampler<I: SourceIterator> {
+    iter: I,
+    config: RocketConfig,
+    clock: PacketClock,
+    options: ResampleOptions,
+    columns: Vec<String>,
+    states: Vec<Column>,
+    /// The index of the first column of each sensor, by sensor ID.
+    offsets: HashMap<u8, usize>,
+    /// The index on the grid of the next row to create.
+    next_index: Option<i64>,
+    /// The time of the most recent packet used.
+    last_time: Option<f64>,
+    pending: VecDeque<PendingRow>,
+    finished: bool,
+}
+
+impl<I: SourceIterator> Resampler<I> {
+    /// Create a new resampler given a Packet iterator and a rocket
+    /// configuration.
+    ///
+    /// # Errors
+    ///
+    /// If the config has no time values, the rate is not positive, a column
+    /// policy is given for a column that does not exist, or a column is linear
+    /// or nearest without a max staleness.
+    pub fn new(iter: I, config: RocketConfig, options: ResampleOptions) -> Result<Self, String> {
+        let clock = PacketClock::new(&config);
+        if !clock.has_source() {
+            return Err("The config has no values with the time role".to_string());
+        }
+        if !options.rate.is_finite() || options.rate <= 0.0 {
+            return Err(format!("The rate must be positive: {}", options.rate));
+        }
+        if options
+            .max_staleness
+            .is_some_and(|max| max.is_nan() || max < 0.0)
+        {
+            return Err("The max staleness must not be negative".to_string());
+        }
+
+        let mut columns = vec![];
+        let mut states = vec![];
+        let mut offsets = HashMap::new();
+        for sensor in config.sensors.iter() {
+            offsets.insert(sensor.id, columns.len());
+
+            for value in sensor.values.iter() {
+                let name = TableGenerator::<I>::column_name(sensor, value);
+                let filter = match value.filters.is_empty() {
+                    true => None,
+                    false => Some(FilterChain::new(&value.filters)),
+                };
+
+                states.push(Column {
+                    policy: options.policy_of(&name),
+                    filter,
+                    last: None,
+                    sum: 0.0,
+                    count: 0,
+                    waiting: 0,
+                });
+                columns.push(name);
+            }
+        }
+
+        if let Some(name) = options
+            .column_policies
+            .keys()
+            .find(|c| !columns.contains(c))
+        {
+            return Err(format!("No column named {name}"));
+        }
+
+        if options.max_staleness.is_none() {
+            let waits = |column: &Column| {
+                matches!(
+                    column.policy,
+                    ResamplePolicy::Linear | ResamplePolicy::Nearest
+                )
+            };
+            if let Some(index) = states.iter().position(waits) {
+                return Err(format!(
+                    "The column {} needs a max staleness to resample it with the {:?} policy",
+                    columns[index], states[index].policy
+                ));
+            }
+        }
+
+        Ok(Self {
+            iter,
+            config,
+            clock,
+            options,
+            columns,
+            states,
+            offsets,
+            next_index: None,
+            last_time: None,
+            pending: VecDeque::new(),
+            finished: false,
+        })
+    }
+
+    /// The names of the columns, starting with [`TIME_COLUMN`].
+    pub fn column_names(&self) -> Vec<String> {
+        let mut names = vec![TIME_COLUMN.to_string()];
+        names.extend(self.columns.iter().cloned());
+        names
+    }
+
+    /// The options used for resampling.
+    pub fn options(&self) -> &ResampleOptions {
+        &self.options
+    }
+
+    fn grid_time(&self, index: i64) -> f64 {
+        index as f64 / self.options.rate
+    }
+
+    fn add_packet(&mut self, packet: Packet) -> Result<(), PacketError> {
+        let Some(sensor) = self.config.get_sensor_by_id(packet.id) else {
+            return Err(PacketError::InvalidId(packet.id));
+        };
+        if packet.values.len() != sensor.values.len() {
+            return Err(PacketError::InvalidValueCount {
+                expected: sensor.values.len(),
+                actual: packet.values.len(),
+            });
+        }
+        let kinds: Vec<ValueKind> = sensor.values.iter().map(|v| v.data_type).collect();
+
+        let Some(time) = self.clock.update(&packet) else {
+            return Ok(());
+        };
+        if self.last_time.is_some_and(|last| time < last) {
+            return Ok(());
+        }
+        self.last_time = Some(time);
+
+        self.create_rows(|row_time| row_time < time);
+
+        // Rows that are too old for the next sample to be used are finished
+        // without it.
+        if let Some(max) = self.options.max_staleness {
+            let stale = self
+                .pending
+                .iter_mut()
+                .take_while(|row| time - row.time > max);
+            for row in stale {
+                for (cell, column) in row.cells.iter_mut().zip(self.states.iter_mut()) {
+                    if let Cell::Waiting(before) = cell {
+                        let value = resolve(column.policy, *before, None, row.time, &self.options);
+                        *cell = Cell::Done(value);
+                        column.waiting -= 1;
+                    }
+                }
+            }
+        }
+
+        let offset = self.offsets[&packet.id];
+        for (i, (kind, value)) in kinds.iter().zip(packet.values.iter()).enumerate() {
+            // The safety is assumed by the packet matching the config.
+            let typed_value = unsafe { TypedValue::new(*value, kind) };
+            let typed_value = match self.states[offset + i].filter.as_mut() {
+                Some(chain) => float_value(chain.apply(typed_value.to_f64())),
+                None => typed_value,
+            };
+
+            self.resolve_waiting(offset + i, Some((time, typed_value)));
+
+            let column = &mut self.states[offset + i];
+            column.last = Some((time, typed_value));
+            column.sum += typed_value.to_f64();
+            column.count += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Finish the rows waiting for the next sample of a column.
+    fn resolve_waiting(&mut self, index: usize, after: Option<Sample>) {
+        let column = &mut self.states[index];
+        let start = self.pending.len() - column.waiting;
+
+        for row in self.pending.range_mut(start..) {
+            if let Cell::Waiting(before) = row.cells[index] {
+                let value = resolve(column.policy, before, after, row.time, &self.options);
+                row.cells[index] = Cell::Done(value);
+            }
+        }
+
+        column.waiting = 0;
+    }
+
+    /// Create the rows on the grid from the next index while their times
+    /// match the predicate.
+    fn create_rows(&mut self, predicate: impl Fn(f64) -> bool) {
+        let Some(last_time) = self.last_time else {
+            return;
+        };
+        let rate = self.options.rate;
+        let mut index = *self
+            .next_index
+            .get_or_insert_with(|| (last_time * rate).floor() as i64);
+
+        while predicate(self.grid_time(index)) {
+            let time = self.grid_time(index);
+            let cells = self
+                .states
+                .iter_mut()
+                .map(|column| column.cell_at(time, &self.options))
+                .collect();
+
+            self.pending.push_back(PendingRow { time, cells });
+            index += 1;
+        }
+
+        self.next_index = Some(index);
+    }
+
+    /// Create the rows up to the last time read and finish them without any
+    /// more samples.
+    fn finish(&mut self) {
+        if let Some(last_time) = self.last_time {
+            self.create_rows(|row_time| row_time <= last_time);
+        }
+
+        for index in 0..self.states.len() {
+            self.resolve_waiting(index, None);
+        }
+
+        self.finished = true;
+    }
+
+    /// Take the first pending row if all of its values are known.
+    fn ready_row(&mut self) -> Option<Vec<Option<TypedValue>>> {
+        let row = self.pending.front()?;
+        if row
+            .cells
+            .iter()
+            .any(|cell| matches!(cell, Cell::Waiting(_)))
+        {
+            return None;
+        }
+
+        let row = self.pending.pop_front()?;
+        let mut result = vec![Some(float_value(row.time))];
+        result.extend(row.cells.into_iter().map(|cell| match cell {
+            Cell::Done(value) => value,
+            Cell::Waiting(_) => None,
+        }));
+
+        Some(result)
+    }
+}
+
+impl Column {
+    /// The cell of a new row, which is waiting for the next sample if the
+    /// policy needs it.
+    fn cell_at(&mut self, time: f64, options: &ResampleOptions) -> Cell {
+        match self.policy {
+            ResamplePolicy::Hold => Cell::Done(
+                self.last
+                    .filter(|(t, _)| options.is_fresh(time - t))
+                    .map(|(_, value)| value),
+            ),
+            ResamplePolicy::Mean => {
+                let mean = (self.count > 0).then(|| float_value(self.sum / self.count as f64));
+                self.sum = 0.0;
+                self.count = 0;
+                Cell::Done(mean)
+            }
+            ResamplePolicy::Linear | ResamplePolicy::Nearest => match self.last {
+                Some((t, value)) if t == time => Cell::Done(Some(value)),
+                last => {
+                    self.waiting += 1;
+                    Cell::Waiting(last)
+                }
+            },
+        }
+    }
+}
+
+/// Find the value of a linear or nearest column from the samples either side
+/// of the time of the row.
+fn resolve(
+    policy: ResamplePolicy,
+    before: Option<Sample>,
+    after: Option<Sample>,
+    time: f64,
+    options: &ResampleOptions,
+) -> Option<TypedValue> {
+    match policy {
+        ResamplePolicy::Linear => {
+            let ((t0, v0), (t1, v1)) = (before?, after?);
+            if !options.is_fresh(t1 - t0) {
+                return None;
+            }
+
+            let (v0, v1) = (v0.to_f64(), v1.to_f64());
+            Some(float_value(v0 + (v1 - v0) * (time - t0) / (t1 - t0)))
+        }
+        _ => {
+            let nearest = match (before, after) {
+                (Some(b), Some(a)) if a.0 - time < time - b.0 => a,
+                (Some(b), _) => b,
+                (None, a) => a?,
+            };
+
+            options
+                .is_fresh((nearest.0 - time).abs())
+                .then_some(nearest.1)
+        }
+    }
+}
+
+impl<I: SourceIterator> Iterator for Resampler<I> {
+    type Item = Result<Vec<Option<TypedValue>>, PacketError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.ready_row() {
+                return Some(Ok(row));
+            }
+            if self.finished {
+                return None;
+            }
+
+            match self.iter.next() {
+                Some(Ok(packet)) => {
+                    if let Err(err) = self.add_packet(packet) {
+                        return Some(Err(err));
+                    }
+                }
+                Some(Err(err)) => return Some(Err(err)),
+                None => self.finish(),
+            }
+        }
+    }
+}
+
+impl<I: SourceIterator> RowSource for Resampler<I> {
+    fn column_names(&self) -> Vec<String> {
+        Resampler::column_names(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::{Axis, Endianess, SensorConfig, ValueConfig, ValueKind, ValueRole};
+    use crate::data::Value;
+
+    use super::*;
+
+    /// A clock at 100 Hz with a fast sensor, and a slow sensor every 50 ms.
+    fn test_config() -> RocketConfig {
+        let value = |name: &str, data_type, role: Option<ValueRole>| ValueConfig {
+            name: name.to_string(),
+            data_type,
+            unit: role.map(|_| "ms".to_string()),
+            role,
+            filters: vec![],
+        };
+
+        RocketConfig {
+            name: "test".to_string(),
+            endianess: Endianess::default(),
+            nose_axis: Axis::default(),
+            include: vec![],
+            display_name: None,
+            description: None,
+            kalman: None,
+            sensors: vec![
+                SensorConfig {
+                    id: 0,
+                    name: "clock".to_string(),
+                    values: vec![value("time", ValueKind::UInt32, Some(ValueRole::Time))],
+                },
+                SensorConfig {
+                    id: 1,
+                    name: "slow".to_string(),
+                    values: vec![value("x", ValueKind::Float64, None)],
+                },
+            ],
+        }
+    }
+
+    /// Packets from 0 ms to 100 ms, where the slow value is the time in ms.
+    fn test_packets() -> Vec<Result<Packet, PacketError>> {
+        let mut packets = vec![];
+        for ms in (0..=100u32).step_by(10) {
+            packets.push(Ok(Packet {
+                id: 0,
+                values: vec![Value { uint_32: ms }],
+            }));
+            if ms % 50 == 0 {
+                packets.push(Ok(Packet {
+                    id: 1,
+                    values: vec![Value {
+                        float_64: ms as f64,
+                    }],
+                }));
+            }
+        }
+        packets
+    }
+
+    fn resample(options: ResampleOptions) -> Vec<Vec<Option<f64>>> {
+        Resampler::new(test_packets().into_iter(), test_config(), options)
+            .unwrap()
+            .map(|row| row.unwrap().iter().map(|v| v.map(|v| v.to_f64())).collect())
+            .collect()
+    }
+
+    fn slow_column(rows: &[Vec<Option<f64>>]) -> Vec<Option<f64>> {
+        rows.iter().map(|row| row[2]).collect()
+    }
+
+    #[test]
+    fn test_policies() {
+        let options = |policy| ResampleOptions {
+            rate: 50.0,
+            policy,
+            max_staleness: Some(1.0),
+            ..Default::default()
+        };
+
+        let rows = resample(options(ResamplePolicy::Hold));
+        let times: Vec<f64> = rows.iter().map(|row| row[0].unwrap()).collect();
+        assert_eq!(times.len(), 6);
+        for (i, time) in times.iter().enumerate() {
+            assert!((time - i as f64 * 0.02).abs() < 1e-9);
+        }
+        assert_eq!(
+            slow_column(&rows),
+            [0.0, 0.0, 0.0, 50.0, 50.0, 100.0].map(Some)
+        );
+
+        let linear = slow_column(&resample(options(ResamplePolicy::Linear)));
+        for (value, expected) in linear.iter().zip([0.0, 20.0, 40.0, 60.0, 80.0, 100.0]) {
+            assert!((value.unwrap() - expected).abs() < 1e-6);
+        }
+
+        let nearest = slow_column(&resample(options(ResamplePolicy::Nearest)));
+        assert_eq!(nearest, [0.0, 0.0, 50.0, 50.0, 100.0, 100.0].map(Some));
+
+        let mean = resample(options(ResamplePolicy::Mean));
+        assert_eq!(
+            slow_column(&mean),
+            [Some(0.0), None, None, Some(50.0), None, Some(100.0)]
+        );
+        assert_eq!(mean[1][1], Some(15.0));
+    }
+
+    #[test]
+    fn test_max_staleness() {
+        let mut options = ResampleOptions {
+            rate: 50.0,
+            max_staleness: Some(0.025),
+            ..Default::default()
+        };
+        options
+            .column_policies
+            .insert("slow_x".to_string(), ResamplePolicy::Hold);
+
+        let rows = resample(options.clone());
+        assert_eq!(
+            slow_column(&rows),
+            [Some(0.0), Some(0.0), None, Some(50.0), None, Some(100.0)]
+        );
+
+        // The slow samples are further apart than the limit.
+        options
+            .column_policies
+            .insert("slow_x".to_string(), ResamplePolicy::Linear);
+        let rows = resample(options);
+        assert_eq!(
+            slow_column(&rows),
+            [Some(0.0), None, None, None, None, Some(100.0)]
+        );
+    }
+
+    #[test]
+    fn test_clock_reset() {
+        let clock = |ms| Packet {
+            id: 0,
+            values: vec![Value { uint_32: ms }],
+        };
+        let slow = |x| Packet {
+            id: 1,
+            values: vec![Value { float_64: x }],
+        };
+        let packets = vec![
+            clock(0),
+            slow(1.0),
+            clock(20),
+            // The clock resets, and the packets until it passes 20 ms again
+            // are ignored.
+            clock(5),
+            slow(2.0),
+            clock(25),
+            slow(3.0),
+            clock(40),
+        ];
+        let options = ResampleOptions {
+            rate: 50.0,
+            ..Default::default()
+        };
+
+        let rows: Vec<Vec<Option<f64>>> =
+            Resampler::new(packets.into_iter().map(Ok), test_config(), options)
+                .unwrap()
+                .map(|row| row.unwrap().iter().map(|v| v.map(|v| v.to_f64())).collect())
+                .collect();
+
+        assert_eq!(slow_column(&rows), [Some(1.0), Some(1.0), Some(3.0)]);
+    }
+
+    #[test]
+    fn test_sensor_that_never_logs() {
+        let packets = test_packets()
+            .into_iter()
+            .filter(|p| p.as_ref().unwrap().id == 0);
+        let options = ResampleOptions {
+            rate: 50.0,
+            policy: ResamplePolicy::Linear,
+            max_staleness: Some(0.025),
+            ..Default::default()
+        };
+        let mut resampler = Resampler::new(packets, test_config(), options).unwrap();
+
+        // Rows are given once they are stale instead of at the end of the log.
+        let row = resampler.next().unwrap().unwrap();
+        assert_eq!(row[2], None);
+        assert!(resampler.pending.len() <= 2);
+        assert!(!resampler.finished);
+
+        let rows: Vec<_> = resampler.map(|row| row.unwrap()).collect();
+        assert_eq!(rows.len(), 5);
+        assert!(rows.iter().all(|row| row[2].is_none()));
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let resampler =
+            |options| Resampler::new(test_packets().into_iter(), test_config(), options);
+
+        assert!(resampler(ResampleOptions {
+            rate: 0.0,
+            ..Default::default()
+        })
+        .is_err());
+
+        let mut options = ResampleOptions::default();
+        options
+            .column_policies
+            .insert("missing".to_string(), ResamplePolicy::Mean);
+        assert!(resampler(options).is_err());
+
+        assert!(resampler(ResampleOptions {
+            policy: ResamplePolicy::Nearest,
+            ..Default::default()
+        })
+        .is_err());
+
+        assert_eq!("Linear".parse(), Ok(ResamplePolicy::Linear));
+        assert!("cubic".parse::<ResamplePolicy>().is_err());
+    }
+}
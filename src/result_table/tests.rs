@@ -1,14 +1,19 @@
 use super::*;
 
-use crate::configuration::ValueKind;
+use crate::analysis::altitude::AltitudeEstimator;
+use crate::analysis::kalman::KalmanFilter;
+use crate::configuration::{ValueKind, ValueRole};
 use crate::data::Value;
 
 fn test_config() -> RocketConfig {
     RocketConfig {
         name: "test".to_string(),
         endianess: crate::configuration::Endianess::default(),
+        nose_axis: crate::configuration::Axis::default(),
+        include: vec![],
         display_name: None,
         description: None,
+        kalman: None,
         sensors: vec![SensorConfig {
             id: 0,
             name: "test".to_string(),
@@ -16,10 +21,16 @@ fn test_config() -> RocketConfig {
                 ValueConfig {
                     name: "value".to_string(),
                     data_type: ValueKind::Float32,
+                    unit: None,
+                    role: None,
+                    filters: vec![],
                 },
                 ValueConfig {
                     name: "value2".to_string(),
                     data_type: ValueKind::Int32,
+                    unit: None,
+                    role: None,
+                    filters: vec![],
                 },
             ],
         }],
@@ -75,3 +86,159 @@ fn test_column_restriction() {
     assert_eq!(table.next().unwrap().unwrap(), vec![Some(3_i32.into())]);
     assert_eq!(table.next().is_none(), true);
 }
+
+/// A config with a pressure and a time, and two packets on the ground.
+fn pressure_packets() -> (RocketConfig, Vec<Packet>) {
+    let mut config = test_config();
+    config.sensors[0].values[0].role = Some(ValueRole::Pressure);
+    config.sensors[0].values[1].role = Some(ValueRole::Time);
+
+    let packets = vec![
+        Packet {
+            id: 0,
+            values: vec![
+                Value {
+                    float_32: 101_325.0,
+                },
+                Value { int_32: 0 },
+            ],
+        },
+        Packet {
+            id: 0,
+            values: vec![
+                Value {
+                    float_32: 101_325.0,
+                },
+                Value { int_32: 100 },
+            ],
+        },
+    ];
+
+    (config, packets)
+}
+
+#[test]
+fn test_altitude_columns() {
+    let (config, packets) = pressure_packets();
+    let estimator = AltitudeEstimator::new(&config, Default::default()).unwrap();
+    let mut table = TableGenerator::new(packets.into_iter().map(Ok), config);
+    table.add_altitude(estimator);
+
+    assert_eq!(
+        table.column_names(),
+        vec![
+            "test_value",
+            "test_value2",
+            ALTITUDE_COLUMN,
+            VERTICAL_VELOCITY_COLUMN
+        ]
+    );
+    for _ in 0..2 {
+        let row = table.next().unwrap().unwrap();
+        assert_eq!(row[2].unwrap().kind(), ValueKind::Float64);
+        assert_eq!(row[2].unwrap().to_f64(), 0.0);
+        assert_eq!(row[3].unwrap().to_f64(), 0.0);
+    }
+    assert!(table.next().is_none());
+}
+
+#[test]
+fn test_kalman_columns() {
+    let (config, packets) = pressure_packets();
+    let filter = KalmanFilter::new(&config, Default::default()).unwrap();
+    let mut table = TableGenerator::new(packets.into_iter().map(Ok), config);
+    table.add_kalman(filter);
+
+    assert_eq!(table.column_names()[2..], KALMAN_COLUMNS);
+    for _ in 0..2 {
+        let row = table.next().unwrap().unwrap();
+        assert!(row[2..].iter().all(|value| value.is_some()));
+        assert!(row[2].unwrap().to_f64().abs() < 1e-6);
+    }
+    assert!(table.next().is_none());
+}
+
+#[test]
+fn test_value_filters() {
+    let mut config = test_config();
+    config.sensors[0].values[0].filters = vec!["moving_average,window=2".parse().unwrap()];
+
+    let packets = [1.0, 3.0, 5.0].map(|float_32| Packet {
+        id: 0,
+        values: vec![Value { float_32 }, Value { int_32: 0 }],
+    });
+    let table = TableGenerator::new(packets.into_iter().map(Ok), config);
+
+    let filtered: Vec<f64> = table.map(|row| row.unwrap()[0].unwrap().to_f64()).collect();
+    assert_eq!(filtered, [1.0, 2.0, 4.0]);
+}
+
+#[test]
+fn test_fill_policy() {
+    let mut config = test_config();
+    config.sensors.push(SensorConfig {
+        id: 1,
+        name: "fast".to_string(),
+        values: vec![ValueConfig {
+            name: "value".to_string(),
+            data_type: ValueKind::Int32,
+            unit: None,
+            role: None,
+            filters: vec![],
+        }],
+    });
+
+    // The fast sensor has its own row three times before the next packet of
+    // the slow one.
+    let mut packets = vec![test_packets()[0].clone()];
+    for int_32 in [10, 20, 30, 40] {
+        packets.push(Packet {
+            id: 1,
+            values: vec![Value { int_32 }],
+        });
+    }
+    packets.push(test_packets()[1].clone());
+
+    let first_column = |fill| {
+        let mut table = TableGenerator::new(packets.clone().into_iter().map(Ok), config.clone());
+        table.set_fill_policy(fill);
+        table
+            .map(|row| row.unwrap()[1].map(|value| value.to_f64()))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        first_column(FillPolicy::None),
+        [Some(1.0), None, None, Some(2.0)]
+    );
+    assert_eq!(
+        first_column(FillPolicy::ForwardFill),
+        [Some(1.0), Some(1.0), Some(1.0), Some(2.0)]
+    );
+    assert_eq!(
+        first_column(FillPolicy::ForwardFillMaxAge(1)),
+        [Some(1.0), Some(1.0), None, Some(2.0)]
+    );
+
+    let mut table = TableGenerator::new(packets.into_iter().map(Ok), config);
+    table.set_fill_policy(FillPolicy::ForwardFill);
+    table.add_updated_columns();
+    assert_eq!(
+        table.column_names()[3..],
+        [
+            "test_value_updated",
+            "test_value2_updated",
+            "fast_value_updated"
+        ]
+    );
+
+    let updated: Vec<u64> = table
+        .nth(1)
+        .unwrap()
+        .unwrap()
+        .iter()
+        .skip(3)
+        .map(|value| value.unwrap().to_f64() as u64)
+        .collect();
+    assert_eq!(updated, [0, 0, 1]);
+}
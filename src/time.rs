@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use crate::configuration::{RocketConfig, ValueKind, ValueRole};
+use crate::data::{Packet, TypedValue};
+
+/// Tracks the time of packets in a stream.
+///
+/// The time comes from values with the [`ValueRole::Time`] role. Packets from
+/// sensors without a time value are given the most recent time that was read,
+/// since the flight computer logs packets in the order they are recorded.
+pub struct PacketClock {
+    /// The time values of each sensor, by sensor ID, with the index of the
+    /// value, its kind and the number of seconds in one unit.
+    sources: HashMap<u8, (usize, ValueKind, f64)>,
+    /// The most recent time read, in seconds.
+    current: Option<f64>,
+}
+
+impl PacketClock {
+    /// Create a new clock for packets using the given rocket configuration.
+    ///
+    /// Time values with an unknown unit are ignored, see
+    /// [`RocketConfig::validate`].
+    pub fn new(config: &RocketConfig) -> Self {
+        let mut sources = HashMap::new();
+
+        for sensor in config.sensors.iter() {
+            let time_value = sensor
+                .values
+                .iter()
+                .enumerate()
+                .find(|(_, v)| v.role == Some(ValueRole::Time));
+
+            let Some((index, value)) = time_value else {
+                continue;
+            };
+            let Some(scale) = value.seconds_per_unit() else {
+                continue;
+            };
+
+            sources.insert(sensor.id, (index, value.data_type, scale));
+        }
+
+        Self {
+            sources,
+            current: None,
+        }
+    }
+
+    /// Whether the configuration has any time values.
+    ///
+    /// If this is false, [`PacketClock::update`] always returns `None`.
+    pub fn has_source(&self) -> bool {
+        !self.sources.is_empty()
+    }
+
+    /// Update the clock with the next packet in the stream.
+    ///
+    /// # Returns
+    ///
+    /// The time of the packet in seconds, or `None` if no time has been read
+    /// yet.
+    pub fn update(&mut self, packet: &Packet) -> Option<f64> {
+        if let Some((index, kind, scale)) = self.sources.get(&packet.id) {
+            if let Some(value) = packet.values.get(*index) {
+                // The safety is assumed by the packet matching the config.
+                let value = unsafe { TypedValue::new(*value, kind) };
+                self.current = Some(value.to_f64() * scale);
+            }
+        }
+
+        self.current
+    }
+
+    /// The most recent time read, in seconds.
+    pub fn now(&self) -> Option<f64> {
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::{Axis, Endianess, SensorConfig, ValueConfig};
+    use crate::data::Value;
+
+    use super::*;
+
+    #[test]
+    fn test_packet_clock() {
+        let config = RocketConfig {
+            name: "test".to_string(),
+            endianess: Endianess::default(),
+            nose_axis: Axis::default(),
+            include: vec![],
+            display_name: None,
+            description: None,
+            kalman: None,
+            sensors: vec![
+                SensorConfig {
+                    id: 0,
+                    name: "clock".to_string(),
+                    values: vec![ValueConfig {
+                        name: "time".to_string(),
+                        data_type: ValueKind::UInt32,
+                        unit: Some("us".to_string()),
+                        role: Some(ValueRole::Time),
+                        filters: vec![],
+                    }],
+                },
+                SensorConfig {
+                    id: 1,
+                    name: "other".to_string(),
+                    values: vec![],
+                },
+            ],
+        };
+
+        let mut clock = PacketClock::new(&config);
+        assert!(clock.has_source());
+
+        let other = Packet {
+            id: 1,
+            values: vec![],
+        };
+        let time = Packet {
+            id: 0,
+            values: vec![Value { uint_32: 1_500_000 }],
+        };
+
+        assert_eq!(clock.update(&other), None);
+        assert_eq!(clock.update(&time), Some(1.5));
+        assert_eq!(clock.update(&other), Some(1.5));
+        assert_eq!(clock.now(), Some(1.5));
+    }
+}
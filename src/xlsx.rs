@@ -0,0 +1,350 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::io::Write;
+
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+
+use crate::analysis::filter::{Filter, FilterChain};
+use crate::configuration::{RocketConfig, ValueConfig, ValueKind};
+use crate::data::{Packet, PacketError, TypedValue};
+use crate::report::Report;
+use crate::result_table::{float_value, SourceIterator};
+use crate::time::PacketClock;
+
+/// The name of the sheet with the statistics of every value.
+pub const SUMMARY_SHEET_NAME: &str = "Summary";
+
+/// The longest name Excel accepts for a worksheet.
+const MAX_SHEET_NAME_LENGTH: usize = 31;
+
+/// Integers with a larger magnitude than this can't be stored exactly as a
+/// number in a cell.
+const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// Writer for Excel workbooks.
+///
+/// The first sheet is a summary with the count, minimum and maximum of every
+/// value, as calculated by [`Report`]. It is followed by one sheet per sensor,
+/// with a row for every packet and a column for every value. If the config has
+/// a time value, the first column of every sensor sheet is the time of the
+/// packet in seconds. Values with filters in the config are filtered in both
+/// the summary and the sensor sheets.
+///
+/// Values are written as numbers, except for 64 bit integers that are too
+/// large to be represented exactly, which are written as text. The header row
+/// of every sheet is frozen and includes the unit of each value.
+pub struct XlsxGenerator<I: SourceIterator> {
+    iter: I,
+    config: RocketConfig,
+}
+
+impl<I: SourceIterator> XlsxGenerator<I> {
+    /// Create a new workbook generator given a Packet iterator and a rocket
+    /// configuration.
+    pub fn new(iter: I, config: RocketConfig) -> Self {
+        Self { iter, config }
+    }
+
+    /// Consume the iterator and write the workbook to the given writer.
+    ///
+    /// All packets are read into memory before anything is written, because
+    /// the summary sheet comes first.
+    ///
+    /// # Errors
+    ///
+    /// The error cases are an IO error if the writer fails to write, a packet
+    /// error if the iterator fails to yield a valid packet, or a workbook
+    /// error such as a sensor having more packets than fit in a sheet.
+    pub fn write_xlsx<W: Write>(self, writer: &mut W) -> Result<(), Box<dyn Error>> {
+        let mut clock = PacketClock::new(&self.config);
+        let mut packets: Vec<(Option<f64>, Packet)> = vec![];
+
+        for packet in self.iter {
+            let packet = packet?;
+
+            let Some(sensor) = self.config.get_sensor_by_id(packet.id) else {
+                return Err(PacketError::InvalidId(packet.id).into());
+            };
+
+            if packet.values.len() != sensor.values.len() {
+                return Err(PacketError::InvalidValueCount {
+                    expected: sensor.values.len(),
+                    actual: packet.values.len(),
+                }
+                .into());
+            }
+
+            packets.push((clock.update(&packet), packet));
+        }
+
+        let report = Report::new(
+            self.config.clone(),
+            packets.iter().map(|(_, packet)| Ok(packet.clone())),
+        );
+
+        let mut workbook = Workbook::new();
+        let header_format = Format::new().set_bold();
+
+        write_summary(
+            workbook.add_worksheet(),
+            &self.config,
+            &report,
+            &header_format,
+        )?;
+
+        let mut sheet_names = HashSet::from([SUMMARY_SHEET_NAME.to_lowercase()]);
+
+        for sensor in self.config.sensors.iter() {
+            let worksheet = workbook.add_worksheet();
+            worksheet.set_name(unique_sheet_name(&sensor.name, &mut sheet_names))?;
+            worksheet.set_freeze_panes(1, 0)?;
+
+            let time_column = u16::from(clock.has_source());
+            if clock.has_source() {
+                worksheet.write_string_with_format(0, 0, "time (s)", &header_format)?;
+            }
+            for (i, value) in sensor.values.iter().enumerate() {
+                let column = time_column + i as u16;
+                worksheet.write_string_with_format(0, column, header(value), &header_format)?;
+            }
+
+            let mut filters: Vec<Option<FilterChain>> = sensor
+                .values
+                .iter()
+                .map(|value| (!value.filters.is_empty()).then(|| FilterChain::new(&value.filters)))
+                .collect();
+
+            let sensor_packets = packets.iter().filter(|(_, p)| p.id == sensor.id);
+            for (row, (time, packet)) in (1..).zip(sensor_packets) {
+                if let Some(time) = time {
+                    worksheet.write_number(row, 0, *time)?;
+                }
+
+                let values = sensor.values.iter().zip(&packet.values);
+                for (i, ((spec, value), filter)) in values.zip(filters.iter_mut()).enumerate() {
+                    let value = unsafe { TypedValue::new(*value, &spec.data_type) };
+                    let value = match filter {
+                        Some(chain) => float_value(chain.apply(value.to_f64())),
+                        None => value,
+                    };
+                    write_value(worksheet, row, time_column + i as u16, &value)?;
+                }
+            }
+        }
+
+        writer.write_all(&workbook.save_to_buffer()?)?;
+
+        Ok(())
+    }
+}
+
+/// Write the summary sheet with the statistics of every value.
+fn write_summary(
+    worksheet: &mut Worksheet,
+    config: &RocketConfig,
+    report: &Report,
+    header_format: &Format,
+) -> Result<(), XlsxError> {
+    worksheet.set_name(SUMMARY_SHEET_NAME)?;
+    worksheet.set_freeze_panes(1, 0)?;
+
+    let headers = ["Sensor", "Value", "Unit", "Count", "Min", "Max"];
+    for (column, name) in headers.iter().enumerate() {
+        worksheet.write_string_with_format(0, column as u16, *name, header_format)?;
+    }
+
+    let values = config
+        .sensors
+        .iter()
+        .flat_map(|sensor| sensor.values.iter().map(move |value| (sensor, value)));
+
+    for (row, (sensor, value)) in (1..).zip(values) {
+        worksheet.write_string(row, 0, &sensor.name)?;
+        worksheet.write_string(row, 1, &value.name)?;
+        worksheet.write_string(row, 2, value.unit.as_deref().unwrap_or_default())?;
+
+        let stats = report
+            .sensor_report(sensor.id)
+            .and_then(|sensor_report| sensor_report.value_stats(&value.name));
+
+        match stats {
+            Some(stats) => {
+                worksheet.write_number(row, 3, stats.count() as f64)?;
+                if let Some(min) = stats.min() {
+                    write_value(worksheet, row, 4, &min)?;
+                }
+                if let Some(max) = stats.max() {
+                    write_value(worksheet, row, 5, &max)?;
+                }
+            }
+            None => {
+                worksheet.write_number(row, 3, 0)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Write a single value as a number, or as text if it can't be represented
+/// exactly.
+fn write_value(
+    worksheet: &mut Worksheet,
+    row: u32,
+    column: u16,
+    value: &TypedValue,
+) -> Result<(), XlsxError> {
+    let number = value.to_f64();
+    let is_large_integer = matches!(value.kind(), ValueKind::Int64 | ValueKind::UInt64)
+        && number.abs() > MAX_EXACT_INTEGER;
+
+    if is_large_integer || !number.is_finite() {
+        worksheet.write_string(row, column, value.to_string())?;
+    } else {
+        worksheet.write_number(row, column, number)?;
+    }
+
+    Ok(())
+}
+
+/// The header of a value column, including the unit if it has one.
+fn header(value: &ValueConfig) -> String {
+    match &value.unit {
+        Some(unit) => format!("{} ({unit})", value.name),
+        None => value.name.clone(),
+    }
+}
+
+/// Make a sensor name usable as a sheet name that isn't already used.
+///
+/// Characters that Excel doesn't allow are replaced with underscores and the
+/// name is truncated to the longest length allowed. Names that are already
+/// used have a number appended.
+fn unique_sheet_name(name: &str, used: &mut HashSet<String>) -> String {
+    let base: String = name
+        .chars()
+        .map(|c| match c {
+            '[' | ']' | ':' | '*' | '?' | '/' | '\\' => '_',
+            c => c,
+        })
+        .take(MAX_SHEET_NAME_LENGTH)
+        .collect();
+    let base = if base.trim().is_empty() {
+        "Sensor".to_string()
+    } else {
+        base
+    };
+
+    let mut candidate = base.clone();
+    let mut suffix = 2;
+
+    // Excel compares sheet names without case.
+    while used.contains(&candidate.to_lowercase()) {
+        let suffix_text = format!(" ({suffix})");
+        let prefix: String = base
+            .chars()
+            .take(MAX_SHEET_NAME_LENGTH - suffix_text.len())
+            .collect();
+        candidate = format!("{prefix}{suffix_text}");
+        suffix += 1;
+    }
+
+    used.insert(candidate.to_lowercase());
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::{Axis, Endianess, SensorConfig};
+    use crate::data::Value;
+
+    use super::*;
+
+    #[test]
+    fn test_unique_sheet_name() {
+        let mut used = HashSet::from([SUMMARY_SHEET_NAME.to_lowercase()]);
+
+        assert_eq!(unique_sheet_name("BMP", &mut used), "BMP");
+        assert_eq!(unique_sheet_name("bmp", &mut used), "bmp (2)");
+        assert_eq!(unique_sheet_name("summary", &mut used), "summary (2)");
+        assert_eq!(unique_sheet_name("a/b", &mut used), "a_b");
+        assert_eq!(unique_sheet_name(&"x".repeat(40), &mut used).len(), 31);
+    }
+
+    #[test]
+    fn test_header_includes_unit() {
+        let mut value = ValueConfig {
+            name: "Pressure".to_string(),
+            data_type: ValueKind::Float32,
+            unit: Some("hPa".to_string()),
+            role: None,
+            filters: vec![],
+        };
+
+        assert_eq!(header(&value), "Pressure (hPa)");
+
+        value.unit = None;
+        assert_eq!(header(&value), "Pressure");
+    }
+
+    #[test]
+    fn test_write_xlsx() {
+        let config = RocketConfig {
+            name: "test".to_string(),
+            endianess: Endianess::default(),
+            nose_axis: Axis::default(),
+            include: vec![],
+            display_name: None,
+            description: None,
+            kalman: None,
+            sensors: vec![SensorConfig {
+                id: 0,
+                name: "BMP".to_string(),
+                values: vec![ValueConfig {
+                    name: "Pressure".to_string(),
+                    data_type: ValueKind::Float32,
+                    unit: Some("hPa".to_string()),
+                    role: None,
+                    filters: vec![],
+                }],
+            }],
+        };
+        let packets = vec![Packet {
+            id: 0,
+            values: vec![Value { float_32: 1013.25 }],
+        }];
+
+        let mut result = vec![];
+        XlsxGenerator::new(packets.into_iter().map(Ok), config)
+            .write_xlsx(&mut result)
+            .unwrap();
+
+        // An xlsx file is a zip archive.
+        assert!(result.starts_with(b"PK\x03\x04"));
+    }
+
+    #[test]
+    fn test_write_xlsx_invalid_id() {
+        let packets = vec![Packet {
+            id: 3,
+            values: vec![],
+        }];
+        let config = RocketConfig {
+            name: "test".to_string(),
+            endianess: Endianess::default(),
+            nose_axis: Axis::default(),
+            include: vec![],
+            display_name: None,
+            description: None,
+            kalman: None,
+            sensors: vec![],
+        };
+
+        let mut result = vec![];
+        let error = XlsxGenerator::new(packets.into_iter().map(Ok), config)
+            .write_xlsx(&mut result)
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), "Invalid packet id: 3");
+    }
+}